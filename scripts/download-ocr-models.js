@@ -0,0 +1,41 @@
+import { mkdir, writeFile, access } from 'node:fs/promises';
+import path from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+// Downloads the ocrs models bundled for offline card name recognition
+const MODEL_BASE_URL = 'https://ocrs-models.s3-accelerate.amazonaws.com';
+const MODELS = ['text-detection.rten', 'text-recognition.rten'];
+
+const scriptDir = path.dirname(fileURLToPath(import.meta.url));
+const targetDir = path.join(scriptDir, '..', 'src-tauri', 'resources', 'ocr');
+
+async function downloadModels() {
+    await mkdir(targetDir, { recursive: true });
+
+    for (const model of MODELS) {
+        const target = path.join(targetDir, model);
+
+        try {
+            await access(target);
+            console.log(`${model} already present, skipping`);
+            continue;
+        } catch {
+            // Not downloaded yet
+        }
+
+        console.log(`Downloading ${model}...`);
+        const response = await fetch(`${MODEL_BASE_URL}/${model}`);
+        if (!response.ok) {
+            throw new Error(`Failed to download ${model}: ${response.status}`);
+        }
+
+        const data = Buffer.from(await response.arrayBuffer());
+        await writeFile(target, data);
+        console.log(`Saved ${model} (${(data.length / 1024 / 1024).toFixed(1)} MB)`);
+    }
+}
+
+downloadModels().catch((error) => {
+    console.error(error);
+    process.exit(1);
+});
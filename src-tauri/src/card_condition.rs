@@ -0,0 +1,508 @@
+use image::{DynamicImage, GrayImage, Luma, RgbImage};
+use imageproc::hough::{detect_lines, LineDetectionOptions, PolarLine};
+use imageproc::region_labelling::{connected_components, Connectivity};
+use serde::Serialize;
+
+/// Depth of the border band checked for whitening, as fractions of the card
+/// width from the edge. The very edge is skipped, rectification often keeps a
+/// sliver of the background there.
+const EDGE_BAND: (f32, f32) = (0.005, 0.03);
+
+/// Side of the square at each corner that counts as the corner, as a fraction of the width
+const CORNER_ZONE: f32 = 0.08;
+
+/// Radius of the die-cut corner rounding, as a fraction of the width (3 mm of 63 mm)
+const CORNER_RADIUS: f32 = 0.048;
+
+/// Brightest a border may be for whitening to be measured on it. White and
+/// silver borders hide whitening.
+const MAX_DARK_BORDER_LUMA: u8 = 100;
+
+/// How much brighter than the border a pixel must be to count as whitened
+const WHITENING_CONTRAST: u8 = 70;
+
+/// How much brighter than the pixels a few steps to either side a pale pixel
+/// must be to count as a scratch
+const SCRATCH_CONTRAST: i16 = 45;
+
+/// Frame strips checked for scratches, as fractions of the card (x0, y0, x1, y1):
+/// the left and right sides between the border's edge and the art and text
+/// boxes. Art, text and symbols elsewhere are full of pale lines.
+const SCRATCH_ZONES: [(f32, f32, f32, f32); 2] = [(0.03, 0.05, 0.075, 0.95), (0.925, 0.05, 0.97, 0.95)];
+
+/// Shortest run of pale pixels that counts as a scratch, as a fraction of the card width
+const MIN_SCRATCH_LENGTH: f32 = 0.02;
+
+/// Widest a run of pale pixels may be on average, in pixels. Anything wider
+/// is glare or a highlight, not a scratch.
+const MAX_SCRATCH_WIDTH: f32 = 4.0;
+
+/// Shortest crease, as a fraction of the card width
+const MIN_CREASE_LENGTH: f32 = 0.5;
+
+/// Share of a crease line, outside the art box, that must show a light or
+/// dark ridge. Lines in the artwork rarely carry on across the frame.
+const MIN_CREASE_RIDGE_SHARE: f32 = 0.6;
+
+/// How much lighter or darker than both sides a crease ridge must be
+const CREASE_CONTRAST: i16 = 12;
+
+/// Art box of a rectified card, as fractions of the card (x0, y0, x1, y1)
+const ART_BOX: (f32, f32, f32, f32) = (0.08, 0.12, 0.92, 0.52);
+
+/// Worst score each grade tolerates, NM to HP; anything worse is DMG
+const EDGE_WHITENING_LIMITS: [f32; 4] = [0.005, 0.02, 0.05, 0.12];
+const CORNER_WEAR_LIMITS: [f32; 4] = [0.02, 0.08, 0.20, 0.40];
+// One line could still be something printed on the card, so until creases are
+// calibrated on real photos it takes two to go below LP
+const CREASE_LIMITS: [f32; 4] = [0.0, 1.0, 2.0, 3.0];
+const SCRATCH_LIMITS: [f32; 4] = [0.002, 0.01, 0.03, 0.06];
+
+/// Card condition, from best to worst
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Condition {
+    #[serde(rename = "NM")]
+    NearMint,
+    #[serde(rename = "LP")]
+    LightlyPlayed,
+    #[serde(rename = "MP")]
+    ModeratelyPlayed,
+    #[serde(rename = "HP")]
+    HeavilyPlayed,
+    #[serde(rename = "DMG")]
+    Damaged,
+}
+
+impl Condition {
+    const GRADES: [Condition; 5] = [
+        Condition::NearMint,
+        Condition::LightlyPlayed,
+        Condition::ModeratelyPlayed,
+        Condition::HeavilyPlayed,
+        Condition::Damaged,
+    ];
+
+    /// The grade of a score, given the worst score each grade tolerates
+    fn from_score(score: f32, limits: [f32; 4]) -> Condition {
+        limits
+            .iter()
+            .position(|limit| score <= *limit)
+            .map_or(Condition::Damaged, |grade| Condition::GRADES[grade])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum Defect {
+    /// Light specks along the edges of a dark border
+    EdgeWhitening,
+    /// Whitened or frayed corners
+    CornerWear,
+    /// Folds crossing the card
+    Crease,
+    /// Thin pale lines over the border and frame
+    Scratches,
+}
+
+/// What one detector measured
+#[derive(Debug, Clone, Serialize)]
+pub struct DefectEvidence {
+    pub defect: Defect,
+    /// Share of the checked pixels that are damaged, or the number of creases
+    pub score: f32,
+    /// The best grade this defect allows
+    pub grade: Condition,
+    /// Readable summary for the UI
+    pub detail: String,
+}
+
+/// Suggested grade of a card and the evidence behind it
+#[derive(Debug, Clone, Serialize)]
+pub struct ConditionEstimate {
+    /// The worst grade of any defect
+    pub grade: Condition,
+    pub evidence: Vec<DefectEvidence>,
+}
+
+/// Estimates the condition of a rectified card from its edges, corners and surface.
+///
+/// Whitening can only be seen on a dark border, so edges and corners are left
+/// out of the estimate for white, silver and borderless cards. Creases are
+/// found as long straight ridges that aren't parallel to the card's sides
+/// and carry on outside the art box, so a fold along the frame lines goes
+/// unnoticed. Scratches are only looked for on the sides of the frame.
+///
+/// # Arguments
+///
+/// * `image` - The rectified card.
+///
+/// # Returns
+///
+/// * `ConditionEstimate` - The suggested grade with per-defect evidence.
+pub fn estimate_condition(image: &DynamicImage) -> ConditionEstimate {
+    let gray = image.to_luma8();
+    let mut evidence = Vec::new();
+
+    if let Some((edge, corners)) = measure_whitening(&gray) {
+        evidence.push(DefectEvidence {
+            defect: Defect::EdgeWhitening,
+            score: edge,
+            grade: Condition::from_score(edge, EDGE_WHITENING_LIMITS),
+            detail: format!("{:.1}% of the border edge is whitened", edge * 100.0),
+        });
+
+        let worst = corners.iter().copied().fold(0.0, f32::max);
+        let worn = corners.iter().filter(|wear| **wear > CORNER_WEAR_LIMITS[0]).count();
+        evidence.push(DefectEvidence {
+            defect: Defect::CornerWear,
+            score: worst,
+            grade: Condition::from_score(worst, CORNER_WEAR_LIMITS),
+            detail: format!("{} of 4 corners worn, worst {:.1}% whitened", worn, worst * 100.0),
+        });
+    }
+
+    let creases = count_creases(&gray) as f32;
+    evidence.push(DefectEvidence {
+        defect: Defect::Crease,
+        score: creases,
+        grade: Condition::from_score(creases, CREASE_LIMITS),
+        detail: format!("{} crease lines", creases),
+    });
+
+    let scratches = measure_scratches(&image.to_rgb8(), &gray);
+    evidence.push(DefectEvidence {
+        defect: Defect::Scratches,
+        score: scratches,
+        grade: Condition::from_score(scratches, SCRATCH_LIMITS),
+        detail: format!("{:.2}% of the frame is scratched", scratches * 100.0),
+    });
+
+    let grade = evidence
+        .iter()
+        .map(|e| e.grade)
+        .max()
+        .unwrap_or(Condition::NearMint);
+    ConditionEstimate { grade, evidence }
+}
+
+/// Whitened share of the border edges and of each corner, None if the border isn't dark
+fn measure_whitening(gray: &GrayImage) -> Option<(f32, [f32; 4])> {
+    let (width, height) = gray.dimensions();
+    let band_start = (width as f32 * EDGE_BAND.0) as u32;
+    let band_end = (width as f32 * EDGE_BAND.1) as u32;
+    let corner_zone = (width as f32 * CORNER_ZONE) as u32;
+    let radius = width as f32 * CORNER_RADIUS;
+    if band_end == 0 || corner_zone * 2 >= width.min(height) {
+        return None;
+    }
+
+    let mut edge_pixels = Vec::new();
+    let mut corner_pixels: [Vec<u8>; 4] = Default::default();
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x.min(width - 1 - x), y.min(height - 1 - y));
+            let depth = dx.min(dy);
+            if depth < band_start || depth >= band_end {
+                continue;
+            }
+
+            let luma = gray.get_pixel(x, y)[0];
+            if dx >= corner_zone || dy >= corner_zone {
+                edge_pixels.push(luma);
+                continue;
+            }
+            // Outside the rounded corner is background, not card
+            let (cx, cy) = (radius - dx as f32, radius - dy as f32);
+            if cx > 0.0 && cy > 0.0 && cx * cx + cy * cy > radius * radius {
+                continue;
+            }
+            let corner = (x >= width / 2) as usize + 2 * (y >= height / 2) as usize;
+            corner_pixels[corner].push(luma);
+        }
+    }
+
+    let mut sorted = edge_pixels.clone();
+    sorted.sort_unstable();
+    let border_luma = *sorted.get(sorted.len() / 2)?;
+    if border_luma > MAX_DARK_BORDER_LUMA {
+        return None;
+    }
+
+    let threshold = border_luma.saturating_add(WHITENING_CONTRAST);
+    let whitened = |pixels: &[u8]| {
+        if pixels.is_empty() {
+            return 0.0;
+        }
+        pixels.iter().filter(|luma| **luma > threshold).count() as f32 / pixels.len() as f32
+    };
+    Some((whitened(&edge_pixels), corner_pixels.each_ref().map(|pixels| whitened(pixels))))
+}
+
+/// Long straight ridges at an angle to the card's sides
+fn count_creases(gray: &GrayImage) -> usize {
+    // Half size is plenty for lines that cross the card, and keeps the vote cheap
+    let (width, height) = gray.dimensions();
+    let small = image::imageops::resize(
+        gray,
+        (width / 2).max(1),
+        (height / 2).max(1),
+        image::imageops::FilterType::Triangle,
+    );
+    let blurred = imageproc::filter::gaussian_blur_f32(&small, 1.0);
+    let edges = imageproc::edges::canny(&blurred, 15.0, 40.0);
+
+    let options = LineDetectionOptions {
+        vote_threshold: (small.width() as f32 * MIN_CREASE_LENGTH) as u32,
+        suppression_radius: 8,
+    };
+    detect_lines(&edges, options)
+        .into_iter()
+        .filter(|line| {
+            let angle = line.angle_in_degrees % 90;
+            angle > 5 && angle < 85
+        })
+        .filter(|line| is_crease(&small, line))
+        .count()
+}
+
+/// Whether a line crosses most of the card as a ridge, lighter or darker than
+/// both of its sides, along the stretches outside the art box
+fn is_crease(gray: &GrayImage, line: &PolarLine) -> bool {
+    let (width, height) = (gray.width() as f32, gray.height() as f32);
+    let (sin, cos) = (line.angle_in_degrees as f32).to_radians().sin_cos();
+    // Points on the line are foot + t * along, the sides are 3 pixels off it
+    let foot = (line.r * cos, line.r * sin);
+    let along = (-sin, cos);
+    let side = (cos * 3.0, sin * 3.0);
+    let luma = |x: f32, y: f32| -> Option<i16> {
+        let (x, y) = (x.round(), y.round());
+        (x >= 0.0 && y >= 0.0 && x < width && y < height).then(|| gray.get_pixel(x as u32, y as u32)[0] as i16)
+    };
+
+    let margin = width * 0.02;
+    let mut length = 0.0;
+    let mut checked = 0usize;
+    let mut ridges = 0usize;
+    let reach = width.hypot(height);
+    let mut t = -reach;
+    while t <= reach {
+        let (x, y) = (foot.0 + t * along.0, foot.1 + t * along.1);
+        t += 1.0;
+        if x < margin || y < margin || x > width - margin || y > height - margin {
+            continue;
+        }
+        length += 1.0;
+        let in_art = x > width * ART_BOX.0 && x < width * ART_BOX.2 && y > height * ART_BOX.1 && y < height * ART_BOX.3;
+        if in_art {
+            continue;
+        }
+        // The vote rounds the angle to a degree, so the ridge can sit a few
+        // pixels off the line
+        let is_ridge = (-5..=5).any(|offset| {
+            let (x, y) = (x + offset as f32 * cos, y + offset as f32 * sin);
+            let (Some(center), Some(a), Some(b)) = (
+                luma(x, y),
+                luma(x - side.0, y - side.1),
+                luma(x + side.0, y + side.1),
+            ) else {
+                return false;
+            };
+            let lighter = center - a > CREASE_CONTRAST && center - b > CREASE_CONTRAST;
+            let darker = a - center > CREASE_CONTRAST && b - center > CREASE_CONTRAST;
+            lighter || darker
+        });
+        checked += 1;
+        if is_ridge {
+            ridges += 1;
+        }
+    }
+
+    length >= width * MIN_CREASE_LENGTH
+        && checked as f32 >= width * MIN_CREASE_LENGTH / 2.0
+        && ridges as f32 >= checked as f32 * MIN_CREASE_RIDGE_SHARE
+}
+
+/// Share of the frame strips covered by thin lines paler than both sides
+fn measure_scratches(rgb: &RgbImage, gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    let step = 3;
+    let min_length = (width as f32 * MIN_SCRATCH_LENGTH).max(2.0);
+
+    let mut scratched = 0u32;
+    let mut pixel_count = 0u32;
+    for (fx0, fy0, fx1, fy1) in SCRATCH_ZONES {
+        let (x0, x1) = ((width as f32 * fx0) as u32, (width as f32 * fx1) as u32);
+        let (y0, y1) = ((height as f32 * fy0) as u32, (height as f32 * fy1) as u32);
+        if x0 < step || y0 < step || x1 + step >= width || y1 + step >= height || x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+
+        // Pale pixels brighter than their neighbors on both sides, across or along
+        let mut pale = GrayImage::new(x1 - x0, y1 - y0);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                pixel_count += 1;
+                let luma = gray.get_pixel(x, y)[0] as i16;
+                if luma < 120 {
+                    continue;
+                }
+                let brighter = |a: (u32, u32), b: (u32, u32)| {
+                    luma - gray.get_pixel(a.0, a.1)[0] as i16 >= SCRATCH_CONTRAST
+                        && luma - gray.get_pixel(b.0, b.1)[0] as i16 >= SCRATCH_CONTRAST
+                };
+                if !brighter((x - step, y), (x + step, y)) && !brighter((x, y - step), (x, y + step)) {
+                    continue;
+                }
+                let pixel = rgb.get_pixel(x, y);
+                let max = pixel[0].max(pixel[1]).max(pixel[2]) as f32;
+                let min = pixel[0].min(pixel[1]).min(pixel[2]) as f32;
+                if (max - min) / max < 0.3 {
+                    pale.put_pixel(x - x0, y - y0, Luma([255]));
+                }
+            }
+        }
+
+        // Keep long thin runs; specks are noise and wide patches are glare
+        let labels = connected_components(&pale, Connectivity::Eight, Luma([0u8]));
+        let mut runs: std::collections::HashMap<u32, (u32, u32, u32, u32, u32)> = std::collections::HashMap::new();
+        for (x, y, label) in labels.enumerate_pixels() {
+            if label[0] == 0 {
+                continue;
+            }
+            let run = runs.entry(label[0]).or_insert((0, x, y, x, y));
+            *run = (run.0 + 1, run.1.min(x), run.2.min(y), run.3.max(x), run.4.max(y));
+        }
+        for (area, min_x, min_y, max_x, max_y) in runs.into_values() {
+            let length = ((max_x - min_x + 1) as f32).hypot((max_y - min_y + 1) as f32);
+            if length >= min_length && area as f32 / length <= MAX_SCRATCH_WIDTH {
+                scratched += area;
+            }
+        }
+    }
+
+    if pixel_count == 0 {
+        return 0.0;
+    }
+    scratched as f32 / pixel_count as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// A black-bordered card with blue art over a pale text box
+    fn synthetic_card() -> RgbImage {
+        RgbImage::from_fn(630, 880, |x, y| {
+            let border = !(28..602).contains(&x) || !(28..852).contains(&y);
+            if border {
+                Rgb([20, 20, 22])
+            } else if (50..580).contains(&x) && (105..458).contains(&y) {
+                Rgb([40, 70 + (y / 20) as u8, 150])
+            } else if (50..580).contains(&x) && (520..775).contains(&y) {
+                Rgb([230, 225, 215])
+            } else {
+                Rgb([60, 90, 160])
+            }
+        })
+    }
+
+    #[test]
+    fn test_clean_card_is_near_mint() {
+        let estimate = estimate_condition(&DynamicImage::ImageRgb8(synthetic_card()));
+        assert_eq!(estimate.grade, Condition::NearMint);
+        assert_eq!(estimate.evidence.len(), 4);
+    }
+
+    #[test]
+    fn test_worn_card_grades_down() {
+        let mut card = synthetic_card();
+        // Whitening along the top edge and on the top-left corner
+        for y in 4..12 {
+            for x in (30..600).step_by(2) {
+                card.put_pixel(x, y, Rgb([235, 235, 235]));
+            }
+        }
+        for y in 20..45 {
+            for x in 20..45 {
+                card.put_pixel(x, y, Rgb([235, 235, 235]));
+            }
+        }
+        // A crease from the bottom left to the top right
+        for step in 0..2000 {
+            let t = step as f32 / 2000.0;
+            let (x, y) = ((t * 629.0) as u32, (700.0 - t * 500.0) as u32);
+            for dy in 0..3 {
+                card.put_pixel(x, y + dy, Rgb([200, 200, 200]));
+            }
+        }
+
+        let estimate = estimate_condition(&DynamicImage::ImageRgb8(card));
+        let grade_of = |defect: Defect| {
+            estimate.evidence.iter().find(|e| e.defect == defect).unwrap().grade
+        };
+        assert!(grade_of(Defect::EdgeWhitening) >= Condition::LightlyPlayed);
+        assert!(grade_of(Defect::CornerWear) >= Condition::LightlyPlayed);
+        assert!(grade_of(Defect::Crease) >= Condition::LightlyPlayed);
+        assert!(estimate.grade >= Condition::LightlyPlayed);
+    }
+
+    #[test]
+    fn test_lines_in_the_art_are_not_creases() {
+        let mut card = synthetic_card();
+        // A long diagonal that stays inside the art box, like a sword or a horizon
+        for step in 0..2000 {
+            let t = step as f32 / 2000.0;
+            let (x, y) = ((60.0 + t * 510.0) as u32, (450.0 - t * 335.0) as u32);
+            for dy in 0..3 {
+                card.put_pixel(x, y + dy, Rgb([220, 220, 220]));
+            }
+        }
+
+        let estimate = estimate_condition(&DynamicImage::ImageRgb8(card));
+        let crease = estimate.evidence.iter().find(|e| e.defect == Defect::Crease).unwrap();
+        assert_eq!(crease.grade, Condition::NearMint, "{}", crease.detail);
+    }
+
+    #[test]
+    fn test_bright_art_and_glare_are_not_scratches() {
+        let mut card = synthetic_card();
+        // Thin white highlights in the art
+        for y in (120..440).step_by(15) {
+            for x in 60..570 {
+                card.put_pixel(x, y, Rgb([240, 240, 240]));
+            }
+        }
+        // Glare over the left side of the frame
+        for y in 300..420 {
+            for x in 15..90 {
+                card.put_pixel(x, y, Rgb([245, 245, 245]));
+            }
+        }
+
+        let estimate = estimate_condition(&DynamicImage::ImageRgb8(card));
+        let scratches = estimate.evidence.iter().find(|e| e.defect == Defect::Scratches).unwrap();
+        assert_eq!(scratches.grade, Condition::NearMint, "{}", scratches.detail);
+    }
+
+    #[test]
+    fn test_scratched_frame_grades_down() {
+        let mut card = synthetic_card();
+        // A thin pale scratch down the left side of the frame
+        for y in 200..420 {
+            card.put_pixel(38 + (y - 200) / 110, y, Rgb([210, 210, 210]));
+        }
+
+        let estimate = estimate_condition(&DynamicImage::ImageRgb8(card));
+        let scratches = estimate.evidence.iter().find(|e| e.defect == Defect::Scratches).unwrap();
+        assert!(scratches.grade >= Condition::LightlyPlayed, "{}", scratches.detail);
+    }
+
+    #[test]
+    fn test_condition_from_score() {
+        assert_eq!(Condition::from_score(0.0, CREASE_LIMITS), Condition::NearMint);
+        assert_eq!(Condition::from_score(1.0, CREASE_LIMITS), Condition::LightlyPlayed);
+        assert_eq!(Condition::from_score(2.0, CREASE_LIMITS), Condition::ModeratelyPlayed);
+        assert_eq!(Condition::from_score(5.0, CREASE_LIMITS), Condition::Damaged);
+        assert_eq!(serde_json::to_string(&Condition::LightlyPlayed).unwrap(), "\"LP\"");
+    }
+}
@@ -0,0 +1,394 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Rgba, RgbaImage};
+use imageproc::contours::{find_contours, BorderType as ContourBorder};
+use imageproc::distance_transform::Norm;
+use imageproc::geometric_transformations::{warp_into, Interpolation, Projection};
+use imageproc::geometry::{approximate_polygon_dp, arc_length, contour_area, convex_hull, min_area_rect};
+use imageproc::point::Point;
+
+/// Size of the rectified card image (63x88mm at 10px/mm)
+pub const CANONICAL_WIDTH: u32 = 630;
+pub const CANONICAL_HEIGHT: u32 = 880;
+
+/// Longest side of the image used for edge detection.
+/// Contours are found on a downscaled copy and mapped back to full resolution.
+const DETECTION_MAX_SIDE: u32 = 800;
+
+/// Smallest fraction of the photo a card outline may cover
+const MIN_CARD_AREA_FRACTION: f64 = 0.08;
+
+/// Largest share of a page outline one of its cards may cover. A card of a
+/// 9-pocket page covers about a tenth of it, while the art and text boxes
+/// inside a card cover a quarter or more of the card.
+const MAX_POCKET_AREA_SHARE: f32 = 0.2;
+
+/// A card outline in image coordinates, ordered top-left, top-right,
+/// bottom-right, bottom-left as seen in the upright card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardQuad {
+    pub corners: [(f32, f32); 4],
+}
+
+impl CardQuad {
+    /// Area of the quadrilateral in square pixels
+    pub fn area(&self) -> f32 {
+        let c = &self.corners;
+        let mut sum = 0.0;
+        for i in 0..4 {
+            let (x1, y1) = c[i];
+            let (x2, y2) = c[(i + 1) % 4];
+            sum += x1 * y2 - x2 * y1;
+        }
+        (sum / 2.0).abs()
+    }
+
+    /// Center of the quadrilateral
+    pub fn center(&self) -> (f32, f32) {
+        let (sx, sy) = self
+            .corners
+            .iter()
+            .fold((0.0, 0.0), |(ax, ay), (x, y)| (ax + x, ay + y));
+        (sx / 4.0, sy / 4.0)
+    }
+}
+
+/// Finds the largest card-shaped quadrilateral in a photo.
+///
+/// # Arguments
+///
+/// * `image` - The photo to search.
+///
+/// # Returns
+///
+/// * `Option<CardQuad>` - The card outline, or None if no card-shaped contour was found.
+pub fn find_card_quad(image: &DynamicImage) -> Option<CardQuad> {
+    find_card_quads(image)
+        .into_iter()
+        .max_by(|a, b| a.area().total_cmp(&b.area()))
+}
+
+/// Finds every card-shaped quadrilateral in a photo, largest first.
+/// Nested outlines (the art box inside a card) are skipped.
+pub fn find_card_quads(image: &DynamicImage) -> Vec<CardQuad> {
+    drop_nested(card_quad_candidates(image, MIN_CARD_AREA_FRACTION))
+}
+
+/// Finds the card outlines in a photo of a binder page or a spread of cards,
+/// largest first.
+///
+/// A page whose edge is roughly card-shaped would otherwise be kept as one big
+/// card with every real card nested inside it, so outlines that hold two or
+/// more pocket-sized outlines are dropped before nested ones are.
+///
+/// # Arguments
+///
+/// * `image` - The photo to search.
+/// * `min_area_fraction` - Smallest fraction of the photo one card may cover,
+///   lower than the single-card default.
+///
+/// # Returns
+///
+/// * `Vec<CardQuad>` - The card outlines.
+pub fn find_page_card_quads(image: &DynamicImage, min_area_fraction: f64) -> Vec<CardQuad> {
+    let candidates = card_quad_candidates(image, min_area_fraction);
+    let cards: Vec<CardQuad> = candidates
+        .iter()
+        .filter(|outer| {
+            let pockets = candidates
+                .iter()
+                .filter(|inner| {
+                    inner.area() <= outer.area() * MAX_POCKET_AREA_SHARE && contains_point(outer, inner.center())
+                })
+                .count();
+            pockets < 2
+        })
+        .cloned()
+        .collect();
+    drop_nested(cards)
+}
+
+/// Every card-shaped outline in a photo, largest first, nested ones included
+fn card_quad_candidates(image: &DynamicImage, min_area_fraction: f64) -> Vec<CardQuad> {
+    let (width, height) = image.dimensions();
+    if width < 16 || height < 16 {
+        return Vec::new();
+    }
+
+    let scale = (DETECTION_MAX_SIDE as f32 / width.max(height) as f32).min(1.0);
+    let small = if scale < 1.0 {
+        image.resize(
+            (width as f32 * scale) as u32,
+            (height as f32 * scale) as u32,
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        image.clone()
+    };
+
+    let edges = edge_map(&small.to_luma8());
+    let image_area = (edges.width() * edges.height()) as f64;
+
+    let contours = find_contours::<i32>(&edges);
+    let mut quads: Vec<CardQuad> = Vec::new();
+
+    for contour in &contours {
+        if contour.border_type != ContourBorder::Outer || contour.points.len() < 20 {
+            continue;
+        }
+
+        let hull = convex_hull(contour.points.clone());
+        let hull_area = contour_area(&hull);
+        if hull_area < image_area * min_area_fraction {
+            continue;
+        }
+
+        if let Some(corners) = quad_corners(&hull, hull_area) {
+            let corners = corners.map(|(x, y)| (x / scale, y / scale));
+            let quad = CardQuad {
+                corners: order_corners(corners),
+            };
+            if is_card_shaped(&quad) {
+                quads.push(quad);
+            }
+        }
+    }
+
+    quads.sort_by(|a, b| b.area().total_cmp(&a.area()));
+    quads
+}
+
+/// Drops outlines whose center lies inside a bigger outline (art box, text box).
+/// `quads` must be sorted largest first.
+fn drop_nested(quads: Vec<CardQuad>) -> Vec<CardQuad> {
+    let mut result: Vec<CardQuad> = Vec::new();
+    for quad in quads {
+        let center = quad.center();
+        if !result.iter().any(|outer| contains_point(outer, center)) {
+            result.push(quad);
+        }
+    }
+
+    result
+}
+
+/// Warps the region inside `quad` onto an upright canonical card image.
+pub fn warp_card(image: &DynamicImage, quad: &CardQuad) -> Option<DynamicImage> {
+    let to = [
+        (0.0, 0.0),
+        (CANONICAL_WIDTH as f32, 0.0),
+        (CANONICAL_WIDTH as f32, CANONICAL_HEIGHT as f32),
+        (0.0, CANONICAL_HEIGHT as f32),
+    ];
+    let projection = Projection::from_control_points(quad.corners, to)?;
+
+    let source = image.to_rgba8();
+    let mut out = RgbaImage::new(CANONICAL_WIDTH, CANONICAL_HEIGHT);
+    warp_into(
+        &source,
+        &projection,
+        Interpolation::Bilinear,
+        Rgba([0, 0, 0, 255]),
+        &mut out,
+    );
+
+    Some(DynamicImage::ImageRgba8(out))
+}
+
+/// Finds the card in a photo and returns it rectified to the canonical 63x88 shape.
+///
+/// # Arguments
+///
+/// * `image` - The photo containing a single card.
+///
+/// # Returns
+///
+/// * `Option<DynamicImage>` - The rectified card, or None if no card outline was found.
+pub fn rectify_card(image: &DynamicImage) -> Option<DynamicImage> {
+    // A tight scan (e.g. a Scryfall image) has no background to find an outline
+    // against, and the biggest quad inside it would be the art box
+    if is_tight_card_scan(image) {
+        return Some(image.resize_exact(
+            CANONICAL_WIDTH,
+            CANONICAL_HEIGHT,
+            image::imageops::FilterType::Triangle,
+        ));
+    }
+
+    let quad = find_card_quad(image)?;
+    warp_card(image, &quad)
+}
+
+/// Whether the image already has the proportions of an upright card (within 3%)
+pub fn is_tight_card_scan(image: &DynamicImage) -> bool {
+    let (width, height) = image.dimensions();
+    if height == 0 {
+        return false;
+    }
+    let ratio = width as f32 / height as f32;
+    let card_ratio = CANONICAL_WIDTH as f32 / CANONICAL_HEIGHT as f32;
+    (ratio / card_ratio - 1.0).abs() < 0.03
+}
+
+/// Blurs and edge-detects a grayscale image, closing small gaps in the outline
+fn edge_map(gray: &GrayImage) -> GrayImage {
+    let blurred = imageproc::filter::gaussian_blur_f32(gray, 1.5);
+    let edges = imageproc::edges::canny(&blurred, 20.0, 60.0);
+    imageproc::morphology::dilate(&edges, Norm::LInf, 1)
+}
+
+/// Reduces a convex hull to four corners.
+/// Rounded card corners sometimes survive the polygon approximation as extra
+/// vertices, in which case the minimum-area rectangle of the hull is used.
+fn quad_corners(hull: &[Point<i32>], hull_area: f64) -> Option<[(f32, f32); 4]> {
+    let perimeter = arc_length(hull, true);
+    let approx = approximate_polygon_dp(hull, perimeter * 0.02, true);
+
+    if approx.len() == 4 {
+        return Some([
+            (approx[0].x as f32, approx[0].y as f32),
+            (approx[1].x as f32, approx[1].y as f32),
+            (approx[2].x as f32, approx[2].y as f32),
+            (approx[3].x as f32, approx[3].y as f32),
+        ]);
+    }
+
+    let rect = min_area_rect(hull);
+    let rect_area = contour_area(&rect);
+    if rect_area > 0.0 && hull_area / rect_area > 0.90 {
+        Some(rect.map(|p| (p.x as f32, p.y as f32)))
+    } else {
+        None
+    }
+}
+
+/// Orders corners clockwise starting at the top-left of the upright card.
+/// A card lying on its side is turned so the long edges become the sides;
+/// whether it was rotated left or right is resolved later by orientation checks.
+fn order_corners(corners: [(f32, f32); 4]) -> [(f32, f32); 4] {
+    let cx = corners.iter().map(|c| c.0).sum::<f32>() / 4.0;
+    let cy = corners.iter().map(|c| c.1).sum::<f32>() / 4.0;
+
+    let mut sorted = corners;
+    sorted.sort_by(|a, b| {
+        let angle_a = (a.1 - cy).atan2(a.0 - cx);
+        let angle_b = (b.1 - cy).atan2(b.0 - cx);
+        angle_a.total_cmp(&angle_b)
+    });
+
+    // Image y grows downwards, so increasing angle is clockwise on screen.
+    // Start from the corner closest to the top-left.
+    let start = (0..4)
+        .min_by(|&i, &j| {
+            let a = sorted[i].0 + sorted[i].1;
+            let b = sorted[j].0 + sorted[j].1;
+            a.total_cmp(&b)
+        })
+        .unwrap_or(0);
+    let mut ordered = [sorted[0]; 4];
+    for (i, corner) in ordered.iter_mut().enumerate() {
+        *corner = sorted[(start + i) % 4];
+    }
+
+    let top = distance(ordered[0], ordered[1]);
+    let left = distance(ordered[0], ordered[3]);
+    if top > left {
+        // Landscape outline: make the left edge the top edge
+        ordered = [ordered[3], ordered[0], ordered[1], ordered[2]];
+    }
+
+    ordered
+}
+
+/// Checks that the outline has roughly the 63:88 proportions of a card.
+/// The range is wide because perspective foreshortens one side.
+fn is_card_shaped(quad: &CardQuad) -> bool {
+    let c = &quad.corners;
+    let width = (distance(c[0], c[1]) + distance(c[3], c[2])) / 2.0;
+    let height = (distance(c[0], c[3]) + distance(c[1], c[2])) / 2.0;
+    if height <= 0.0 {
+        return false;
+    }
+
+    let ratio = width / height;
+    (0.50..=0.95).contains(&ratio)
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Point-in-convex-quad test using edge cross products
+fn contains_point(quad: &CardQuad, point: (f32, f32)) -> bool {
+    let c = &quad.corners;
+    let mut sign = 0.0f32;
+    for i in 0..4 {
+        let (x1, y1) = c[i];
+        let (x2, y2) = c[(i + 1) % 4];
+        let cross = (x2 - x1) * (point.1 - y1) - (y2 - y1) * (point.0 - x1);
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Draws a light card-sized rectangle on a dark background
+    fn synthetic_photo(card_x: u32, card_y: u32, card_w: u32, card_h: u32) -> DynamicImage {
+        let mut img = image::RgbImage::from_pixel(800, 800, Rgb([30, 90, 40]));
+        for y in card_y..card_y + card_h {
+            for x in card_x..card_x + card_w {
+                img.put_pixel(x, y, Rgb([230, 230, 220]));
+            }
+        }
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_find_card_quad_on_background() {
+        let photo = synthetic_photo(200, 100, 315, 440);
+        let quad = find_card_quad(&photo).expect("card should be found");
+
+        let (cx, cy) = quad.center();
+        assert!((cx - 357.0).abs() < 10.0);
+        assert!((cy - 320.0).abs() < 10.0);
+        // Top-left corner comes first
+        assert!((quad.corners[0].0 - 200.0).abs() < 10.0);
+        assert!((quad.corners[0].1 - 100.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn test_rectify_card_returns_canonical_size() {
+        let photo = synthetic_photo(200, 100, 315, 440);
+        let card = rectify_card(&photo).expect("card should be rectified");
+        assert_eq!(card.dimensions(), (CANONICAL_WIDTH, CANONICAL_HEIGHT));
+    }
+
+    #[test]
+    fn test_tight_scan_is_used_as_is() {
+        let scan = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(488, 680, Rgb([20, 20, 20])));
+        assert!(is_tight_card_scan(&scan));
+        let card = rectify_card(&scan).expect("scan should be accepted");
+        assert_eq!(card.dimensions(), (CANONICAL_WIDTH, CANONICAL_HEIGHT));
+    }
+
+    #[test]
+    fn test_no_card_in_blank_image() {
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(400, 400, Rgb([50, 50, 50])));
+        assert!(find_card_quad(&photo).is_none());
+    }
+
+    #[test]
+    fn test_order_corners_turns_landscape_outline_upright() {
+        let ordered = order_corners([(0.0, 0.0), (880.0, 0.0), (880.0, 630.0), (0.0, 630.0)]);
+        let top = distance(ordered[0], ordered[1]);
+        let left = distance(ordered[0], ordered[3]);
+        assert!(top < left);
+    }
+}
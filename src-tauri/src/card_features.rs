@@ -9,6 +9,37 @@ pub struct CardFeatures {
     pub has_corner_dots: bool,
     pub is_foil: bool,
     pub phash: u64, // Perceptual hash for image comparison
+    /// Set matched from the expansion symbol, None if not recognized
+    pub set_code: Option<String>,
+    /// How sure each detector is of what it read
+    pub confidence: DetectionConfidence,
+}
+
+/// How sure each detector is of its reading of this card (0-1). 0.5 is a
+/// reading right at the detector's threshold, 0 a feature that wasn't detected.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct DetectionConfidence {
+    pub border: f32,
+    pub frame_color: f32,
+    pub frame_style: f32,
+    pub set_symbol: f32,
+}
+
+/// Confidence of a reading `margin` past the threshold that decided it: a
+/// coin flip at the threshold, certain once the margin reaches `full`
+pub fn margin_confidence(margin: f32, full: f32) -> f32 {
+    0.5 + 0.5 * (margin / full).clamp(0.0, 1.0)
+}
+
+/// A photo prepared for the feature detectors
+#[derive(Debug, Clone)]
+pub struct CardImage {
+    /// The rectified card, or the original photo if no card outline was found
+    pub image: DynamicImage,
+    /// Whether `image` was perspective-corrected to the canonical 63x88 shape
+    pub rectified: bool,
+    /// Clockwise degrees the photo was turned to make the card upright
+    pub rotation: u32,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -19,6 +50,37 @@ pub enum BorderType {
     Unknown,
 }
 
+/// One of the five colors of Magic, in WUBRG order
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ManaColor {
+    White,
+    Blue,
+    Black,
+    Red,
+    Green,
+}
+
+impl ManaColor {
+    pub const ALL: [ManaColor; 5] = [
+        ManaColor::White,
+        ManaColor::Blue,
+        ManaColor::Black,
+        ManaColor::Red,
+        ManaColor::Green,
+    ];
+
+    /// Scryfall's letter for the color
+    pub fn symbol(self) -> char {
+        match self {
+            ManaColor::White => 'w',
+            ManaColor::Blue => 'u',
+            ManaColor::Black => 'b',
+            ManaColor::Red => 'r',
+            ManaColor::Green => 'g',
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum FrameColor {
     Black,
@@ -26,21 +88,57 @@ pub enum FrameColor {
     Red,
     Green,
     White,
+    /// Multicolor, when the colors couldn't be read
     Gold,
+    /// Gold frame with the two colors in its pinlines, in WUBRG order
+    TwoColor(ManaColor, ManaColor),
+    /// Frame split between two colors, in WUBRG order
+    Hybrid(ManaColor, ManaColor),
+    /// Grey modern or brown old-frame artifact
+    Artifact,
+    /// Pale grey frame of colorless non-artifacts (Eldrazi, devoid)
+    Colorless,
+    /// Brown frame of colorless vehicles
+    Vehicle,
     Land,
     Unknown,
 }
 
+impl From<ManaColor> for FrameColor {
+    fn from(color: ManaColor) -> Self {
+        match color {
+            ManaColor::White => FrameColor::White,
+            ManaColor::Blue => FrameColor::Blue,
+            ManaColor::Black => FrameColor::Black,
+            ManaColor::Red => FrameColor::Red,
+            ManaColor::Green => FrameColor::Green,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum FrameStyle {
+    /// 1993 or 1997 frame, when the two can't be told apart
     OldFrame,
+    /// Alpha through Fifth Edition
+    OldFrame1993,
+    /// Mirage through Scourge
+    OldFrame1997,
+    /// 2003 frame (Eighth Edition through M14)
     ModernFrame,
+    /// 2015 frame (M15 onwards)
     M15Frame,
+    /// Future Sight "futureshifted" frame
+    FutureFrame,
+    /// Modern layout with a stylized showcase frame
+    ShowcaseFrame,
+    /// Art extends to the card edge
+    BorderlessFrame,
     Unknown,
 }
 
 /// Convert RGB to HSV color space
-fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+pub(crate) fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     let r = r as f32 / 255.0;
     let g = g as f32 / 255.0;
     let b = b as f32 / 255.0;
@@ -65,16 +163,29 @@ fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     (if h < 0.0 { h + 360.0 } else { h }, s, v)
 }
 
-/// Detect the border type by analyzing outer edge pixels
-pub fn detect_border_type(image: &DynamicImage) -> BorderType {
-    let (width, height) = image.dimensions();
-    
+/// Detect the border type by analyzing outer edge pixels of an unprocessed photo
+pub fn detect_border_type(image: &DynamicImage) -> (BorderType, f32) {
     // Instead of checking the absolute edge (which might be background),
     // check a strip slightly inside the image (e.g., 15% in)
     // This assumes the card is somewhat centered and fills most of the frame
-    let margin_x = (width as f32 * 0.15) as u32;
-    let margin_y = (height as f32 * 0.15) as u32;
-    let sample_width = (width as f32 * 0.05) as u32; // Sample a 5% strip
+    detect_border_type_with_margin(image, 0.15, 0.05)
+}
+
+/// Detect the border type of a rectified card.
+/// The border is the outermost ~4.5% of the card, so sample just inside the edge.
+pub fn detect_rectified_border_type(image: &DynamicImage) -> (BorderType, f32) {
+    detect_border_type_with_margin(image, 0.01, 0.025)
+}
+
+/// Detect the border type from strips `margin` in from each edge, `strip` wide
+/// (both as fractions of the image size), with how far the border's color is
+/// from the white/black threshold as the confidence
+fn detect_border_type_with_margin(image: &DynamicImage, margin: f32, strip: f32) -> (BorderType, f32) {
+    let (width, height) = image.dimensions();
+
+    let margin_x = (width as f32 * margin) as u32;
+    let margin_y = (height as f32 * margin) as u32;
+    let sample_width = ((width as f32 * strip) as u32).max(1);
     
     let mut total_r = 0u64;
     let mut total_g = 0u64;
@@ -134,7 +245,7 @@ pub fn detect_border_type(image: &DynamicImage) -> BorderType {
     }
 
     if pixel_count == 0 {
-        return BorderType::Unknown;
+        return (BorderType::Unknown, 0.0);
     }
 
     let avg_r = (total_r / pixel_count) as u8;
@@ -148,85 +259,110 @@ pub fn detect_border_type(image: &DynamicImage) -> BorderType {
     
     // If it's very bright and low saturation -> White
     if s < 0.20 && v > 0.60 {
-        BorderType::White
+        (BorderType::White, margin_confidence((0.20 - s).min(v - 0.60), 0.2))
     } else {
         // Default to Black for everything else (including dark grey/glare)
-        // This is safer for MTG cards where 90% are black border
-        BorderType::Black
+        // This is safer for MTG cards where 90% are black border. Grey from
+        // glare is close to the threshold, so it isn't trusted much.
+        (BorderType::Black, margin_confidence((s - 0.20).max(0.60 - v), 0.2))
     }
 }
 
-/// Detect frame color by analyzing the card frame pixels
-pub fn detect_frame_color(image: &DynamicImage) -> FrameColor {
+/// Detect frame color by analyzing the card frame pixels, with how sure the
+/// reading is. A hybrid frame is as sure as its sides are clear; any
+/// other color is sure when both sides of the frame read it on their own,
+/// less so when one side caught glare or shadow.
+pub fn detect_frame_color(image: &DynamicImage) -> (FrameColor, f32) {
     let (width, height) = image.dimensions();
     
     // Sample points along the left and right frame edges
-    let frame_x_left = (width as f32 * 0.05) as u32;
-    let frame_x_right = (width as f32 * 0.95) as u32;
+    // (just inside the ~4.5% border of a rectified card)
+    let frame_x_left = (width as f32 * 0.07) as u32;
+    let frame_x_right = (width as f32 * 0.93) as u32;
     let start_y = (height as f32 * 0.15) as u32;
     let end_y = (height as f32 * 0.85) as u32;
-    
-    let mut total_r = 0u64;
-    let mut total_g = 0u64;
-    let mut total_b = 0u64;
-    let mut pixel_count = 0u64;
 
-    // Sample left frame
-    for y in (start_y..end_y).step_by(10) {
-        if y < height && frame_x_left < width {
-            let pixel = image.get_pixel(frame_x_left, y);
-            total_r += pixel[0] as u64;
-            total_g += pixel[1] as u64;
-            total_b += pixel[2] as u64;
-            pixel_count += 1;
-        }
-    }
+    let (left, right) = match (
+        column_average(image, frame_x_left, start_y, end_y),
+        column_average(image, frame_x_right, start_y, end_y),
+    ) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return (FrameColor::Unknown, 0.0),
+    };
+    let left_hsv = rgb_to_hsv(left[0], left[1], left[2]);
+    let right_hsv = rgb_to_hsv(right[0], right[1], right[2]);
 
-    // Sample right frame
-    for y in (start_y..end_y).step_by(10) {
-        if y < height && frame_x_right < width {
-            let pixel = image.get_pixel(frame_x_right, y);
-            total_r += pixel[0] as u64;
-            total_g += pixel[1] as u64;
-            total_b += pixel[2] as u64;
-            pixel_count += 1;
+    // Hybrid frames are split, one color on each side. Glare turns part of a
+    // side white and shadow turns it black, so only colors that hold evenly
+    // along the whole frame count.
+    if let (Some((a, a_clarity)), Some((b, b_clarity))) = (
+        side_hybrid_color(image, frame_x_left, start_y, end_y),
+        side_hybrid_color(image, frame_x_right, start_y, end_y),
+    ) {
+        if a != b {
+            let (first, second) = color_pair(a, b);
+            return (FrameColor::Hybrid(first, second), margin_confidence(a_clarity.min(b_clarity), 1.0));
         }
     }
 
-    if pixel_count == 0 {
-        return FrameColor::Unknown;
-    }
+    // Sides that disagree otherwise: the saturated one is the frame's color,
+    // the other caught glare or shadow. With neither saturated, it can't be told.
+    let sample = match (classify_mana_color(left_hsv), classify_mana_color(right_hsv)) {
+        (Some(a), Some(b)) if a != b => match (is_saturated(left_hsv), is_saturated(right_hsv)) {
+            (true, false) => left,
+            (false, true) => right,
+            _ => return (FrameColor::Unknown, 0.0),
+        },
+        _ => [0, 1, 2].map(|i| ((left[i] as u16 + right[i] as u16) / 2) as u8),
+    };
 
-    let avg_r = (total_r / pixel_count) as u8;
-    let avg_g = (total_g / pixel_count) as u8;
-    let avg_b = (total_b / pixel_count) as u8;
+    let color = classify_frame_sample(image, sample);
+    if color == FrameColor::Unknown {
+        return (FrameColor::Unknown, 0.0);
+    }
+    let agreeing_sides = [left, right]
+        .into_iter()
+        .filter(|side| classify_frame_sample(image, *side) == color)
+        .count();
+    (color, [0.5, 0.7, 0.9][agreeing_sides])
+}
 
-    let (h, s, v) = rgb_to_hsv(avg_r, avg_g, avg_b);
+/// The frame color of an averaged frame sample. Gold samples look at the
+/// pinlines to tell two-color cards apart.
+fn classify_frame_sample(image: &DynamicImage, sample: [u8; 3]) -> FrameColor {
+    let (h, s, v) = rgb_to_hsv(sample[0], sample[1], sample[2]);
 
     // Classify color based on HSV values
     // Check in order of specificity
     if v < 0.25 {
-        // Very dark - black/artifact frame
+        // Very dark - black frame
         FrameColor::Black
+    } else if s < 0.04 && v > 0.60 && v <= 0.75 {
+        // Clearly neutral light grey, duller than paper white - colorless
+        // (Eldrazi, devoid) frame. White balance can make white frames as
+        // neutral, so anything brighter stays white.
+        FrameColor::Colorless
     } else if s < 0.20 && v > 0.60 {
-        // Low saturation, high brightness - white/colorless
+        // Low saturation, high brightness - white frame
         FrameColor::White
-    } else if h >= 200.0 && h <= 260.0 && s > 0.25 {
-        // Blue hue with decent saturation
-        FrameColor::Blue
-    } else if ((h >= 0.0 && h <= 20.0) || (h >= 345.0 && h <= 360.0)) && s > 0.25 {
-        // Red hue with decent saturation
-        FrameColor::Red
-    } else if h >= 90.0 && h <= 150.0 && s > 0.25 {
-        // Green hue with decent saturation
-        FrameColor::Green
+    } else if s < 0.20 {
+        // Mid grey, often tinted blue - modern artifact frame
+        FrameColor::Artifact
+    } else if let Some(color) = classify_mana_color((h, s, v)) {
+        // Blue, red or green hue with decent saturation
+        FrameColor::from(color)
     } else if h >= 35.0 && h <= 65.0 && s > 0.35 && v > 0.40 {
-        // Yellow/gold - multicolor cards
-        FrameColor::Gold
+        // Yellow/gold - multicolor cards, two-color ones have colored pinlines
+        match detect_pinline_colors(image) {
+            Some((first, second)) => FrameColor::TwoColor(first, second),
+            None => FrameColor::Gold,
+        }
+    } else if (15.0..=35.0).contains(&h) && s > 0.50 && v < 0.55 {
+        // Dark, saturated leather brown - vehicle frame
+        FrameColor::Vehicle
     } else if h >= 15.0 && h <= 45.0 && s > 0.15 && s < 0.50 && v > 0.25 && v < 0.65 {
-        // Brown/tan - artifacts and lands (old frame)
-        // This is the tricky one - artifacts have a brownish frame
-        FrameColor::Black  // Treat as artifact/colorless
+        // Greyish brown - old frame artifacts
+        FrameColor::Artifact
     } else if h >= 20.0 && h <= 50.0 && v > 0.50 {
         // Lighter brown/tan - could be land
         FrameColor::Land
@@ -235,11 +371,360 @@ pub fn detect_frame_color(image: &DynamicImage) -> FrameColor {
     }
 }
 
-/// Detect frame style (simplified version for now)
-pub fn detect_frame_style(_image: &DynamicImage) -> FrameStyle {
-    // For now, return Unknown - this would require more sophisticated analysis
-    // Could be enhanced by detecting texture patterns, holographic effects, etc.
-    FrameStyle::Unknown
+/// Average color of every 10th pixel in a column, None if the column is off the image
+fn column_average(image: &DynamicImage, x: u32, start_y: u32, end_y: u32) -> Option<[u8; 3]> {
+    let (width, height) = image.dimensions();
+    if x >= width {
+        return None;
+    }
+
+    let mut total = [0u64; 3];
+    let mut pixel_count = 0u64;
+    for y in (start_y..end_y.min(height)).step_by(10) {
+        let pixel = image.get_pixel(x, y);
+        for (sum, value) in total.iter_mut().zip(pixel.0.iter()) {
+            *sum += *value as u64;
+        }
+        pixel_count += 1;
+    }
+
+    if pixel_count == 0 {
+        return None;
+    }
+    Some(total.map(|sum| (sum / pixel_count) as u8))
+}
+
+/// Row bands a side of the frame is split into when looking for a hybrid frame
+const HYBRID_BANDS: u32 = 4;
+
+/// Most the saturation of a white or black hybrid half may vary between bands,
+/// and its brightness relative to the side's. Glare and shadow fall unevenly
+/// along a side.
+const HYBRID_FLAT_SPREAD: f32 = 0.1;
+
+/// The color of one side of a hybrid frame and how clearly it reads (0-1).
+/// Blue, red and green sides must be saturated enough not to be glare or
+/// shadow, white and black ones as bright and as saturated in every band.
+/// None if the side is washed out, changes color or is unevenly lit.
+fn side_hybrid_color(image: &DynamicImage, x: u32, start_y: u32, end_y: u32) -> Option<(ManaColor, f32)> {
+    let band = (end_y.saturating_sub(start_y) / HYBRID_BANDS).max(1);
+    let mut side = None;
+    let mut bands = Vec::new();
+    for i in 0..HYBRID_BANDS {
+        let y = start_y + i * band;
+        let average = column_average(image, x, y, y + band)?;
+        let hsv = rgb_to_hsv(average[0], average[1], average[2]);
+        let color = classify_mana_color(hsv)?;
+        if side.is_some_and(|side| side != color) {
+            return None;
+        }
+        side = Some(color);
+        bands.push(hsv);
+    }
+
+    let color = side?;
+    if matches!(color, ManaColor::White | ManaColor::Black) {
+        let spread = |value: fn(&(f32, f32, f32)) -> f32| {
+            let values = bands.iter().map(value);
+            values.clone().fold(f32::MIN, f32::max) - values.fold(f32::MAX, f32::min)
+        };
+        let brightness = bands.iter().map(|hsv| hsv.2).sum::<f32>() / bands.len() as f32;
+        let spread = spread(|hsv| hsv.1).max(spread(|hsv| hsv.2) / brightness.max(0.01));
+        (spread <= HYBRID_FLAT_SPREAD).then_some((color, 1.0 - spread / HYBRID_FLAT_SPREAD))
+    } else {
+        let saturation = bands.iter().map(|hsv| hsv.1).fold(f32::MAX, f32::min);
+        bands
+            .iter()
+            .all(|hsv| is_saturated(*hsv))
+            .then_some((color, ((saturation - 0.35) / 0.3).min(1.0)))
+    }
+}
+
+/// Whether a sample is a clear color rather than a washed-out or dark one
+fn is_saturated((_, s, v): (f32, f32, f32)) -> bool {
+    s >= 0.35 && v >= 0.30
+}
+
+/// The mana color of a frame or pinline sample, None for gold, grey and brown
+fn classify_mana_color((h, s, v): (f32, f32, f32)) -> Option<ManaColor> {
+    if v < 0.25 {
+        Some(ManaColor::Black)
+    } else if s < 0.20 && v > 0.60 {
+        Some(ManaColor::White)
+    } else if s <= 0.25 {
+        None
+    } else if (200.0..=260.0).contains(&h) {
+        Some(ManaColor::Blue)
+    } else if h <= 20.0 || h >= 345.0 {
+        Some(ManaColor::Red)
+    } else if (90.0..=150.0).contains(&h) {
+        Some(ManaColor::Green)
+    } else {
+        None
+    }
+}
+
+/// Orders two colors in WUBRG order
+fn color_pair(a: ManaColor, b: ManaColor) -> (ManaColor, ManaColor) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Smallest share of a pinline strip that must be one color to count
+const PINLINE_MIN_PERCENT: usize = 10;
+
+/// Reads the colors of a gold card's pinlines, the thin lines between the
+/// frame and the text box. Two-color gold cards draw one color on each side.
+fn detect_pinline_colors(image: &DynamicImage) -> Option<(ManaColor, ManaColor)> {
+    let (width, height) = image.dimensions();
+    let start_y = (height as f32 * 0.55) as u32;
+    let end_y = (height as f32 * 0.90) as u32;
+
+    let side_color = |from: f32, to: f32| -> Option<ManaColor> {
+        let (x0, x1) = ((width as f32 * from) as u32, (width as f32 * to) as u32);
+        let mut counts = [0usize; 5];
+        let mut samples = 0usize;
+        for y in (start_y..end_y).step_by(5) {
+            for x in x0..x1.min(width) {
+                let pixel = image.get_pixel(x, y);
+                if let Some(color) = classify_mana_color(rgb_to_hsv(pixel[0], pixel[1], pixel[2])) {
+                    counts[color as usize] += 1;
+                }
+                samples += 1;
+            }
+        }
+
+        let (index, &count) = counts.iter().enumerate().max_by_key(|(_, count)| **count)?;
+        // The pinline is a few pixels of a strip that is mostly frame and text box
+        (samples > 0 && count * 100 >= samples * PINLINE_MIN_PERCENT).then(|| ManaColor::ALL[index])
+    };
+
+    let left = side_color(0.075, 0.10)?;
+    let right = side_color(0.90, 0.925)?;
+    (left != right).then(|| color_pair(left, right))
+}
+
+/// Mean color and luminance spread of an image region
+struct RegionStats {
+    mean_rgb: [f32; 3],
+    mean_luma: f32,
+    luma_std: f32,
+}
+
+impl RegionStats {
+    fn color_distance(&self, other: &RegionStats) -> f32 {
+        self.mean_rgb
+            .iter()
+            .zip(other.mean_rgb.iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+}
+
+/// Compute stats over a region given as fractions of the image size.
+/// Samples every other pixel, which is plenty for these coarse measurements.
+fn region_stats(image: &DynamicImage, x0: f32, y0: f32, x1: f32, y1: f32) -> Option<RegionStats> {
+    let (width, height) = image.dimensions();
+    let px0 = (width as f32 * x0) as u32;
+    let py0 = (height as f32 * y0) as u32;
+    let px1 = ((width as f32 * x1) as u32).min(width);
+    let py1 = ((height as f32 * y1) as u32).min(height);
+
+    let mut sum = [0f32; 3];
+    let mut luma_sum = 0f32;
+    let mut luma_sq_sum = 0f32;
+    let mut count = 0f32;
+
+    for y in (py0..py1).step_by(2) {
+        for x in (px0..px1).step_by(2) {
+            let pixel = image.get_pixel(x, y);
+            let r = pixel[0] as f32 / 255.0;
+            let g = pixel[1] as f32 / 255.0;
+            let b = pixel[2] as f32 / 255.0;
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+
+            sum[0] += r;
+            sum[1] += g;
+            sum[2] += b;
+            luma_sum += luma;
+            luma_sq_sum += luma * luma;
+            count += 1.0;
+        }
+    }
+
+    if count == 0.0 {
+        return None;
+    }
+
+    let mean_luma = luma_sum / count;
+    Some(RegionStats {
+        mean_rgb: [sum[0] / count, sum[1] / count, sum[2] / count],
+        mean_luma,
+        luma_std: (luma_sq_sum / count - mean_luma * mean_luma).max(0.0).sqrt(),
+    })
+}
+
+/// Count separate bands of rows containing ink (pixels that differ from the
+/// background luminance), e.g. lines of small print in a region
+fn count_text_lines(
+    image: &DynamicImage,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    background_luma: f32,
+) -> usize {
+    let (width, height) = image.dimensions();
+    let px0 = (width as f32 * x0) as u32;
+    let py0 = (height as f32 * y0) as u32;
+    let px1 = ((width as f32 * x1) as u32).min(width);
+    let py1 = ((height as f32 * y1) as u32).min(height);
+    if px1 <= px0 {
+        return 0;
+    }
+
+    let mut lines = 0;
+    let mut in_line = false;
+    let mut gap_rows = 0;
+
+    for y in py0..py1 {
+        let mut ink = 0;
+        for x in px0..px1 {
+            let pixel = image.get_pixel(x, y);
+            let luma = (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.0;
+            if (luma - background_luma).abs() > 0.35 {
+                ink += 1;
+            }
+        }
+
+        // A row is part of a text line if at least 2% of it is ink
+        if ink * 50 >= px1 - px0 {
+            if !in_line {
+                lines += 1;
+                in_line = true;
+            }
+            gap_rows = 0;
+        } else {
+            gap_rows += 1;
+            // Require a couple of empty rows so one line isn't counted twice
+            if gap_rows >= 2 {
+                in_line = false;
+            }
+        }
+    }
+
+    lines
+}
+
+/// Detect frame style from the layout of a card image.
+///
+/// Assumes the image is the card itself (rectified or a tight scan). The checks,
+/// in order:
+/// - Borderless: the outer border strip is textured art instead of a flat color.
+/// - Old vs new layout: old frames (1993/1997) continue the colored frame down to
+///   the border and print the artist inside it, while 2003+ frames end above a
+///   band of plain border color that holds the small print.
+/// - 2003 vs 2015: the 2015 frame prints two lines of info (collector number,
+///   set code and language) at the bottom-left of the border, 2003 prints one.
+/// - Future/Showcase: the side frame is textured instead of a flat color.
+/// - 1993: core sets up to Fifth Edition have no expansion symbol on the type
+///   line. Cards with a symbol could be either frame (Chronicles is white-bordered
+///   with a symbol in the 1993 frame), as could cards whose type line is too busy
+///   or glary to tell.
+/// - 1997: below the text box, the 1997 frame prints the artist and, on a line
+///   of its own, the copyright and collector number. 1993 frames print only the
+///   artist there. Cards with a symbol and one line stay `OldFrame`.
+///
+/// How sure the reading is comes from how clearly the bottom band takes after
+/// the border or the frame, and how clearly each later check came out.
+pub fn detect_frame_style(image: &DynamicImage) -> (FrameStyle, f32) {
+    // Outer border strips (top, bottom, left, right)
+    let border_regions = [
+        region_stats(image, 0.05, 0.005, 0.95, 0.025),
+        region_stats(image, 0.05, 0.975, 0.95, 0.995),
+        region_stats(image, 0.005, 0.05, 0.03, 0.95),
+        region_stats(image, 0.97, 0.05, 0.995, 0.95),
+    ];
+    let border_regions: Vec<RegionStats> = border_regions.into_iter().flatten().collect();
+    if border_regions.len() < 4 {
+        return (FrameStyle::Unknown, 0.0);
+    }
+
+    let textured_sides = border_regions.iter().filter(|r| r.luma_std > 0.12).count();
+    if textured_sides >= 3 {
+        return (FrameStyle::BorderlessFrame, margin_confidence(textured_sides as f32 - 2.5, 1.5));
+    }
+
+    let border = match region_stats(image, 0.005, 0.05, 0.03, 0.95) {
+        Some(stats) => stats,
+        None => return (FrameStyle::Unknown, 0.0),
+    };
+    let (side_frame, bottom_band) = match (
+        region_stats(image, 0.055, 0.20, 0.075, 0.50),
+        region_stats(image, 0.20, 0.935, 0.80, 0.955),
+    ) {
+        (Some(side), Some(bottom)) => (side, bottom),
+        _ => return (FrameStyle::Unknown, 0.0),
+    };
+
+    let to_border = bottom_band.color_distance(&border);
+    let to_frame = bottom_band.color_distance(&side_frame);
+
+    // Frame and border the same color (e.g. black cards with a black border):
+    // the bottom band can't tell the layouts apart
+    if border.color_distance(&side_frame) < 0.08 {
+        return (FrameStyle::Unknown, 0.0);
+    }
+
+    let modern_layout = to_border < to_frame;
+    let layout = margin_confidence((to_frame - to_border).abs() / to_frame.max(to_border), 0.5);
+    let textured_frame = side_frame.luma_std > 0.15;
+    let texture = margin_confidence((side_frame.luma_std - 0.15).abs(), 0.1);
+
+    if modern_layout {
+        if textured_frame {
+            return (FrameStyle::ShowcaseFrame, layout * texture);
+        }
+
+        // One info line or two is a count of a few pixel rows, never a clear call
+        let info_lines = count_text_lines(image, 0.045, 0.935, 0.40, 0.99, border.mean_luma);
+        if info_lines >= 2 {
+            (FrameStyle::M15Frame, layout * texture * 0.8)
+        } else {
+            (FrameStyle::ModernFrame, layout * texture * 0.8)
+        }
+    } else {
+        if textured_frame {
+            return (FrameStyle::FutureFrame, layout * texture);
+        }
+
+        // Expansion symbol at the right end of the type line, compared with the
+        // usually empty stretch of type line to its left. Only a symbol spot as
+        // flat as the empty stretch counts as no symbol.
+        let symbol = region_stats(image, 0.84, 0.545, 0.92, 0.585);
+        let empty_type_line = region_stats(image, 0.62, 0.545, 0.78, 0.585);
+        let symbol_margin = match (symbol, empty_type_line) {
+            (Some(symbol), Some(empty)) => empty.luma_std * 1.25 + 0.015 - symbol.luma_std,
+            _ => 0.0,
+        };
+
+        if symbol_margin > 0.0 {
+            return (FrameStyle::OldFrame1993, layout * texture * margin_confidence(symbol_margin, 0.03));
+        }
+
+        // Artist and copyright lines in the frame below the text box, which
+        // like the info lines of modern frames is a count of a few pixel rows
+        let credit_lines = count_text_lines(image, 0.10, 0.915, 0.90, 0.97, side_frame.mean_luma);
+        if credit_lines >= 2 {
+            (FrameStyle::OldFrame1997, layout * texture * 0.8)
+        } else {
+            (FrameStyle::OldFrame, layout * texture)
+        }
+    }
 }
 
 /// Calculate perceptual hash (dHash) of an image
@@ -269,6 +754,23 @@ pub fn calculate_phash(image: &DynamicImage) -> u64 {
     hash
 }
 
+/// Crops the art box of a card image.
+/// The region sits inside the art on every frame style, so hashes of the same
+/// art match even when the frame around it differs.
+pub fn crop_art_box(image: &DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let x = (width as f32 * 0.12) as u32;
+    let y = (height as f32 * 0.14) as u32;
+    let crop_width = (width as f32 * 0.76) as u32;
+    let crop_height = (height as f32 * 0.38) as u32;
+    image.crop_imm(x, y, crop_width.max(1), crop_height.max(1))
+}
+
+/// Calculate the perceptual hash of a card's art box
+pub fn calculate_art_phash(image: &DynamicImage) -> u64 {
+    calculate_phash(&crop_art_box(image))
+}
+
 /// Calculate Hamming distance between two hashes
 /// Returns the number of differing bits (0-64)
 /// Lower distance means more similar images
@@ -324,29 +826,58 @@ pub fn detect_corner_dots(image: &DynamicImage) -> bool {
     white_pixels_left > threshold && white_pixels_right > threshold
 }
 
-/// Extract all card features from an image
-pub fn extract_card_features(image_data: &[u8]) -> Result<CardFeatures, String> {
-    use base64::prelude::*;
-    
-    // Decode base64 if needed
-    let image_bytes = if let Ok(decoded) = BASE64_STANDARD.decode(image_data) {
-        decoded
+/// Locate the card in a photo and rectify it for the feature detectors.
+/// Falls back to the photo itself when no card outline is found
+/// (e.g. a scan that is already cropped to the card edge). A rectified card
+/// that is upside down is turned around.
+pub fn prepare_card_image(photo: DynamicImage) -> CardImage {
+    match crate::card_detection::rectify_card(&photo) {
+        Some(image) => crate::orientation::orient_card(CardImage {
+            image,
+            rectified: true,
+            rotation: 0,
+        }),
+        None => CardImage {
+            image: photo,
+            rectified: false,
+            rotation: 0,
+        },
+    }
+}
+
+/// Run every feature detector on a prepared card image
+pub fn extract_features_from_image(card: &CardImage) -> CardFeatures {
+    let image = &card.image;
+    let (border_type, border_confidence) = if card.rectified {
+        detect_rectified_border_type(image)
     } else {
-        image_data.to_vec()
+        detect_border_type(image)
     };
 
-    // Load image
-    let image = image::load_from_memory(&image_bytes)
-        .map_err(|e| format!("Failed to load image: {}", e))?;
+    // The layout checks measure positions on the card, which are meaningless
+    // if the card couldn't be located in the photo
+    let (frame_style, frame_style_confidence) = if card.rectified {
+        detect_frame_style(image)
+    } else {
+        (FrameStyle::Unknown, 0.0)
+    };
+    let (frame_color, frame_color_confidence) = detect_frame_color(image);
 
-    Ok(CardFeatures {
-        border_type: detect_border_type(&image),
-        frame_color: detect_frame_color(&image),
-        frame_style: detect_frame_style(&image),
-        has_corner_dots: detect_corner_dots(&image),
+    CardFeatures {
+        border_type,
+        frame_color,
+        frame_style,
+        has_corner_dots: detect_corner_dots(image),
         is_foil: false, // TODO: Implement foil detection
-        phash: calculate_phash(&image),
-    })
+        phash: calculate_phash(image),
+        set_code: None, // Filled in by the set symbol index, which lives in the app state
+        confidence: DetectionConfidence {
+            border: border_confidence,
+            frame_color: frame_color_confidence,
+            frame_style: frame_style_confidence,
+            set_symbol: 0.0,
+        },
+    }
 }
 
 #[cfg(test)]
@@ -367,4 +898,170 @@ mod tests {
         assert!((s - 1.0).abs() < 0.01);
         assert!((v - 1.0).abs() < 0.01);
     }
+
+    /// Draws a rectified card with a black border and a flat blue frame.
+    /// `frame_bottom` is where the frame ends (as a fraction of the height).
+    fn synthetic_card(frame_bottom: f32) -> image::RgbImage {
+        let (width, height) = (630u32, 880u32);
+        let mut img = image::RgbImage::from_pixel(width, height, image::Rgb([10, 10, 10]));
+        let x0 = (width as f32 * 0.048) as u32;
+        let y0 = (height as f32 * 0.034) as u32;
+        let y1 = (height as f32 * frame_bottom) as u32;
+        for y in y0..y1 {
+            for x in x0..(width - x0) {
+                img.put_pixel(x, y, image::Rgb([60, 90, 160]));
+            }
+        }
+        img
+    }
+
+    fn draw_text_line(img: &mut image::RgbImage, y_frac: f32) {
+        let (width, height) = img.dimensions();
+        let y = (height as f32 * y_frac) as u32;
+        for yy in y..y + 6 {
+            for x in (width as f32 * 0.06) as u32..(width as f32 * 0.30) as u32 {
+                if x % 4 != 0 {
+                    img.put_pixel(x, yy, image::Rgb([240, 240, 240]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_frame_style_m15() {
+        let mut card = synthetic_card(0.925);
+        draw_text_line(&mut card, 0.940);
+        draw_text_line(&mut card, 0.962);
+        let style = detect_frame_style(&DynamicImage::ImageRgb8(card)).0;
+        assert_eq!(style, FrameStyle::M15Frame);
+    }
+
+    #[test]
+    fn test_detect_frame_style_modern() {
+        let mut card = synthetic_card(0.925);
+        draw_text_line(&mut card, 0.950);
+        let style = detect_frame_style(&DynamicImage::ImageRgb8(card)).0;
+        assert_eq!(style, FrameStyle::ModernFrame);
+    }
+
+    /// Draws a rectified card whose left and right halves have the given frame colors
+    fn frame_card(left: [u8; 3], right: [u8; 3]) -> image::RgbImage {
+        image::RgbImage::from_fn(630, 880, |x, _| image::Rgb(if x < 315 { left } else { right }))
+    }
+
+    /// Draws a rectified card whose halves fade from one color at the top to
+    /// another at the bottom, as glare and shadow do
+    fn unevenly_lit_card(left: ([u8; 3], [u8; 3]), right: ([u8; 3], [u8; 3])) -> image::RgbImage {
+        image::RgbImage::from_fn(630, 880, |x, y| {
+            let (top, bottom) = if x < 315 { left } else { right };
+            let t = y as f32 / 879.0;
+            image::Rgb([0, 1, 2].map(|i| (top[i] as f32 * (1.0 - t) + bottom[i] as f32 * t).round() as u8))
+        })
+    }
+
+    #[test]
+    fn test_detect_frame_color() {
+        let detect = |card: image::RgbImage| detect_frame_color(&DynamicImage::ImageRgb8(card)).0;
+        let gold = [210, 170, 60];
+
+        assert_eq!(detect(frame_card([40, 90, 200], [40, 90, 200])), FrameColor::Blue);
+        assert_eq!(detect(frame_card(gold, gold)), FrameColor::Gold);
+        assert_eq!(detect(frame_card([130, 135, 140], [130, 135, 140])), FrameColor::Artifact);
+        assert_eq!(detect(frame_card([170, 170, 173], [170, 170, 173])), FrameColor::Colorless);
+        // Modern white frames are near neutral, more so after white balance
+        assert_eq!(detect(frame_card([200, 200, 202], [200, 200, 202])), FrameColor::White);
+        assert_eq!(detect(frame_card([236, 235, 232], [236, 235, 232])), FrameColor::White);
+        assert_eq!(detect(frame_card([120, 70, 40], [120, 70, 40])), FrameColor::Vehicle);
+        assert_eq!(
+            detect(frame_card([200, 40, 30], [40, 90, 200])),
+            FrameColor::Hybrid(ManaColor::Blue, ManaColor::Red)
+        );
+
+        // Glare or shadow fading along one side
+        let glare = ([250, 248, 240], [190, 180, 160]);
+        let shadow = ([62, 60, 58], [25, 24, 23]);
+        let (red, blue) = (([200, 40, 30], [200, 40, 30]), ([40, 90, 200], [40, 90, 200]));
+        assert_eq!(detect(unevenly_lit_card(glare, red)), FrameColor::Red);
+        assert_eq!(detect(unevenly_lit_card(blue, shadow)), FrameColor::Blue);
+        assert_eq!(detect(unevenly_lit_card(glare, shadow)), FrameColor::Unknown);
+        // Both sides reading red is surer than one side doing so
+        let confidence = |card: image::RgbImage| detect_frame_color(&DynamicImage::ImageRgb8(card)).1;
+        assert!(
+            confidence(frame_card([200, 40, 30], [200, 40, 30]))
+                > confidence(unevenly_lit_card(glare, red))
+        );
+        // Evenly lit white and black halves are hybrids
+        assert_eq!(
+            detect(frame_card([230, 228, 220], [30, 30, 32])),
+            FrameColor::Hybrid(ManaColor::White, ManaColor::Black)
+        );
+        assert_eq!(
+            detect(frame_card([230, 228, 220], [40, 90, 200])),
+            FrameColor::Hybrid(ManaColor::White, ManaColor::Blue)
+        );
+        // A glare band along one side is not a second color
+        let mut card = frame_card([40, 150, 60], [200, 40, 30]);
+        for y in 150..350 {
+            for x in 0..315 {
+                card.put_pixel(x, y, image::Rgb([220, 225, 225]));
+            }
+        }
+        assert!(!matches!(detect(card), FrameColor::Hybrid(..)));
+
+        // Gold frame with a red pinline on the left and a blue one on the right
+        let mut card = frame_card(gold, gold);
+        for y in 480..800 {
+            for x in 50..57 {
+                card.put_pixel(x, y, image::Rgb([200, 40, 30]));
+                card.put_pixel(629 - x, y, image::Rgb([40, 90, 200]));
+            }
+        }
+        assert_eq!(detect(card), FrameColor::TwoColor(ManaColor::Blue, ManaColor::Red));
+    }
+
+    #[test]
+    fn test_detect_frame_style_old_core_set() {
+        let card = synthetic_card(0.966);
+        let style = detect_frame_style(&DynamicImage::ImageRgb8(card)).0;
+        assert_eq!(style, FrameStyle::OldFrame1993);
+    }
+
+    #[test]
+    fn test_detect_frame_style_old_with_symbol() {
+        // A faint mark where the expansion symbol goes is not enough to rule out
+        // a symbol, and a symbol alone doesn't tell 1993 from 1997, so no year is given
+        for shade in [[90, 115, 185], [200, 200, 200]] {
+            let mut card = synthetic_card(0.966);
+            for y in 485..510 {
+                for x in 540..570 {
+                    if (x + y) % 3 == 0 {
+                        card.put_pixel(x, y, image::Rgb(shade));
+                    }
+                }
+            }
+            let style = detect_frame_style(&DynamicImage::ImageRgb8(card)).0;
+            assert_eq!(style, FrameStyle::OldFrame);
+        }
+    }
+
+    #[test]
+    fn test_detect_frame_style_1997() {
+        let mut card = synthetic_card(0.966);
+        for y in 485..510 {
+            for x in 540..570 {
+                if (x + y) % 3 == 0 {
+                    card.put_pixel(x, y, image::Rgb([200, 200, 200]));
+                }
+            }
+        }
+        // The artist alone is the 1993 frame's credit
+        draw_text_line(&mut card, 0.930);
+        let style = detect_frame_style(&DynamicImage::ImageRgb8(card.clone())).0;
+        assert_eq!(style, FrameStyle::OldFrame);
+
+        // The copyright and collector number below it make it 1997
+        draw_text_line(&mut card, 0.950);
+        let style = detect_frame_style(&DynamicImage::ImageRgb8(card)).0;
+        assert_eq!(style, FrameStyle::OldFrame1997);
+    }
 }
@@ -1,53 +1,172 @@
 use crate::card_features::{BorderType, CardFeatures, FrameColor, FrameStyle};
+use std::cmp::Ordering;
 
-/// Build a Scryfall search query based on detected card features
-pub fn build_search_query(features: &CardFeatures, ocr_text: Option<String>) -> String {
-    let mut query_parts = Vec::new();
+/// The detector a search filter came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterSource {
+    Name,
+    FrameColor,
+    Border,
+    FrameStyle,
+    SetSymbol,
+}
+
+/// One filter of a Scryfall search query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchFilter {
+    pub source: FilterSource,
+    pub filter: String,
+    /// How sure the detector was of this card's reading (0-1)
+    pub confidence: f32,
+}
+
+/// Build the Scryfall search filters of the detected card features, in query order
+///
+/// # Arguments
+///
+/// * `features` - The detected card features, with the confidence of each detection.
+/// * `ocr_text` - The card name read from the title bar and how sure the read is, if any.
+///
+/// # Returns
+///
+/// * `Vec<SearchFilter>` - One filter per detected feature.
+pub fn search_filters(features: &CardFeatures, ocr_text: Option<(String, f32)>) -> Vec<SearchFilter> {
+    let mut filters = Vec::new();
+    let confidence = features.confidence;
+    let mut push = |source: FilterSource, filter: String, confidence: f32| {
+        filters.push(SearchFilter { source, filter, confidence })
+    };
 
     // Add OCR text if available
-    if let Some(text) = ocr_text {
+    if let Some((text, read_confidence)) = ocr_text {
         if !text.trim().is_empty() {
-            query_parts.push(text.trim().to_string());
+            push(FilterSource::Name, text.trim().to_string(), read_confidence);
         }
     }
 
     // Add color filter based on frame color
-    match features.frame_color {
-        FrameColor::Blue => query_parts.push("c:u".to_string()),
-        FrameColor::Red => query_parts.push("c:r".to_string()),
-        FrameColor::Green => query_parts.push("c:g".to_string()),
-        FrameColor::White => query_parts.push("c:w".to_string()),
-        FrameColor::Black => query_parts.push("c:b".to_string()),
-        FrameColor::Gold => query_parts.push("c:m".to_string()), // multicolor
-        FrameColor::Land => query_parts.push("t:land".to_string()),
-        FrameColor::Unknown => {}
+    let color_filter = match features.frame_color {
+        FrameColor::Blue => Some("c:u".to_string()),
+        FrameColor::Red => Some("c:r".to_string()),
+        FrameColor::Green => Some("c:g".to_string()),
+        FrameColor::White => Some("c:w".to_string()),
+        FrameColor::Black => Some("c:b".to_string()),
+        FrameColor::Gold => Some("c:m".to_string()), // multicolor
+        FrameColor::TwoColor(first, second) | FrameColor::Hybrid(first, second) => {
+            Some(format!("c={}{}", first.symbol(), second.symbol()))
+        }
+        FrameColor::Artifact => Some("t:artifact c=c".to_string()),
+        FrameColor::Colorless => Some("c=c -t:artifact".to_string()),
+        FrameColor::Vehicle => Some("t:vehicle c=c".to_string()),
+        FrameColor::Land => Some("t:land".to_string()),
+        FrameColor::Unknown => None,
+    };
+    if let Some(filter) = color_filter {
+        push(FilterSource::FrameColor, filter, confidence.frame_color);
     }
 
-    // Add border filter
-    match features.border_type {
-        BorderType::Black => query_parts.push("border:black".to_string()),
-        BorderType::White => query_parts.push("border:white".to_string()),
-        BorderType::Silver => query_parts.push("border:silver".to_string()),
-        BorderType::Unknown => {}
+    // Add border filter (a borderless card has no border color to match)
+    if features.frame_style != FrameStyle::BorderlessFrame {
+        match features.border_type {
+            BorderType::Black => push(FilterSource::Border, "border:black".to_string(), confidence.border),
+            BorderType::White => push(FilterSource::Border, "border:white".to_string(), confidence.border),
+            BorderType::Silver => push(FilterSource::Border, "border:silver".to_string(), confidence.border),
+            BorderType::Unknown => {}
+        }
     }
 
     // Add frame style filter
-    match features.frame_style {
-        FrameStyle::OldFrame => query_parts.push("frame:old".to_string()),
-        FrameStyle::ModernFrame => query_parts.push("frame:modern".to_string()),
-        FrameStyle::M15Frame => query_parts.push("frame:2015".to_string()),
-        FrameStyle::Unknown => {}
+    let style_filter = match features.frame_style {
+        FrameStyle::OldFrame => Some("frame:old"),
+        FrameStyle::OldFrame1993 => Some("frame:1993"),
+        FrameStyle::OldFrame1997 => Some("frame:1997"),
+        FrameStyle::ModernFrame => Some("frame:modern"),
+        FrameStyle::M15Frame => Some("frame:2015"),
+        FrameStyle::FutureFrame => Some("frame:future"),
+        FrameStyle::ShowcaseFrame => Some("is:showcase"),
+        FrameStyle::BorderlessFrame => Some("border:borderless"),
+        FrameStyle::Unknown => None,
+    };
+    if let Some(filter) = style_filter {
+        push(FilterSource::FrameStyle, filter.to_string(), confidence.frame_style);
+    }
+
+    // Add the edition read from the expansion symbol
+    if let Some(set_code) = &features.set_code {
+        push(FilterSource::SetSymbol, format!("e:{}", set_code), confidence.set_symbol);
     }
 
-    // Join all parts with spaces
-    if query_parts.is_empty() {
+    filters
+}
+
+/// Joins search filters into a query
+fn join_filters(filters: &[SearchFilter]) -> String {
+    if filters.is_empty() {
         // If no features detected, return a generic query
         "*".to_string()
     } else {
-        query_parts.join(" ")
+        filters
+            .iter()
+            .map(|f| f.filter.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 }
 
+/// Build a Scryfall search query based on detected card features
+pub fn build_search_query(features: &CardFeatures, ocr_text: Option<(String, f32)>) -> String {
+    join_filters(&search_filters(features, ocr_text))
+}
+
+/// The queries to try in turn when a search finds nothing. The first has
+/// every filter. The next ones drop a single filter each, least trusted
+/// detection first, so one wrong detection is found whichever it is; the
+/// name is dropped last, since a query without it matches whole sets of
+/// cards. After that, the least trusted filters are dropped together, down
+/// to the single most trusted one.
+///
+/// # Arguments
+///
+/// * `features` - The detected card features, with the confidence of each detection.
+/// * `ocr_text` - The card name read from the title bar and how sure the read is, if any.
+///
+/// # Returns
+///
+/// * `Vec<String>` - The queries, strictest first.
+pub fn relaxed_queries(features: &CardFeatures, ocr_text: Option<(String, f32)>) -> Vec<String> {
+    let mut queries = vec![build_search_query(features, ocr_text.clone())];
+    let filters = search_filters(features, ocr_text);
+    if filters.len() < 2 {
+        return queries;
+    }
+
+    let by_trust = |a: &&SearchFilter, b: &&SearchFilter| a.confidence.total_cmp(&b.confidence);
+    let mut single_drops: Vec<&SearchFilter> = filters.iter().collect();
+    single_drops.sort_by(|a, b| match (a.source == FilterSource::Name, b.source == FilterSource::Name) {
+        (false, true) => Ordering::Less,
+        (true, false) => Ordering::Greater,
+        _ => by_trust(a, b),
+    });
+    let without = |dropped: &[&SearchFilter]| {
+        let kept: Vec<SearchFilter> = filters
+            .iter()
+            .filter(|f| !dropped.iter().any(|d| std::ptr::eq(*d, *f)))
+            .cloned()
+            .collect();
+        join_filters(&kept)
+    };
+    for filter in &single_drops {
+        queries.push(without(&[filter]));
+    }
+
+    let mut least_trusted: Vec<&SearchFilter> = filters.iter().collect();
+    least_trusted.sort_by(by_trust);
+    for dropped in 2..filters.len() {
+        queries.push(without(&least_trusted[..dropped]));
+    }
+    queries
+}
+
 /// Get a description of the detected features for debugging/UI
 pub fn describe_features(features: &CardFeatures) -> String {
     let mut parts = Vec::new();
@@ -59,6 +178,12 @@ pub fn describe_features(features: &CardFeatures) -> String {
         BorderType::Unknown => parts.push("Unknown border"),
     }
 
+    let pair_description = match &features.frame_color {
+        FrameColor::TwoColor(first, second) => Some(format!("{:?}-{:?} frame", first, second)),
+        FrameColor::Hybrid(first, second) => Some(format!("{:?}-{:?} hybrid frame", first, second)),
+        _ => None,
+    };
+
     match features.frame_color {
         FrameColor::Blue => parts.push("Blue frame"),
         FrameColor::Red => parts.push("Red frame"),
@@ -66,17 +191,34 @@ pub fn describe_features(features: &CardFeatures) -> String {
         FrameColor::White => parts.push("White frame"),
         FrameColor::Black => parts.push("Black frame"),
         FrameColor::Gold => parts.push("Multicolor frame"),
+        FrameColor::TwoColor(..) | FrameColor::Hybrid(..) => parts.extend(pair_description.as_deref()),
+        FrameColor::Artifact => parts.push("Artifact frame"),
+        FrameColor::Colorless => parts.push("Colorless frame"),
+        FrameColor::Vehicle => parts.push("Vehicle frame"),
         FrameColor::Land => parts.push("Land frame"),
         FrameColor::Unknown => parts.push("Unknown frame color"),
     }
 
     match features.frame_style {
         FrameStyle::OldFrame => parts.push("Old frame style"),
+        FrameStyle::OldFrame1993 => parts.push("1993 frame style"),
+        FrameStyle::OldFrame1997 => parts.push("1997 frame style"),
         FrameStyle::ModernFrame => parts.push("Modern frame style"),
         FrameStyle::M15Frame => parts.push("M15 frame style"),
+        FrameStyle::FutureFrame => parts.push("Future frame style"),
+        FrameStyle::ShowcaseFrame => parts.push("Showcase frame style"),
+        FrameStyle::BorderlessFrame => parts.push("Borderless"),
         FrameStyle::Unknown => {}
     }
 
+    let set_description = features
+        .set_code
+        .as_ref()
+        .map(|code| format!("Set {}", code.to_uppercase()));
+    if let Some(description) = &set_description {
+        parts.push(description);
+    }
+
     if features.has_corner_dots {
         parts.push("Has corner dots");
     }
@@ -91,6 +233,7 @@ pub fn describe_features(features: &CardFeatures) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::card_features::{DetectionConfidence, ManaColor};
 
     #[test]
     fn test_build_query_with_blue_card() {
@@ -101,9 +244,11 @@ mod tests {
             has_corner_dots: false,
             is_foil: false,
             phash: 0,
+            set_code: None,
+            confidence: DetectionConfidence::default(),
         };
 
-        let query = build_search_query(&features, Some("Counterspell".to_string()));
+        let query = build_search_query(&features, Some(("Counterspell".to_string(), 1.0)));
         assert!(query.contains("Counterspell"));
         assert!(query.contains("c:u"));
         assert!(query.contains("border:white"));
@@ -118,12 +263,137 @@ mod tests {
             has_corner_dots: false,
             is_foil: false,
             phash: 0,
+            set_code: None,
+            confidence: DetectionConfidence::default(),
         };
 
-        let query = build_search_query(&features, Some("Lightning Bolt".to_string()));
+        let query = build_search_query(&features, Some(("Lightning Bolt".to_string(), 1.0)));
         assert!(query.contains("Lightning Bolt"));
         assert!(query.contains("c:r"));
         assert!(query.contains("border:black"));
         assert!(query.contains("frame:old"));
     }
+
+    #[test]
+    fn test_build_query_borderless_skips_border_color() {
+        let features = CardFeatures {
+            border_type: BorderType::Black,
+            frame_color: FrameColor::Unknown,
+            frame_style: FrameStyle::BorderlessFrame,
+            has_corner_dots: false,
+            is_foil: false,
+            phash: 0,
+            set_code: None,
+            confidence: DetectionConfidence::default(),
+        };
+
+        let query = build_search_query(&features, None);
+        assert_eq!(query, "border:borderless");
+    }
+
+    /// A white-bordered modern Counterspell from 7th Edition
+    fn counterspell_features(frame_color: FrameColor) -> CardFeatures {
+        CardFeatures {
+            border_type: BorderType::White,
+            frame_color,
+            frame_style: FrameStyle::ModernFrame,
+            has_corner_dots: false,
+            is_foil: false,
+            phash: 0,
+            set_code: Some("7ed".to_string()),
+            confidence: DetectionConfidence {
+                border: 0.6,
+                frame_color: 0.9,
+                frame_style: 0.5,
+                set_symbol: 0.7,
+            },
+        }
+    }
+
+    #[test]
+    fn test_relaxed_queries_drop_one_filter_before_two() {
+        let features = counterspell_features(FrameColor::Blue);
+        let name = Some(("Counterspell".to_string(), 0.8));
+
+        let queries = relaxed_queries(&features, name.clone());
+        assert_eq!(
+            queries,
+            vec![
+                "Counterspell c:u border:white frame:modern e:7ed",
+                "Counterspell c:u border:white e:7ed",
+                "Counterspell c:u frame:modern e:7ed",
+                "Counterspell c:u border:white frame:modern",
+                "Counterspell border:white frame:modern e:7ed",
+                "c:u border:white frame:modern e:7ed",
+                "Counterspell c:u e:7ed",
+                "Counterspell c:u",
+                "c:u",
+            ]
+        );
+        assert_eq!(queries[0], build_search_query(&features, name.clone()));
+
+        // The same detections, less sure of the set symbol than of the frame
+        let unsure_symbol = CardFeatures {
+            confidence: DetectionConfidence { set_symbol: 0.3, ..features.confidence },
+            ..features.clone()
+        };
+        assert_eq!(relaxed_queries(&unsure_symbol, name)[1], "Counterspell c:u border:white frame:modern");
+
+        let nothing_detected = CardFeatures {
+            border_type: BorderType::Unknown,
+            frame_color: FrameColor::Unknown,
+            frame_style: FrameStyle::Unknown,
+            ..features
+        };
+        assert_eq!(relaxed_queries(&CardFeatures { set_code: None, ..nothing_detected }, None), vec!["*"]);
+    }
+
+    #[test]
+    fn test_relaxed_queries_keep_name_when_color_is_wrong() {
+        // The name was read right, but glare turned the blue frame red, and
+        // the color reading is the surest of all
+        let features = counterspell_features(FrameColor::Red);
+        let queries = relaxed_queries(&features, Some(("Counterspell".to_string(), 0.6)));
+
+        let position = |query: &str| queries.iter().position(|q| q == query).unwrap();
+        let right_query = position("Counterspell border:white frame:modern e:7ed");
+        assert!(queries[..right_query].iter().all(|q| q.contains("Counterspell")));
+        assert!(right_query < position("c:r border:white frame:modern e:7ed"));
+    }
+
+    #[test]
+    fn test_build_query_with_set_code() {
+        let features = CardFeatures {
+            border_type: BorderType::Black,
+            frame_color: FrameColor::Green,
+            frame_style: FrameStyle::M15Frame,
+            has_corner_dots: false,
+            is_foil: false,
+            phash: 0,
+            set_code: Some("dom".to_string()),
+            confidence: DetectionConfidence::default(),
+        };
+
+        let query = build_search_query(&features, Some(("Llanowar Elves".to_string(), 1.0)));
+        assert_eq!(query, "Llanowar Elves c:g border:black frame:2015 e:dom");
+        assert!(describe_features(&features).contains("Set DOM"));
+    }
+
+    #[test]
+    fn test_build_query_with_color_pair() {
+        let features = CardFeatures {
+            border_type: BorderType::Black,
+            frame_color: FrameColor::TwoColor(ManaColor::White, ManaColor::Blue),
+            frame_style: FrameStyle::M15Frame,
+            has_corner_dots: false,
+            is_foil: false,
+            phash: 0,
+            set_code: None,
+            confidence: DetectionConfidence::default(),
+        };
+
+        let query = build_search_query(&features, Some(("Absorb".to_string(), 1.0)));
+        assert_eq!(query, "Absorb c=wu border:black frame:2015");
+        assert!(describe_features(&features).contains("White-Blue frame"));
+    }
 }
@@ -0,0 +1,252 @@
+use crate::card_features::rgb_to_hsv;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+use serde::Serialize;
+
+/// Size frames are compared at (a tenth of a millimeter per pixel is far
+/// more than foiling needs, and small frames forgive rectification jitter)
+const GRID_WIDTH: u32 = 126;
+const GRID_HEIGHT: u32 = 176;
+
+/// Change in exposure-normalized brightness across frames above which a
+/// pixel counts as catching the light
+const SHIMMER_RANGE: f32 = 0.25;
+
+/// Hue change (degrees) across frames above which a colorful pixel counts as
+/// shifting color. Printed ink keeps its hue under any angle.
+const RAINBOW_HUE: f32 = 40.0;
+
+/// Saturation a pixel needs for its hue to be read
+const MIN_RAINBOW_SATURATION: f32 = 0.2;
+
+/// Share of art pixels shifting color above which a card is foil
+const FOIL_RAINBOW: f32 = 0.08;
+
+/// Share of frame pixels catching the light above which a card without
+/// rainbow art is etched, as long as the art stays matte
+const ETCHED_SHIMMER: f32 = 0.15;
+
+/// Etched foiling is on the frame only; the art shimmers at most this much relative to it
+const ETCHED_ART_RATIO: f32 = 0.5;
+
+/// Fewest frames a finish can be told from
+pub const MIN_FINISH_FRAMES: usize = 2;
+
+/// The finish of a printing, named as in `AddCardArgs.finish`
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    Nonfoil,
+    /// Traditional foil, a rainbow sheen over the whole card
+    Foil,
+    /// Etched foil, a metallic frame around matte art
+    Etched,
+}
+
+/// Suggested finish of a card and what it was told from
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishEstimate {
+    pub finish: Finish,
+    /// Share of art pixels that shifted color across the frames
+    pub art_rainbow: f32,
+    /// Share of art pixels that caught the light across the frames
+    pub art_shimmer: f32,
+    /// Share of frame pixels that caught the light across the frames
+    pub frame_shimmer: f32,
+    /// Number of frames compared
+    pub frames: usize,
+}
+
+/// Estimates the finish of a card from a burst of rectified frames taken at
+/// slightly different angles.
+///
+/// Any glossy card shows a white glare that moves as it is tilted, so glare
+/// alone doesn't make a foil. Foil splits the light into colors that shift
+/// with the angle, while printed ink keeps its hue. Etched foils have a
+/// metallic frame that catches the light around art that doesn't.
+///
+/// # Arguments
+///
+/// * `frames` - The rectified card in each frame of the burst.
+///
+/// # Returns
+///
+/// * `Option<FinishEstimate>` - The suggested finish, or None with fewer than [`MIN_FINISH_FRAMES`] frames.
+pub fn estimate_finish(frames: &[DynamicImage]) -> Option<FinishEstimate> {
+    if frames.len() < MIN_FINISH_FRAMES {
+        return None;
+    }
+    let grids: Vec<RgbImage> = frames
+        .iter()
+        .map(|frame| frame.resize_exact(GRID_WIDTH, GRID_HEIGHT, FilterType::Triangle).to_rgb8())
+        .collect();
+    // Auto-exposure brightens and darkens whole frames, which isn't glare
+    let exposures: Vec<f32> = grids.iter().map(|grid| mean_luma(grid).max(1.0)).collect();
+
+    let (mut art, mut frame) = (RegionStats::default(), RegionStats::default());
+    for y in 0..GRID_HEIGHT {
+        for x in 0..GRID_WIDTH {
+            let region = match card_region(x, y) {
+                Some(CardRegion::Art) => &mut art,
+                Some(CardRegion::Frame) => &mut frame,
+                None => continue,
+            };
+
+            let mut brightness = (f32::MAX, f32::MIN);
+            let mut hues = Vec::with_capacity(grids.len());
+            for (grid, exposure) in grids.iter().zip(&exposures) {
+                let pixel = grid.get_pixel(x, y);
+                let normalized = luma(pixel.0) / exposure;
+                brightness = (brightness.0.min(normalized), brightness.1.max(normalized));
+
+                let (hue, saturation, _) = rgb_to_hsv(pixel[0], pixel[1], pixel[2]);
+                if saturation >= MIN_RAINBOW_SATURATION {
+                    hues.push(hue);
+                }
+            }
+
+            region.pixels += 1;
+            if brightness.1 - brightness.0 > SHIMMER_RANGE {
+                region.shimmering += 1;
+            }
+            if hue_spread(&hues) > RAINBOW_HUE {
+                region.rainbow += 1;
+            }
+        }
+    }
+
+    let art_rainbow = art.share(art.rainbow);
+    let art_shimmer = art.share(art.shimmering);
+    let frame_shimmer = frame.share(frame.shimmering);
+    let finish = if art_rainbow >= FOIL_RAINBOW {
+        Finish::Foil
+    } else if frame_shimmer >= ETCHED_SHIMMER && art_shimmer <= frame_shimmer * ETCHED_ART_RATIO {
+        Finish::Etched
+    } else {
+        Finish::Nonfoil
+    };
+
+    Some(FinishEstimate {
+        finish,
+        art_rainbow,
+        art_shimmer,
+        frame_shimmer,
+        frames: frames.len(),
+    })
+}
+
+/// Pixel counts of one region of the card
+#[derive(Default)]
+struct RegionStats {
+    pixels: u32,
+    shimmering: u32,
+    rainbow: u32,
+}
+
+impl RegionStats {
+    fn share(&self, count: u32) -> f32 {
+        if self.pixels == 0 {
+            0.0
+        } else {
+            count as f32 / self.pixels as f32
+        }
+    }
+}
+
+enum CardRegion {
+    Art,
+    Frame,
+}
+
+/// Which part of the card a grid pixel is in, None for the border and text box
+fn card_region(x: u32, y: u32) -> Option<CardRegion> {
+    let fx = x as f32 / GRID_WIDTH as f32;
+    let fy = y as f32 / GRID_HEIGHT as f32;
+    // Same art box as `crop_art_box`
+    if (0.12..0.88).contains(&fx) && (0.14..0.52).contains(&fy) {
+        return Some(CardRegion::Art);
+    }
+    // The frame between the border and the art, along the sides and across the title bar
+    let on_side = (0.05..0.10).contains(&fx) || (0.90..0.95).contains(&fx);
+    let on_title = (0.05..0.95).contains(&fx) && (0.05..0.11).contains(&fy);
+    ((on_side && (0.05..0.95).contains(&fy)) || on_title).then_some(CardRegion::Frame)
+}
+
+fn luma(rgb: [u8; 3]) -> f32 {
+    0.299 * rgb[0] as f32 + 0.587 * rgb[1] as f32 + 0.114 * rgb[2] as f32
+}
+
+fn mean_luma(grid: &RgbImage) -> f32 {
+    let total: f32 = grid.pixels().map(|p| luma(p.0)).sum();
+    total / (grid.width() * grid.height()).max(1) as f32
+}
+
+/// Widest angle between any two hues, in degrees (0-180)
+fn hue_spread(hues: &[f32]) -> f32 {
+    let mut spread: f32 = 0.0;
+    for (i, a) in hues.iter().enumerate() {
+        for b in &hues[i + 1..] {
+            let difference = (a - b).abs();
+            spread = spread.max(difference.min(360.0 - difference));
+        }
+    }
+    spread
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// A card with a black border, a pale text box and the given art and frame colors
+    fn card_frame(art: Rgb<u8>, frame: Rgb<u8>) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(630, 880, |x, y| {
+            match card_region(x * GRID_WIDTH / 630, y * GRID_HEIGHT / 880) {
+                Some(CardRegion::Art) => art,
+                Some(CardRegion::Frame) => frame,
+                None if !(25..=605).contains(&x) || !(25..=855).contains(&y) => Rgb([15, 15, 15]),
+                None => Rgb([200, 190, 170]),
+            }
+        }))
+    }
+
+    #[test]
+    fn test_estimate_finish() {
+        let art = Rgb([40, 80, 160]);
+        let frame = Rgb([110, 80, 50]);
+
+        // Exposure changes alone are not foiling
+        let nonfoil: Vec<DynamicImage> = [0.8, 1.0, 1.2]
+            .iter()
+            .map(|gain| card_frame(art, frame).brighten(((gain - 1.0) * 100.0) as i32))
+            .collect();
+        assert_eq!(estimate_finish(&nonfoil).unwrap().finish, Finish::Nonfoil);
+
+        // Art that changes color as the card tilts is foil
+        let foil: Vec<DynamicImage> = [art, Rgb([160, 40, 120]), Rgb([60, 160, 60])]
+            .iter()
+            .map(|tint| card_frame(*tint, frame))
+            .collect();
+        let estimate = estimate_finish(&foil).unwrap();
+        assert_eq!(estimate.finish, Finish::Foil);
+        assert!(estimate.art_rainbow > 0.9);
+
+        // A frame that flashes around steady art is etched
+        let etched: Vec<DynamicImage> = [frame, Rgb([240, 235, 225]), frame]
+            .iter()
+            .map(|shine| card_frame(art, *shine))
+            .collect();
+        let estimate = estimate_finish(&etched).unwrap();
+        assert_eq!(estimate.finish, Finish::Etched);
+        assert_eq!(estimate.frames, 3);
+
+        assert!(estimate_finish(&nonfoil[..1]).is_none());
+    }
+
+    #[test]
+    fn test_hue_spread_wraps_around() {
+        assert_eq!(hue_spread(&[350.0, 10.0]), 20.0);
+        assert_eq!(hue_spread(&[0.0, 90.0, 180.0]), 180.0);
+        assert_eq!(hue_spread(&[]), 0.0);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::card_features::{calculate_phash, hamming_distance};
+use image::DynamicImage;
+
+/// Version tag stored next to every hash set.
+/// Bump it whenever an algorithm below changes so stored hashes get recomputed.
+pub const HASH_ALGORITHM: &str = "multi-v1";
+
+/// Fused similarity at or above which a local candidate is accepted.
+/// Equivalent to a 5-bit dHash distance, the old acceptance threshold.
+pub const MATCH_SCORE: f32 = 0.92;
+
+/// Weights of each hash in the fused similarity (sum to 1).
+/// The DCT hash and the 256-bit dHash carry most of the weight: they separate
+/// reprints whose 64-bit dHashes tie, while aHash only adds robustness to blur.
+const DHASH_WEIGHT: f32 = 0.2;
+const PHASH_WEIGHT: f32 = 0.35;
+const AHASH_WEIGHT: f32 = 0.1;
+const DHASH256_WEIGHT: f32 = 0.35;
+
+/// Lowest acceptance score feedback can teach a printing, about the 11-bit
+/// dHash distance of the candidate radius
+const MIN_LEARNED_SCORE: f32 = 0.83;
+/// Margin kept above the best score of a photo wrongly matched to a printing
+const REJECTION_MARGIN: f32 = 0.01;
+
+/// Logistic calibration of the fused similarity into a match probability.
+/// Unrelated cards land around 0.5 (half the bits differ) and map to ~0,
+/// photos of the same printing score above 0.9 and map to ~0.9 and up.
+const CONFIDENCE_MIDPOINT: f32 = 0.82;
+const CONFIDENCE_STEEPNESS: f32 = 30.0;
+
+/// The full set of perceptual hashes of a card image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardHashes {
+    /// 64-bit difference hash, the key of the in-memory indexes
+    pub dhash: u64,
+    /// 64-bit DCT hash
+    pub phash: u64,
+    /// 64-bit average hash
+    pub ahash: u64,
+    /// 256-bit difference hash on a 17x16 resize
+    pub dhash256: [u64; 4],
+}
+
+impl CardHashes {
+    /// Computes every hash of an image
+    pub fn compute(image: &DynamicImage) -> Self {
+        CardHashes {
+            dhash: calculate_phash(image),
+            phash: calculate_dct_phash(image),
+            ahash: calculate_ahash(image),
+            dhash256: calculate_dhash256(image),
+        }
+    }
+
+    /// Encodes the hashes as a fixed-width hex string for the database
+    pub fn to_hex(self) -> String {
+        let mut hex = format!("{:016x}{:016x}{:016x}", self.dhash, self.phash, self.ahash);
+        for word in self.dhash256 {
+            hex.push_str(&format!("{:016x}", word));
+        }
+        hex
+    }
+
+    /// Decodes hashes written by [`CardHashes::to_hex`]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 16 * 7 || !hex.is_ascii() {
+            return None;
+        }
+        let word = |i: usize| u64::from_str_radix(&hex[i * 16..(i + 1) * 16], 16).ok();
+
+        Some(CardHashes {
+            dhash: word(0)?,
+            phash: word(1)?,
+            ahash: word(2)?,
+            dhash256: [word(3)?, word(4)?, word(5)?, word(6)?],
+        })
+    }
+
+    /// Weighted similarity between two hash sets, from 0 (opposite) to 1 (identical)
+    pub fn similarity(&self, other: &CardHashes) -> f32 {
+        let dhash256_distance: u32 = self
+            .dhash256
+            .iter()
+            .zip(other.dhash256.iter())
+            .map(|(a, b)| hamming_distance(*a, *b))
+            .sum();
+
+        DHASH_WEIGHT * bit_similarity(hamming_distance(self.dhash, other.dhash), 64)
+            + PHASH_WEIGHT * bit_similarity(hamming_distance(self.phash, other.phash), 64)
+            + AHASH_WEIGHT * bit_similarity(hamming_distance(self.ahash, other.ahash), 64)
+            + DHASH256_WEIGHT * bit_similarity(dhash256_distance, 256)
+    }
+}
+
+/// Hashes read back from the database. The dHash is always present, the full
+/// set only if it was computed with the current [`HASH_ALGORITHM`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoredHashes {
+    pub dhash: u64,
+    pub full: Option<CardHashes>,
+}
+
+impl StoredHashes {
+    /// Parses stored hashes, ignoring a full set from an older algorithm
+    pub fn parse(phash: &str, hashes: Option<&str>, algorithm: Option<&str>) -> Option<Self> {
+        Some(StoredHashes {
+            dhash: crate::hash_index::parse_hash(phash)?,
+            full: hashes
+                .filter(|_| algorithm == Some(HASH_ALGORITHM))
+                .and_then(CardHashes::from_hex),
+        })
+    }
+
+    /// Fused similarity when both sides have a full hash set, dHash similarity otherwise
+    pub fn similarity(&self, other: &StoredHashes) -> f32 {
+        match (&self.full, &other.full) {
+            (Some(a), Some(b)) => a.similarity(b),
+            _ => dhash_similarity(hamming_distance(self.dhash, other.dhash)),
+        }
+    }
+}
+
+impl From<CardHashes> for StoredHashes {
+    fn from(hashes: CardHashes) -> Self {
+        StoredHashes {
+            dhash: hashes.dhash,
+            full: Some(hashes),
+        }
+    }
+}
+
+/// Acceptance score for one printing, tuned from recognition feedback.
+///
+/// Photos the user confirmed as the printing lower the score from
+/// [`MATCH_SCORE`] to the lowest of them (never below an 11-bit distance), so
+/// a consistent lighting setup gets recognized. Photos wrongly matched to it
+/// raise the score above the best of them, whatever the confirmations say.
+///
+/// # Arguments
+///
+/// * `confirmed` - Similarities between the printing and photos confirmed as it.
+/// * `rejected` - Similarities between the printing and photos rejected as it.
+///
+/// # Returns
+///
+/// * `f32` - The similarity a photo needs to be accepted as the printing.
+pub fn learned_threshold(confirmed: &[f32], rejected: &[f32]) -> f32 {
+    let mut threshold = MATCH_SCORE;
+    if let Some(lowest) = confirmed.iter().copied().reduce(f32::min) {
+        threshold = threshold.min(lowest.max(MIN_LEARNED_SCORE));
+    }
+    if let Some(highest) = rejected.iter().copied().reduce(f32::max) {
+        threshold = threshold.max(highest + REJECTION_MARGIN);
+    }
+    threshold
+}
+
+/// Similarity from a 64-bit dHash distance alone, for hashes stored before
+/// multi-algorithm hashing
+pub fn dhash_similarity(distance: u32) -> f32 {
+    bit_similarity(distance, 64)
+}
+
+/// Converts a fused similarity into a calibrated match confidence (0-1)
+pub fn match_confidence(similarity: f32) -> f32 {
+    1.0 / (1.0 + (-CONFIDENCE_STEEPNESS * (similarity - CONFIDENCE_MIDPOINT)).exp())
+}
+
+fn bit_similarity(distance: u32, bits: u32) -> f32 {
+    1.0 - distance as f32 / bits as f32
+}
+
+/// Calculate the DCT perceptual hash of an image.
+/// Resizes to 32x32 grayscale, keeps the 8x8 lowest frequencies of the DCT and
+/// sets a bit for every coefficient above their median. Low frequencies
+/// describe the overall layout, so the hash tolerates noise, blur and
+/// brightness changes better than the difference hash.
+pub fn calculate_dct_phash(image: &DynamicImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let gray = image
+        .resize_exact(SIZE as u32, SIZE as u32, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let cosines: Vec<[f32; SIZE]> = (0..LOW)
+        .map(|u| {
+            let mut row = [0.0; SIZE];
+            for (x, value) in row.iter_mut().enumerate() {
+                *value = (std::f32::consts::PI * (2 * x + 1) as f32 * u as f32 / (2 * SIZE) as f32)
+                    .cos();
+            }
+            row
+        })
+        .collect();
+
+    // Separable 2D DCT-II, computing only the low-frequency coefficients
+    let mut rows = [[0.0f32; LOW]; SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..SIZE)
+                .map(|x| gray.get_pixel(x as u32, y as u32)[0] as f32 * cosines[u][x])
+                .sum();
+        }
+    }
+
+    let mut coefficients = [0.0f32; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            coefficients[v * LOW + u] = (0..SIZE).map(|y| rows[y][u] * cosines[v][y]).sum();
+        }
+    }
+
+    // The DC term is the mean brightness and would dominate the median
+    let mut sorted: Vec<f32> = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c > median)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// Calculate the average hash of an image.
+/// Resizes to 8x8 grayscale and sets a bit for every pixel above the mean.
+pub fn calculate_ahash(image: &DynamicImage) -> u64 {
+    let gray = image
+        .resize_exact(8, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mean = gray.pixels().map(|p| p[0] as u32).sum::<u32>() / 64;
+
+    gray.pixels()
+        .enumerate()
+        .filter(|(_, p)| p[0] as u32 > mean)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// Calculate a 256-bit difference hash on a 17x16 resize.
+/// Same idea as `calculate_phash`, with enough resolution to tell apart
+/// printings that only differ in small frame or text details.
+pub fn calculate_dhash256(image: &DynamicImage) -> [u64; 4] {
+    let gray = image
+        .resize_exact(17, 16, image::imageops::FilterType::Lanczos3)
+        .to_luma8();
+
+    let mut words = [0u64; 4];
+    for y in 0..16 {
+        for x in 0..16 {
+            if gray.get_pixel(x, y)[0] > gray.get_pixel(x + 1, y)[0] {
+                let bit = (y * 16 + x) as usize;
+                words[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// A card-sized image with some structure for the hashes to pick up
+    fn test_image(shift: u32, brightness: i32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(146, 204, |x, y| {
+            let wave = (((x + shift) / 12 + y / 17) % 3) as i32 * 60;
+            let value = (wave + (x as i32 * 80 / 146) + brightness).clamp(0, 255) as u8;
+            Rgb([value, value / 2, 255 - value])
+        }))
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let hashes = CardHashes::compute(&test_image(0, 0));
+        let hex = hashes.to_hex();
+        assert_eq!(hex.len(), 112);
+        assert_eq!(CardHashes::from_hex(&hex), Some(hashes));
+        assert_eq!(CardHashes::from_hex("abc"), None);
+    }
+
+    #[test]
+    fn test_similarity_separates_images() {
+        let original = CardHashes::compute(&test_image(0, 0));
+        let brighter = CardHashes::compute(&test_image(0, 25));
+        let different = CardHashes::compute(&test_image(40, 0));
+
+        assert_eq!(original.similarity(&original), 1.0);
+        let same_score = original.similarity(&brighter);
+        let different_score = original.similarity(&different);
+        assert!(same_score >= MATCH_SCORE, "same image scored {}", same_score);
+        assert!(different_score < same_score);
+    }
+
+    #[test]
+    fn test_match_confidence_calibration() {
+        assert!(match_confidence(0.5) < 0.01);
+        assert!(match_confidence(MATCH_SCORE) > 0.9);
+        assert!(match_confidence(0.85) < match_confidence(0.9));
+        // The legacy threshold (5 bits) stays acceptable on its own
+        assert!(dhash_similarity(5) >= MATCH_SCORE);
+        assert!(dhash_similarity(6) < MATCH_SCORE);
+    }
+
+    #[test]
+    fn test_learned_threshold() {
+        assert_eq!(learned_threshold(&[], &[]), MATCH_SCORE);
+        // Confirmations loosen the threshold, down to a floor
+        assert_eq!(learned_threshold(&[0.88, 0.95], &[]), 0.88);
+        assert_eq!(learned_threshold(&[0.6], &[]), MIN_LEARNED_SCORE);
+        // A near miss tightens it, and wins over confirmations
+        assert!(learned_threshold(&[], &[0.94]) > 0.94);
+        assert!(learned_threshold(&[0.88], &[0.9]) > 0.9);
+    }
+
+    #[test]
+    fn test_stored_hashes_fall_back_to_dhash() {
+        let hashes = CardHashes::compute(&test_image(0, 0));
+        let hex = hashes.to_hex();
+        let dhash_hex = format!("{:x}", hashes.dhash);
+
+        let current = StoredHashes::parse(&dhash_hex, Some(&hex), Some(HASH_ALGORITHM)).unwrap();
+        assert_eq!(current.full, Some(hashes));
+        let outdated = StoredHashes::parse(&dhash_hex, Some(&hex), Some("old")).unwrap();
+        assert_eq!(outdated.full, None);
+
+        let mut other = hashes;
+        other.dhash ^= 0b111;
+        other.phash = !other.phash;
+        // Without a full set on one side only the dHash is compared
+        assert_eq!(outdated.similarity(&other.into()), dhash_similarity(3));
+        assert!(current.similarity(&other.into()) < dhash_similarity(3));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::card_features::margin_confidence;
+use image::{DynamicImage, GenericImageView};
+use ocrs::{ImageSource, OcrEngine, OcrEngineParams};
+use rten::Model;
+use std::path::Path;
+
+/// File names of the bundled ocrs models (see `scripts/download-ocr-models.js`)
+pub const DETECTION_MODEL_FILE: &str = "text-detection.rten";
+pub const RECOGNITION_MODEL_FILE: &str = "text-recognition.rten";
+
+/// Minimum width the title crop is scaled up to before running OCR.
+/// Small phone crops produce glyphs too thin for the recognition model.
+const MIN_OCR_WIDTH: u32 = 600;
+
+/// Local OCR engine used to read printed text from card photos.
+/// Runs entirely offline using the bundled ocrs models.
+pub struct CardOcr {
+    engine: OcrEngine,
+}
+
+impl CardOcr {
+    /// Loads the detection and recognition models from a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_dir` - Directory containing the `.rten` model files.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CardOcr, String>` - The OCR engine or an error message.
+    pub fn load(model_dir: &Path) -> Result<Self, String> {
+        let detection_model = Model::load_file(model_dir.join(DETECTION_MODEL_FILE))
+            .map_err(|e| format!("Failed to load OCR detection model: {}", e))?;
+        let recognition_model = Model::load_file(model_dir.join(RECOGNITION_MODEL_FILE))
+            .map_err(|e| format!("Failed to load OCR recognition model: {}", e))?;
+
+        let engine = OcrEngine::new(OcrEngineParams {
+            detection_model: Some(detection_model),
+            recognition_model: Some(recognition_model),
+            ..Default::default()
+        })
+        .map_err(|e| format!("Failed to create OCR engine: {}", e))?;
+
+        Ok(CardOcr { engine })
+    }
+
+    /// Reads all text from an image region, one line per detected text line.
+    pub fn read_text(&self, image: &DynamicImage) -> Result<String, String> {
+        let image = upscale_for_ocr(image);
+        let rgb = image.to_rgb8();
+        let source = ImageSource::from_bytes(rgb.as_raw(), rgb.dimensions())
+            .map_err(|e| format!("Failed to prepare OCR input: {}", e))?;
+        let input = self
+            .engine
+            .prepare_input(source)
+            .map_err(|e| format!("Failed to prepare OCR input: {}", e))?;
+
+        self.engine
+            .get_text(&input)
+            .map_err(|e| format!("OCR failed: {}", e))
+    }
+
+    /// Crops the title bar of a card image and reads the card name from it.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(String, f32)>` - The cleaned card name and how sure the read is
+    ///   (0-1), or None if nothing legible was found.
+    pub fn read_card_name(&self, image: &DynamicImage) -> Option<(String, f32)> {
+        let title = crop_title_bar(image);
+        match self.read_text(&title) {
+            Ok(text) => clean_card_name(&text),
+            Err(e) => {
+                println!("Title OCR failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Crops the info line of a card image and reads the printing from it.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<InfoLine>` - The set code, collector number and language, or None
+    ///   if the line could not be read.
+    pub fn read_info_line(&self, image: &DynamicImage) -> Option<InfoLine> {
+        let info = crop_info_line(image);
+        match self.read_text(&info) {
+            Ok(text) => parse_info_line(&text),
+            Err(e) => {
+                println!("Info line OCR failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The printing details from the info line in the bottom-left corner of
+/// cards printed from 2015 on ("0123/0280 R" above "DOM • EN")
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfoLine {
+    /// Lowercase set code, as Scryfall uses it
+    pub set_code: String,
+    /// Collector number without leading zeros
+    pub collector_number: String,
+    /// Scryfall language code ("en", "ja", ...)
+    pub language: String,
+}
+
+/// Crops the card name area from a card image.
+/// Assumes the card fills the image; the mana cost on the right is excluded
+/// so mana symbols are not read as letters.
+pub fn crop_title_bar(image: &DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+
+    let x = (width as f32 * 0.06) as u32;
+    let y = (height as f32 * 0.035) as u32;
+    let crop_width = (width as f32 * 0.68) as u32;
+    let crop_height = (height as f32 * 0.065) as u32;
+
+    image.crop_imm(x, y, crop_width.max(1), crop_height.max(1))
+}
+
+/// Crops the info line (collector number, set code and language) from a card image.
+/// Assumes the card fills the image; the artist credit on the same lines is
+/// partly captured and ignored by the parser.
+pub fn crop_info_line(image: &DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+
+    let x = (width as f32 * 0.03) as u32;
+    let y = (height as f32 * 0.925) as u32;
+    let crop_width = (width as f32 * 0.42) as u32;
+    let crop_height = (height as f32 * 0.05) as u32;
+
+    image.crop_imm(x, y, crop_width.max(1), crop_height.max(1))
+}
+
+/// Scales small crops up so text is large enough for the recognition model
+fn upscale_for_ocr(image: &DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width >= MIN_OCR_WIDTH || width == 0 {
+        return image.clone();
+    }
+
+    let scale = MIN_OCR_WIDTH as f32 / width as f32;
+    let new_height = ((height as f32 * scale) as u32).max(1);
+    image.resize_exact(
+        MIN_OCR_WIDTH,
+        new_height,
+        image::imageops::FilterType::Lanczos3,
+    )
+}
+
+/// Cleans raw OCR output into a plausible card name.
+/// Keeps the first line with enough letters and drops characters
+/// that never appear in card names (mana symbols read as digits, etc).
+/// How sure the read is comes from the share of the line's characters that
+/// survived cleaning, lowered for short names where one misread letter
+/// matters more.
+pub fn clean_card_name(raw: &str) -> Option<(String, f32)> {
+    for line in raw.lines() {
+        // Drop a trailing mana cost ("1R", "2UU", "{X}{G}") if part of it was captured
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        while tokens
+            .last()
+            .is_some_and(|t| t.chars().all(|c| "0123456789WUBRGCX{}/".contains(c)))
+        {
+            tokens.pop();
+        }
+
+        let filtered: String = tokens
+            .join(" ")
+            .chars()
+            .filter(|c| c.is_alphabetic() || matches!(c, ' ' | ',' | '\'' | '-'))
+            .collect();
+
+        let name = filtered
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_matches(|c: char| matches!(c, ',' | '\'' | '-'))
+            .to_string();
+
+        let letter_count = name.chars().filter(|c| c.is_alphabetic()).count();
+        if letter_count >= 3 {
+            let read = tokens.iter().map(|t| t.chars().count()).sum::<usize>();
+            let kept = name.chars().filter(|c| !c.is_whitespace()).count();
+            let confidence = kept as f32 / read.max(1) as f32 * margin_confidence(letter_count as f32 - 3.0, 6.0);
+            return Some((name, confidence));
+        }
+    }
+
+    None
+}
+
+/// Parses raw OCR output of the info line.
+/// The set code is the token right before the language code, and the
+/// collector number is the first numeric token ahead of it (the printed
+/// total after a slash is dropped).
+pub fn parse_info_line(raw: &str) -> Option<InfoLine> {
+    let normalized: String = raw
+        .chars()
+        .map(|c| if matches!(c, '•' | '·' | '*' | '.') { ' ' } else { c })
+        .collect();
+    let tokens: Vec<&str> = normalized.split_whitespace().collect();
+
+    let (language_index, language) = tokens
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, _)| is_set_code(tokens[i - 1]))
+        .find_map(|(i, token)| printed_language_code(token).map(|code| (i, code)))?;
+    let set_code = tokens[language_index - 1].to_lowercase();
+
+    let collector_number = tokens[..language_index - 1]
+        .iter()
+        .find_map(|token| parse_collector_number(token))?;
+
+    Some(InfoLine {
+        set_code,
+        collector_number,
+        language: language.to_string(),
+    })
+}
+
+/// Set codes are 3 to 5 letters and digits with at least one letter
+fn is_set_code(token: &str) -> bool {
+    (3..=5).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_alphanumeric())
+        && token.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Reads "0123", "0123/0280" or "123a" into a collector number without leading zeros
+fn parse_collector_number(token: &str) -> Option<String> {
+    let number = token.split('/').next()?;
+    let digits_end = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    let (digits, suffix) = number.split_at(digits_end);
+
+    if digits.is_empty() || digits.len() > 4 || suffix.len() > 1 {
+        return None;
+    }
+    if !suffix.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    let trimmed = digits.trim_start_matches('0');
+    let trimmed = if trimmed.is_empty() { "0" } else { trimmed };
+    Some(format!("{}{}", trimmed, suffix))
+}
+
+/// Maps the language code printed on the card to Scryfall's language code
+fn printed_language_code(token: &str) -> Option<&'static str> {
+    match token.to_uppercase().as_str() {
+        "EN" => Some("en"),
+        "ES" | "SP" => Some("es"),
+        "FR" => Some("fr"),
+        "DE" => Some("de"),
+        "IT" => Some("it"),
+        "PT" => Some("pt"),
+        "JP" | "JA" => Some("ja"),
+        "KO" | "KR" => Some("ko"),
+        "RU" => Some("ru"),
+        "CS" | "ZHS" => Some("zhs"),
+        "CT" | "ZHT" => Some("zht"),
+        "PH" => Some("ph"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_card_name() {
+        let name = |raw: &str| clean_card_name(raw).map(|(name, _)| name);
+        assert_eq!(name("Lightning Bolt  1R\n"), Some("Lightning Bolt".to_string()));
+        assert_eq!(
+            name("  Jace, the Mind Sculptor 2UU"),
+            Some("Jace, the Mind Sculptor".to_string())
+        );
+        assert_eq!(name("12 %\nSol Ring"), Some("Sol Ring".to_string()));
+        assert_eq!(name("~~ 3"), None);
+
+        let (_, clean_read) = clean_card_name("Lightning Bolt 1R").unwrap();
+        let (noisy_name, noisy_read) = clean_card_name("Li%ght#ning B0lt").unwrap();
+        assert_eq!(noisy_name, "Lightning Blt");
+        assert_eq!(clean_read, 1.0);
+        assert!(noisy_read < clean_read);
+        let (_, short_read) = clean_card_name("Opt").unwrap();
+        assert!(short_read < clean_read);
+    }
+
+    #[test]
+    fn test_parse_info_line() {
+        assert_eq!(
+            parse_info_line("0123/0280 R\nDOM • EN  Illus Artist Name"),
+            Some(InfoLine {
+                set_code: "dom".to_string(),
+                collector_number: "123".to_string(),
+                language: "en".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_info_line("0042 U\nMKM * JP"),
+            Some(InfoLine {
+                set_code: "mkm".to_string(),
+                collector_number: "42".to_string(),
+                language: "ja".to_string(),
+            })
+        );
+        // Without a legible language code the set code can't be told apart
+        assert_eq!(parse_info_line("0123/0280 R\nDOM Illus Artist"), None);
+        assert_eq!(parse_info_line("R\nDOM • EN"), None);
+    }
+
+    #[test]
+    fn test_crop_title_bar_stays_in_bounds() {
+        let image = DynamicImage::new_rgb8(630, 880);
+        let title = crop_title_bar(&image);
+        assert!(title.width() > 0 && title.width() < 630);
+        assert!(title.height() > 0 && title.height() < 100);
+    }
+}
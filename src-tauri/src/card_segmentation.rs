@@ -0,0 +1,219 @@
+use crate::card_detection::{find_page_card_quads, warp_card, CardQuad};
+use crate::card_features::{prepare_card_image, CardImage};
+use crate::orientation::orient_card;
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+
+/// Pockets per row and column of a standard binder page
+pub const BINDER_ROWS: u32 = 3;
+pub const BINDER_COLUMNS: u32 = 3;
+
+/// Smallest fraction of the photo one card of a page or spread may cover.
+/// A 9-pocket page shot with some margin puts each card around 8%.
+const MIN_PAGE_CARD_AREA_FRACTION: f64 = 0.015;
+
+/// Where a card sits in a photo of several cards
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CardSlot {
+    /// Row in the page, from the top
+    pub row: u32,
+    /// Column in the row, from the left
+    pub column: u32,
+    /// Card outline in photo pixels: top-left, top-right, bottom-right, bottom-left
+    pub corners: [(f32, f32); 4],
+    /// Whether the card's outline was found. False for a pocket cut from the
+    /// even grid, whose `corners` are the grid cell's.
+    pub outlined: bool,
+}
+
+/// One card cut out of a photo of several cards
+pub struct SegmentedCard {
+    pub slot: CardSlot,
+    pub image: CardImage,
+}
+
+/// Splits a photo of a binder page or a spread of cards into single cards.
+///
+/// The photo is taken to be filled by an even `rows` x `columns` grid of
+/// pockets. Card outlines are detected first and each is placed in the pocket
+/// that holds its center; pockets without an outline (sleeves and pocket edges
+/// often hide them) are cut from the grid and prepared like a single-card
+/// photo. When fewer than two outlines are found, every pocket is cut from
+/// the grid.
+///
+/// # Arguments
+///
+/// * `photo` - The photo of the page.
+/// * `rows` - Rows of pockets in the page.
+/// * `columns` - Columns of pockets in the page.
+///
+/// # Returns
+///
+/// * `Vec<SegmentedCard>` - One card per pocket, in reading order.
+pub fn segment_cards(photo: &DynamicImage, rows: u32, columns: u32) -> Vec<SegmentedCard> {
+    let rows = rows.max(1);
+    let columns = columns.max(1);
+    let quads = find_page_card_quads(photo, MIN_PAGE_CARD_AREA_FRACTION);
+    if quads.len() < 2 {
+        println!("Found {} card outlines, splitting the photo into a grid", quads.len());
+        return split_into_grid(photo, rows, columns);
+    }
+
+    let (width, height) = photo.dimensions();
+    let mut pockets: Vec<Option<CardQuad>> = vec![None; (rows * columns) as usize];
+    // Largest first, so a pocket with two outlines keeps the bigger one
+    for quad in quads {
+        let (row, column) = pocket_of(&quad, width, height, rows, columns);
+        let pocket = &mut pockets[(row * columns + column) as usize];
+        if pocket.is_none() {
+            *pocket = Some(quad);
+        } else {
+            println!("Two card outlines at row {}, column {}, keeping the larger", row, column);
+        }
+    }
+
+    pockets
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, quad)| {
+            let (row, column) = (i as u32 / columns, i as u32 % columns);
+            let warped = quad.and_then(|quad| warp_card(photo, &quad).map(|image| (quad, image)));
+            match warped {
+                Some((quad, image)) => Some(SegmentedCard {
+                    slot: CardSlot {
+                        row,
+                        column,
+                        corners: quad.corners,
+                        outlined: true,
+                    },
+                    image: orient_card(CardImage {
+                        image,
+                        rectified: true,
+                        rotation: 0,
+                    }),
+                }),
+                None => {
+                    println!("No card outline at row {}, column {}, cutting the pocket from the grid", row, column);
+                    grid_pocket(photo, row, column, rows, columns)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The row and column of the grid cell holding an outline's center
+fn pocket_of(quad: &CardQuad, width: u32, height: u32, rows: u32, columns: u32) -> (u32, u32) {
+    let (x, y) = quad.center();
+    let column = (x / width as f32 * columns as f32).max(0.0) as u32;
+    let row = (y / height as f32 * rows as f32).max(0.0) as u32;
+    (row.min(rows - 1), column.min(columns - 1))
+}
+
+/// Cuts the photo into an even grid of pockets
+fn split_into_grid(photo: &DynamicImage, rows: u32, columns: u32) -> Vec<SegmentedCard> {
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (row, column)))
+        .filter_map(|(row, column)| grid_pocket(photo, row, column, rows, columns))
+        .collect()
+}
+
+/// Cuts one pocket out of an even grid, None if the photo is too small for the grid
+fn grid_pocket(photo: &DynamicImage, row: u32, column: u32, rows: u32, columns: u32) -> Option<SegmentedCard> {
+    let (width, height) = photo.dimensions();
+    let cell_width = width / columns;
+    let cell_height = height / rows;
+    if cell_width == 0 || cell_height == 0 {
+        return None;
+    }
+
+    let x = column * cell_width;
+    let y = row * cell_height;
+    let cell = photo.crop_imm(x, y, cell_width, cell_height);
+
+    let (x0, y0) = (x as f32, y as f32);
+    let (x1, y1) = ((x + cell_width) as f32, (y + cell_height) as f32);
+    Some(SegmentedCard {
+        slot: CardSlot {
+            row,
+            column,
+            corners: [(x0, y0), (x1, y0), (x1, y1), (x0, y1)],
+            outlined: false,
+        },
+        image: prepare_card_image(cell),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Draws a 3x3 page of light cards on a dark background, leaving out the
+    /// card at `missing` (row, column). With `page_edge`, the cards sit on a
+    /// lighter page whose edge is roughly card-shaped.
+    fn synthetic_page(missing: Option<(u32, u32)>, page_edge: bool) -> DynamicImage {
+        let mut img = image::RgbImage::from_pixel(1000, 1300, Rgb([30, 30, 35]));
+        if page_edge {
+            for y in 12..1288 {
+                for x in 12..988 {
+                    img.put_pixel(x, y, Rgb([95, 95, 100]));
+                }
+            }
+        }
+        for row in 0..3 {
+            for column in 0..3 {
+                if missing == Some((row, column)) {
+                    continue;
+                }
+                let (card_x, card_y) = (40 + column * 320, 40 + row * 420);
+                for y in card_y..card_y + 380 {
+                    for x in card_x..card_x + 272 {
+                        img.put_pixel(x, y, Rgb([225, 220, 210]));
+                    }
+                }
+            }
+        }
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_segment_binder_page() {
+        for page_edge in [false, true] {
+            let cards = segment_cards(&synthetic_page(None, page_edge), BINDER_ROWS, BINDER_COLUMNS);
+            assert_eq!(cards.len(), 9);
+
+            for (i, card) in cards.iter().enumerate() {
+                assert_eq!(card.slot.row, i as u32 / 3);
+                assert_eq!(card.slot.column, i as u32 % 3);
+                assert!(card.slot.outlined && card.image.rectified, "page edge {}: card {}", page_edge, i);
+            }
+            // The middle card's top-left corner
+            let (x, y) = cards[4].slot.corners[0];
+            assert!((x - 360.0).abs() < 10.0 && (y - 460.0).abs() < 10.0);
+        }
+    }
+
+    #[test]
+    fn test_missing_outline_keeps_pockets_in_place() {
+        let cards = segment_cards(&synthetic_page(Some((0, 1)), false), BINDER_ROWS, BINDER_COLUMNS);
+        assert_eq!(cards.len(), 9);
+
+        let pocket = &cards[1];
+        assert_eq!((pocket.slot.row, pocket.slot.column, pocket.slot.outlined), (0, 1, false));
+        assert_eq!(pocket.slot.corners[0], (333.0, 0.0));
+        // The card after the empty pocket keeps its own column
+        let (x, _) = cards[2].slot.corners[0];
+        assert!(cards[2].slot.outlined && (x - 680.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn test_grid_fallback_without_outlines() {
+        let blank = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(600, 840, Rgb([60, 60, 60])));
+        let cards = segment_cards(&blank, 3, 3);
+        assert_eq!(cards.len(), 9);
+        assert_eq!(cards[5].slot.row, 1);
+        assert_eq!(cards[5].slot.column, 2);
+        assert_eq!(cards[5].slot.corners[0], (400.0, 280.0));
+        assert!(!cards[5].slot.outlined);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::models::scryfall::ScryfallCard;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize};
+use std::fmt;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Cards written per progress report during an import
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Local copy of Scryfall's card data, loaded from a bulk data file, so
+/// cards can be looked up and added without a connection.
+///
+/// The catalog lives in its own database file: it is rebuilt wholesale on
+/// every import, which would otherwise hold the collection's lock for minutes.
+/// Lookups share one connection, opened on first use; imports write through
+/// their own, so lookups aren't held up while the file is read.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    path: PathBuf,
+    conn: Arc<Mutex<Option<Connection>>>,
+}
+
+/// Which bulk file the catalog was loaded from
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CatalogInfo {
+    /// `default_cards` (one printing per language-neutral card) or `all_cards` (every language)
+    pub kind: String,
+    /// The bulk file's `updated_at`, its version
+    pub updated_at: String,
+    pub card_count: usize,
+    pub imported_at: String,
+}
+
+/// A lookup the catalog can answer, parsed from a Scryfall query
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardLookup {
+    /// `!"Name"`, optionally with `set:`
+    Name { name: String, set_code: Option<String> },
+    /// `set:` and `cn:`
+    Printing { set_code: String, collector_number: String },
+}
+
+impl CardLookup {
+    /// Parses the exact-name and set/collector-number queries the app builds.
+    /// Any other Scryfall syntax gives None, and goes to the API.
+    pub fn parse(query: &str) -> Option<CardLookup> {
+        let mut rest = query.trim();
+        let mut name = None;
+        if let Some(quoted) = rest.strip_prefix("!\"") {
+            let end = quoted.find('"')?;
+            name = Some(quoted[..end].to_string());
+            rest = &quoted[end + 1..];
+        }
+
+        let mut set_code = None;
+        let mut collector_number = None;
+        for term in rest.split_whitespace() {
+            match term.split_once(':') {
+                Some(("set" | "e" | "s", value)) if !value.is_empty() => set_code = Some(value.to_lowercase()),
+                Some(("cn" | "number", value)) if !value.is_empty() => collector_number = Some(value.to_string()),
+                _ => return None,
+            }
+        }
+
+        match (name, set_code, collector_number) {
+            (Some(name), set_code, None) => Some(CardLookup::Name { name, set_code }),
+            (None, Some(set_code), Some(collector_number)) => Some(CardLookup::Printing { set_code, collector_number }),
+            _ => None,
+        }
+    }
+}
+
+impl Catalog {
+    pub fn new(path: PathBuf) -> Self {
+        Catalog {
+            path,
+            conn: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Opens the catalog database, creating its tables if needed
+    fn connect(&self) -> Result<Connection, String> {
+        let conn = Connection::open(&self.path).map_err(|e| format!("Failed to open catalog: {}", e))?;
+        create_catalog_tables(&conn).map_err(|e| format!("Failed to create catalog tables: {}", e))?;
+        Ok(conn)
+    }
+
+    /// Runs a lookup on the shared connection, opening it on first use.
+    /// Gives None without creating the file if nothing was ever imported.
+    fn with_connection<T>(&self, run: impl FnOnce(&Connection) -> Result<T, String>) -> Result<Option<T>, String> {
+        let mut guard = self.conn.lock().map_err(|_| "Catalog lock poisoned".to_string())?;
+        let conn = match &mut *guard {
+            Some(conn) => conn,
+            _ if !self.path.exists() => return Ok(None),
+            slot => slot.insert(self.connect()?),
+        };
+        run(conn).map(Some)
+    }
+
+    /// Describes the loaded bulk file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<CatalogInfo>, String>` - The catalog's version, None if it is empty, or an error message.
+    pub fn info(&self) -> Result<Option<CatalogInfo>, String> {
+        let info = self.with_connection(|conn| {
+            conn.query_row(
+                "SELECT kind, updated_at, card_count, imported_at FROM catalog_info WHERE id = 1",
+                [],
+                |row| {
+                    Ok(CatalogInfo {
+                        kind: row.get(0)?,
+                        updated_at: row.get(1)?,
+                        card_count: row.get::<_, i64>(2)? as usize,
+                        imported_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })?;
+        Ok(info.flatten())
+    }
+
+    /// Replaces the catalog with the cards of a Scryfall bulk data file
+    /// (`default_cards` or `all_cards`). The file is read one card at a time,
+    /// so memory stays flat however large it is. Cards that don't parse are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The bulk data JSON file.
+    /// * `kind` - The bulk data type, `default_cards` or `all_cards`.
+    /// * `updated_at` - The bulk file's `updated_at`.
+    /// * `progress` - Called with the number of cards written so far, every [`PROGRESS_INTERVAL`] cards.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CatalogInfo, String>` - The new catalog version, or an error message. On error the old catalog is kept.
+    pub fn import_bulk_file(
+        &self,
+        file: &Path,
+        kind: &str,
+        updated_at: &str,
+        mut progress: impl FnMut(usize),
+    ) -> Result<CatalogInfo, String> {
+        let reader = std::fs::File::open(file)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open bulk file: {}", e))?;
+
+        let mut conn = self.connect()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM catalog_cards", []).map_err(|e| e.to_string())?;
+
+        let mut written = 0;
+        let mut skipped = 0;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO catalog_cards
+                     (scryfall_id, oracle_id, name, name_key, set_code, collector_number, lang, released_at, card)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let mut on_card = |value: serde_json::Value| -> Result<(), String> {
+                // Tokens, art cards and odd layouts miss fields the app needs
+                let Ok(card) = serde_json::from_value::<ScryfallCard>(value) else {
+                    skipped += 1;
+                    return Ok(());
+                };
+                let json = serde_json::to_string(&card).map_err(|e| e.to_string())?;
+                insert
+                    .execute(params![
+                        card.id,
+                        card.oracle_id,
+                        card.name,
+                        card.name.to_lowercase(),
+                        card.set.to_lowercase(),
+                        card.collector_number,
+                        card.lang,
+                        card.released_at,
+                        json,
+                    ])
+                    .map_err(|e| e.to_string())?;
+                written += 1;
+                if written % PROGRESS_INTERVAL == 0 {
+                    progress(written);
+                }
+                Ok(())
+            };
+
+            serde_json::Deserializer::from_reader(reader)
+                .deserialize_seq(EachCard(&mut on_card))
+                .map_err(|e| format!("Failed to read bulk file: {}", e))?;
+        }
+
+        let info = CatalogInfo {
+            kind: kind.to_string(),
+            updated_at: updated_at.to_string(),
+            card_count: written,
+            imported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO catalog_info (id, kind, updated_at, card_count, imported_at)
+             VALUES (1, ?1, ?2, ?3, ?4)",
+            params![info.kind, info.updated_at, info.card_count as i64, info.imported_at],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        println!("Imported {} catalog cards, skipped {}", written, skipped);
+        Ok(info)
+    }
+
+    /// Looks up a printing by its Scryfall ID
+    pub fn card(&self, scryfall_id: &str) -> Result<Option<ScryfallCard>, String> {
+        let json: Option<String> = self
+            .with_connection(|conn| {
+                conn.query_row(
+                    "SELECT card FROM catalog_cards WHERE scryfall_id = ?1",
+                    params![scryfall_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())
+            })?
+            .flatten();
+        json.map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// Finds the printings matching a lookup, English and newest first
+    pub fn find(&self, lookup: &CardLookup) -> Result<Vec<ScryfallCard>, String> {
+        let (condition, values) = match lookup {
+            CardLookup::Name { name, set_code: None } => ("name_key = ?1", vec![name.to_lowercase()]),
+            CardLookup::Name { name, set_code: Some(set_code) } => {
+                ("name_key = ?1 AND set_code = ?2", vec![name.to_lowercase(), set_code.to_lowercase()])
+            }
+            CardLookup::Printing { set_code, collector_number } => (
+                "set_code = ?1 AND collector_number = ?2",
+                vec![set_code.to_lowercase(), collector_number.clone()],
+            ),
+        };
+
+        let rows = self.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT card FROM catalog_cards WHERE {}
+                     ORDER BY (lang = 'en') DESC, released_at DESC",
+                    condition
+                ))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(values), |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<rusqlite::Result<Vec<String>>>().map_err(|e| e.to_string())
+        })?;
+
+        let mut cards = Vec::new();
+        for json in rows.unwrap_or_default() {
+            cards.push(serde_json::from_str(&json).map_err(|e| e.to_string())?);
+        }
+        Ok(cards)
+    }
+
+    /// Languages a card was printed in within a set. Only complete when the
+    /// catalog was loaded from `all_cards`, see [`Catalog::info`].
+    pub fn languages(&self, oracle_id: &str, set_code: &str) -> Result<Vec<String>, String> {
+        let languages = self.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT DISTINCT lang FROM catalog_cards
+                     WHERE oracle_id = ?1 AND set_code = ?2 AND lang IS NOT NULL ORDER BY lang",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![oracle_id, set_code.to_lowercase()], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()
+                .map_err(|e| e.to_string())
+        })?;
+        Ok(languages.unwrap_or_default())
+    }
+}
+
+/// Reads the bulk data type and version from a Scryfall bulk file name,
+/// such as `default-cards-20241017091205.json`.
+///
+/// # Arguments
+///
+/// * `file` - The bulk data file.
+///
+/// # Returns
+///
+/// * `(Option<String>, Option<String>)` - The bulk data type and `updated_at`, each None if the name doesn't say.
+pub fn bulk_file_version(file: &Path) -> (Option<String>, Option<String>) {
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let kind = ["default_cards", "all_cards"]
+        .into_iter()
+        .find(|kind| name.starts_with(&kind.replace('_', "-")))
+        .map(str::to_string);
+
+    let stamp = name.rsplit('-').next().unwrap_or_default();
+    let updated_at = (stamp.len() == 14 && stamp.chars().all(|c| c.is_ascii_digit())).then(|| {
+        format!(
+            "{}-{}-{}T{}:{}:{}+00:00",
+            &stamp[0..4],
+            &stamp[4..6],
+            &stamp[6..8],
+            &stamp[8..10],
+            &stamp[10..12],
+            &stamp[12..14]
+        )
+    });
+    (kind, updated_at)
+}
+
+/// Creates the catalog tables
+fn create_catalog_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS catalog_cards (
+            scryfall_id TEXT PRIMARY KEY,
+            oracle_id TEXT,
+            name TEXT NOT NULL,
+            name_key TEXT NOT NULL,
+            set_code TEXT NOT NULL,
+            collector_number TEXT NOT NULL,
+            lang TEXT,
+            released_at TEXT,
+            card TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_catalog_cards_name ON catalog_cards(name_key, set_code)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_catalog_cards_printing ON catalog_cards(set_code, collector_number)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_catalog_cards_oracle ON catalog_cards(oracle_id, set_code)",
+        [],
+    )?;
+
+    // A single row describing the loaded bulk file
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS catalog_info (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            kind TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            card_count INTEGER NOT NULL,
+            imported_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Hands each element of a JSON array to a callback as it is read, rather
+/// than collecting the array
+struct EachCard<'a, F>(&'a mut F);
+
+impl<'de, F> Visitor<'de> for EachCard<'_, F>
+where
+    F: FnMut(serde_json::Value) -> Result<(), String>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON array of Scryfall cards")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            (self.0)(value).map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk_card(id: &str, name: &str, set: &str, number: &str, lang: &str) -> serde_json::Value {
+        serde_json::json!({
+            "object": "card",
+            "id": id,
+            "oracle_id": format!("oracle-{}", name),
+            "name": name,
+            "lang": lang,
+            "set": set,
+            "set_name": "Test Set",
+            "collector_number": number,
+            "released_at": "2020-01-01",
+            "prices": { "usd": "1.00", "usd_foil": null, "eur": null, "eur_foil": null },
+            "rarity": "common",
+            "legalities": { "modern": "legal" }
+        })
+    }
+
+    #[test]
+    fn test_import_and_lookup() {
+        let dir = std::env::temp_dir().join(format!("mtgia-catalog-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("all-cards-20241017091205.json");
+        let cards = serde_json::json!([
+            bulk_card("a1", "Llanowar Elves", "dom", "168", "en"),
+            bulk_card("a2", "Llanowar Elves", "dom", "168", "de"),
+            bulk_card("b1", "Shock", "m19", "156", "en"),
+            { "object": "card", "id": "broken" }
+        ]);
+        std::fs::write(&file, cards.to_string()).unwrap();
+
+        let catalog = Catalog::new(dir.join("catalog.db"));
+        assert_eq!(catalog.info().unwrap(), None);
+        assert!(catalog.card("a1").unwrap().is_none());
+
+        let (kind, updated_at) = bulk_file_version(&file);
+        assert_eq!(kind.as_deref(), Some("all_cards"));
+        assert_eq!(updated_at.as_deref(), Some("2024-10-17T09:12:05+00:00"));
+        let info = catalog
+            .import_bulk_file(&file, &kind.unwrap(), &updated_at.unwrap(), |_| {})
+            .unwrap();
+        assert_eq!(info.card_count, 3);
+        assert_eq!(catalog.info().unwrap(), Some(info));
+
+        assert_eq!(catalog.card("b1").unwrap().unwrap().name, "Shock");
+        let elves = catalog.find(&CardLookup::parse("set:DOM cn:168").unwrap()).unwrap();
+        assert_eq!(elves.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec!["a1", "a2"]);
+        let shock = catalog.find(&CardLookup::parse("!\"shock\"").unwrap()).unwrap();
+        assert_eq!(shock.len(), 1);
+        assert!(catalog.find(&CardLookup::parse("!\"Shock\" set:dom").unwrap()).unwrap().is_empty());
+        assert_eq!(catalog.languages("oracle-Llanowar Elves", "DOM").unwrap(), vec!["de", "en"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_card_lookup() {
+        assert_eq!(
+            CardLookup::parse("!\"Lightning Bolt\" set:m10"),
+            Some(CardLookup::Name { name: "Lightning Bolt".to_string(), set_code: Some("m10".to_string()) })
+        );
+        assert_eq!(
+            CardLookup::parse("set:dom cn:1"),
+            Some(CardLookup::Printing { set_code: "dom".to_string(), collector_number: "1".to_string() })
+        );
+        // Anything the catalog can't answer exactly goes to Scryfall
+        assert_eq!(CardLookup::parse("set:dom"), None);
+        assert_eq!(CardLookup::parse("Counterspell c:u border:white"), None);
+        assert_eq!(CardLookup::parse("!\"Shock\" t:instant"), None);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::catalog::{bulk_file_version, CatalogInfo};
+use crate::commands::scryfall::ProgressPayload;
+use crate::AppState;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+
+/// Loads a Scryfall bulk data file (`default_cards` or `all_cards`) into the
+/// local catalog, replacing what was there, so cards can be looked up and
+/// added offline. Emits `catalog-progress` events to the frontend.
+///
+/// # Arguments
+///
+/// * `app` - The application handle to emit events.
+/// * `state` - The application state.
+/// * `path` - The bulk data JSON file.
+/// * `kind` - The bulk data type, read from a Scryfall file name if omitted.
+/// * `updated_at` - The bulk file's `updated_at`, read from a Scryfall file name if omitted.
+///
+/// # Returns
+///
+/// * `Result<CatalogInfo, String>` - The catalog's new version, or an error message.
+#[tauri::command]
+pub async fn import_catalog(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    kind: Option<String>,
+    updated_at: Option<String>,
+) -> Result<CatalogInfo, String> {
+    let file = PathBuf::from(path);
+    let (file_kind, file_updated_at) = bulk_file_version(&file);
+    let kind = kind.or(file_kind).unwrap_or_else(|| "default_cards".to_string());
+    let updated_at = updated_at
+        .or(file_updated_at)
+        .ok_or("The bulk file's updated_at can't be read from its name, pass it explicitly")?;
+
+    if let Some(info) = state.catalog.info()? {
+        if info.kind == kind && info.updated_at == updated_at {
+            println!("Catalog is already at {} {}", kind, updated_at);
+            return Ok(info);
+        }
+    }
+
+    // Parsing hundreds of megabytes of JSON would stall the async runtime
+    let catalog = state.catalog.clone();
+    tokio::task::spawn_blocking(move || {
+        catalog.import_bulk_file(&file, &kind, &updated_at, |written| {
+            let _ = app.emit(
+                "catalog-progress",
+                ProgressPayload {
+                    current: written,
+                    total: 0,
+                    message: format!("Imported {} cards", written),
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Describes the bulk data file the local catalog was loaded from.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `Result<Option<CatalogInfo>, String>` - The catalog's version, None if nothing was imported, or an error message.
+#[tauri::command]
+pub async fn get_catalog_info(state: State<'_, AppState>) -> Result<Option<CatalogInfo>, String> {
+    state.catalog.info()
+}
@@ -1,6 +1,6 @@
 use crate::database::operations;
+use crate::hash_index::parse_hash;
 use crate::models::scryfall::ScryfallCard;
-use crate::services::scryfall::ScryfallService;
 use crate::AppState;
 use tauri::State;
 use uuid::Uuid;
@@ -36,8 +36,8 @@ pub async fn add_card(
     args: AddCardArgs,
     currency_preference: String,
 ) -> Result<String, String> {
-    let service = ScryfallService::new();
-    let card = service
+    let card = state
+        .card_data
         .fetch_card(&args.scryfall_id)
         .await
         .map_err(|e| e.to_string())?;
@@ -52,6 +52,14 @@ pub async fn add_card(
     operations::insert_card(&db, &id, &card, &args, &currency_preference)
         .map_err(|e| e.to_string())?;
 
+    if let Some(hash) = args.phash.as_deref().and_then(parse_hash) {
+        state
+            .phash_index
+            .lock()
+            .map_err(|_| "Failed to lock hash index".to_string())?
+            .insert(id.clone(), hash);
+    }
+
     Ok(id)
 }
 
@@ -66,11 +74,12 @@ pub async fn add_card(
 /// * `Result<Vec<ScryfallCard>, String>` - A list of matching cards or an error message.
 #[tauri::command]
 pub async fn search_scryfall(
+    state: State<'_, AppState>,
     query: String,
     page: u32,
 ) -> Result<crate::models::scryfall::ScryfallCardList, String> {
-    let service = ScryfallService::new();
-    service
+    state
+        .card_data
         .search_cards(&query, page)
         .await
         .map_err(|e| e.to_string())
@@ -86,9 +95,12 @@ pub async fn search_scryfall(
 ///
 /// * `Result<ScryfallCard, String>` - The card data or an error message.
 #[tauri::command]
-pub async fn get_card(scryfall_id: String) -> Result<ScryfallCard, String> {
-    let service = ScryfallService::new();
-    service
+pub async fn get_card(
+    state: State<'_, AppState>,
+    scryfall_id: String,
+) -> Result<ScryfallCard, String> {
+    state
+        .card_data
         .fetch_card(&scryfall_id)
         .await
         .map_err(|e| e.to_string())
@@ -106,11 +118,12 @@ pub async fn get_card(scryfall_id: String) -> Result<ScryfallCard, String> {
 /// * `Result<Vec<String>, String>` - A list of language codes.
 #[tauri::command]
 pub async fn get_card_languages(
+    state: State<'_, AppState>,
     oracle_id: String,
     set_code: String,
 ) -> Result<Vec<String>, String> {
-    let service = ScryfallService::new();
-    service
+    state
+        .card_data
         .get_card_languages(&oracle_id, &set_code)
         .await
         .map_err(|e| e.to_string())
@@ -170,7 +183,14 @@ pub async fn remove_card(state: State<'_, AppState>, id: String) -> Result<(), S
         .db
         .lock()
         .map_err(|_| "Failed to lock db".to_string())?;
-    operations::remove_card(&db, &id).map_err(|e| e.to_string())
+    operations::remove_card(&db, &id).map_err(|e| e.to_string())?;
+
+    state
+        .phash_index
+        .lock()
+        .map_err(|_| "Failed to lock hash index".to_string())?
+        .remove(&id);
+    Ok(())
 }
 
 /// Updates the quantity of a card in the collection.
@@ -198,7 +218,7 @@ pub async fn update_card_quantity(
 }
 
 /// Updates prices for all cards in the collection.
-/// This is a long-running operation that fetches latest prices from Scryfall.
+/// Fetches the latest prices from Scryfall, one request per 75 printings.
 ///
 /// # Arguments
 ///
@@ -213,7 +233,7 @@ pub async fn update_prices(
     state: State<'_, AppState>,
     currency_preference: String,
 ) -> Result<String, String> {
-    let service = crate::services::prices::PriceService::new();
+    let service = crate::services::prices::PriceService::new(state.card_data.clone());
 
     // Extract card data while holding the lock, then release it
     let cards = {
@@ -224,35 +244,32 @@ pub async fn update_prices(
         operations::get_all_cards(&db).map_err(|e| e.to_string())?
     };
 
-    // Now do async work without holding the lock
-    let mut updated_count = 0;
-    for card in cards {
-        std::thread::sleep(std::time::Duration::from_millis(100));
-
-        match service
-            .fetch_and_update_price(&card, &currency_preference)
-            .await
-        {
-            Ok(Some(price)) => {
-                // Re-acquire lock for each update
-                let db = state
-                    .db
-                    .lock()
-                    .map_err(|_| "Failed to lock db".to_string())?;
-                operations::update_card_price(&db, &card.id, price).map_err(|e| e.to_string())?;
-                operations::insert_price_history(&db, &card.id, price, &currency_preference)
-                    .map_err(|e| e.to_string())?;
-                updated_count += 1;
-            }
-            Ok(None) => {
-                println!("No price available for {}", card.name);
-            }
-            Err(e) => {
-                println!("Failed to fetch price for {}: {}", card.name, e);
-            }
-        }
+    // Now do async work without holding the lock. Cards sharing a printing
+    // share its lookup, and printings are fetched in batches.
+    let refresh = service.refresh_prices(&cards, &currency_preference).await;
+    println!(
+        "Fetched {} prices, {} cards have no price, {} failed",
+        refresh.prices.len(),
+        refresh.unpriced,
+        refresh.failed
+    );
+
+    {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| "Failed to lock db".to_string())?;
+        operations::update_card_prices(&db, &refresh.prices, &currency_preference)
+            .map_err(|e| e.to_string())?;
     }
 
+    let updated_count = refresh.prices.len();
+    if refresh.failed > 0 {
+        return Ok(format!(
+            "Updated prices for {} cards, failed to fetch {}",
+            updated_count, refresh.failed
+        ));
+    }
     Ok(format!("Updated prices for {} cards", updated_count))
 }
 
@@ -465,7 +482,6 @@ pub async fn import_collection(
     let mut imported = 0;
     let mut skipped = 0;
 
-    let scryfall_service = ScryfallService::new();
 
     for card in cards {
         let mut scryfall_id = card.scryfall_id.clone();
@@ -476,7 +492,7 @@ pub async fn import_collection(
             if let Some(set) = &card.set_code {
                 if let Some(cn) = &card.collector_number {
                     let query = format!("set:{} cn:{}", set, cn);
-                    if let Ok(results) = scryfall_service.search_cards(&query, 1).await {
+                    if let Ok(results) = state.card_data.search_cards(&query, 1).await {
                         if let Some(first) = results.data.first() {
                             scryfall_id = Some(first.id.clone());
                             scryfall_card_data = Some(first.clone());
@@ -484,7 +500,7 @@ pub async fn import_collection(
                     }
                 } else {
                     let query = format!("!\"{}\" set:{}", card.name, set);
-                    if let Ok(results) = scryfall_service.search_cards(&query, 1).await {
+                    if let Ok(results) = state.card_data.search_cards(&query, 1).await {
                         if let Some(first) = results.data.first() {
                             scryfall_id = Some(first.id.clone());
                             scryfall_card_data = Some(first.clone());
@@ -493,7 +509,7 @@ pub async fn import_collection(
                 }
             } else {
                 let query = format!("!\"{}\"", card.name);
-                if let Ok(results) = scryfall_service.search_cards(&query, 1).await {
+                if let Ok(results) = state.card_data.search_cards(&query, 1).await {
                     if let Some(first) = results.data.first() {
                         scryfall_id = Some(first.id.clone());
                         scryfall_card_data = Some(first.clone());
@@ -507,7 +523,7 @@ pub async fn import_collection(
             let card_data = if let Some(data) = scryfall_card_data {
                 data
             } else {
-                match scryfall_service.fetch_card(&sid).await {
+                match state.card_data.fetch_card(&sid).await {
                     Ok(data) => data,
                     Err(_) => {
                         skipped += 1;
@@ -541,8 +557,6 @@ pub async fn import_collection(
                     Err(_) => skipped += 1,
                 }
             }
-
-            std::thread::sleep(std::time::Duration::from_millis(50));
         } else {
             skipped += 1;
         }
@@ -554,21 +568,24 @@ pub async fn import_collection(
     ))
 }
 
-/// Background task to calculate pHashes for cards that don't have them.
-/// Downloads the image, calculates dHash, and updates the database.
+/// Background task to calculate hashes for cards that don't have them, or whose
+/// hashes were computed by an older version of the hash algorithms.
+/// Downloads the image, calculates the hash set, and updates the database.
 #[tauri::command]
 pub async fn calculate_missing_hashes(state: State<'_, AppState>) -> Result<String, String> {
-    use crate::card_features::calculate_phash;
+    use crate::card_hash::{CardHashes, HASH_ALGORITHM};
     use image::load_from_memory;
     
-    // 1. Get all cards without phash
+    // 1. Get all cards without hashes, or hashed by an older algorithm
     let cards_to_process = {
         let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
         let mut stmt = db.prepare(
-            "SELECT id, name, image_uri FROM cards WHERE phash IS NULL AND image_uri IS NOT NULL AND image_uri != ''"
+            "SELECT id, name, image_uri FROM cards
+             WHERE (phash IS NULL OR phash_algo IS NULL OR phash_algo != ?1)
+             AND image_uri IS NOT NULL AND image_uri != ''"
         ).map_err(|e| e.to_string())?;
         
-        let card_iter = stmt.query_map([], |row| {
+        let card_iter = stmt.query_map([HASH_ALGORITHM], |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
@@ -601,19 +618,21 @@ pub async fn calculate_missing_hashes(state: State<'_, AppState>) -> Result<Stri
             Ok(resp) => {
                 if let Ok(bytes) = resp.bytes().await {
                     if let Ok(img) = load_from_memory(&bytes) {
-                        // Calculate hash
-                        let hash = calculate_phash(&img);
-                        let hash_str = format!("{:x}", hash);
+                        // Calculate hashes
+                        let hashes = CardHashes::compute(&img);
                         
                         // Update DB
                         let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
-                        db.execute(
-                            "UPDATE cards SET phash = ?1 WHERE id = ?2",
-                            rusqlite::params![hash_str, id],
-                        ).map_err(|e| e.to_string())?;
+                        operations::update_card_hashes(&db, &id, &hashes)
+                            .map_err(|e| e.to_string())?;
+                        state
+                            .phash_index
+                            .lock()
+                            .map_err(|_| "Failed to lock hash index".to_string())?
+                            .insert(id.clone(), hashes.dhash);
                         
                         success_count += 1;
-                        println!("Indexed {}: {}", name, hash_str);
+                        println!("Indexed {}: {:x}", name, hashes.dhash);
                     } else {
                         println!("Failed to decode image for {}", name);
                         fail_count += 1;
@@ -0,0 +1,52 @@
+use crate::card_hash::CardHashes;
+use crate::database::operations;
+use crate::models::scryfall::ScryfallCard;
+use crate::AppState;
+use tauri::State;
+
+/// Records which candidate the user picked for a scan. The pick is stored as
+/// a confirmed exemplar, and every candidate that was ranked above it as a
+/// wrong match, so later scans under the same setup are recognized locally.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `image_hashes` - The scan's hashes, as returned in `RecognitionResult::image_hashes`.
+/// * `chosen` - The printing the user picked.
+/// * `rejected` - The candidates ranked above the pick.
+///
+/// # Returns
+///
+/// * `Result<usize, String>` - The number of new feedback entries or an error message.
+#[tauri::command]
+pub async fn record_recognition_feedback(
+    state: State<'_, AppState>,
+    image_hashes: String,
+    chosen: ScryfallCard,
+    rejected: Vec<ScryfallCard>,
+) -> Result<usize, String> {
+    let hashes = CardHashes::from_hex(&image_hashes).ok_or_else(|| "Invalid image hashes".to_string())?;
+
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    let mut index = state
+        .feedback_index
+        .lock()
+        .map_err(|_| "Failed to lock feedback index".to_string())?;
+
+    let verdicts = std::iter::once((&chosen, true))
+        .chain(rejected.iter().filter(|card| card.id != chosen.id).map(|card| (card, false)));
+
+    let mut recorded = 0;
+    for (card, confirmed) in verdicts {
+        if let Some(id) = operations::insert_recognition_feedback(&db, card, &hashes, confirmed)
+            .map_err(|e| e.to_string())?
+        {
+            index.insert(id, card.id.clone(), hashes.dhash, confirmed);
+            recorded += 1;
+        }
+    }
+    Ok(recorded)
+}
@@ -1,7 +1,9 @@
 use crate::models::scryfall::ScryfallCard;
-use crate::services::scryfall::ScryfallService;
+use crate::services::card_data::CardDataProvider;
+use crate::AppState;
 use serde::Serialize;
 use std::sync::Arc;
+use tauri::State;
 
 #[derive(Serialize)]
 pub struct MarketTrends {
@@ -12,10 +14,20 @@ pub struct MarketTrends {
 }
 
 #[tauri::command]
-pub async fn get_market_trends() -> Result<MarketTrends, String> {
-    let service = ScryfallService::new();
-    let service = Arc::new(service);
+pub async fn get_market_trends(state: State<'_, AppState>) -> Result<MarketTrends, String> {
+    market_trends(state.card_data.clone()).await
+}
 
+/// Fetches the four market trend lists in parallel.
+///
+/// # Arguments
+///
+/// * `service` - The card data provider to ask.
+///
+/// # Returns
+///
+/// * `Result<MarketTrends, String>` - The trend lists, or an error message if any fetch failed.
+async fn market_trends(service: Arc<dyn CardDataProvider>) -> Result<MarketTrends, String> {
     // Define queries
     // Standard Staples: Legal in Standard, sorted by USD price descending
     let standard_query = "f:standard game:paper";
@@ -85,40 +97,28 @@ pub async fn get_market_trends() -> Result<MarketTrends, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::bundled_fixtures;
+
+    fn names(cards: &[ScryfallCard]) -> Vec<&str> {
+        cards.iter().map(|card| card.name.as_str()).collect()
+    }
 
     #[tokio::test]
     async fn test_get_market_trends() {
-        // This is an integration test that hits the real Scryfall API.
-        // It might be slow or flaky if the API is down.
-        let result = get_market_trends().await;
-
-        match result {
-            Ok(trends) => {
-                println!("Standard Staples: {}", trends.standard_staples.len());
-                println!("Modern Staples: {}", trends.modern_staples.len());
-                println!(
-                    "Commander Popularity: {}",
-                    trends.commander_popularity.len()
-                );
-                println!("New & Hot: {}", trends.new_hot.len());
-
-                assert!(
-                    !trends.standard_staples.is_empty(),
-                    "Should have standard staples"
-                );
-                assert!(
-                    !trends.modern_staples.is_empty(),
-                    "Should have modern staples"
-                );
-                assert!(
-                    !trends.commander_popularity.is_empty(),
-                    "Should have commander popularity"
-                );
-                assert!(!trends.new_hot.is_empty(), "Should have new & hot cards");
-            }
-            Err(e) => {
-                panic!("Failed to get market trends: {}", e);
-            }
-        }
+        let trends = market_trends(Arc::new(bundled_fixtures()))
+            .await
+            .expect("Failed to get market trends");
+
+        // The fixtures can't evaluate format and date terms, so every list
+        // draws from all English printings
+        assert_eq!(trends.standard_staples.len(), 7);
+        assert_eq!(
+            names(&trends.standard_staples[..3]),
+            vec!["Sheoldred, the Apocalypse", "Ragavan, Nimble Pilferer", "Urza's Saga"]
+        );
+        // Cards without a price sort last
+        assert_eq!(trends.modern_staples.last().unwrap().name, "Black Lotus");
+        assert!(!trends.commander_popularity.is_empty(), "Should have commander popularity");
+        assert!(!trends.new_hot.is_empty(), "Should have new & hot cards");
     }
 }
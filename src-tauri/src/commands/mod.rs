@@ -1,6 +1,10 @@
 pub mod analytics;
+pub mod catalog;
 pub mod collection;
+pub mod feedback;
 pub mod market;
+pub mod reference;
+pub mod scan_session;
 pub mod scryfall;
 pub mod sets;
 pub mod tags;
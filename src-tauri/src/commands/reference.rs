@@ -0,0 +1,382 @@
+use crate::card_features::calculate_art_phash;
+use crate::card_hash::{CardHashes, HASH_ALGORITHM};
+use crate::commands::scryfall::ProgressPayload;
+use crate::database::operations;
+use crate::models::reference::ReferenceHash;
+use crate::models::scryfall::ScryfallCard;
+use crate::AppState;
+use image::DynamicImage;
+use serde::Deserialize;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+
+/// Name of the card list inside a reference image archive
+const ARCHIVE_MANIFEST_FILE: &str = "cards.json";
+
+/// Image extensions looked up for each card in a reference image archive
+const ARCHIVE_IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Hashes a card image into a reference entry for a printing.
+///
+/// # Arguments
+///
+/// * `card` - The printing the image belongs to.
+/// * `image` - The full card image.
+/// * `source` - Where the image came from ("scryfall", "archive" or "search").
+///
+/// # Returns
+///
+/// * `ReferenceHash` - The printing with its card and art-box hashes.
+pub fn reference_from_image(card: &ScryfallCard, image: &DynamicImage, source: &str) -> ReferenceHash {
+    reference_from_hashes(card, &CardHashes::compute(image), calculate_art_phash(image), source)
+}
+
+/// Builds a reference entry from hashes that were already computed.
+///
+/// # Arguments
+///
+/// * `card` - The printing the hashes belong to.
+/// * `hashes` - The full card image's hash set.
+/// * `art_hash` - The hash of the card's art box.
+/// * `source` - Where the image came from ("scryfall", "archive" or "search").
+///
+/// # Returns
+///
+/// * `ReferenceHash` - The printing with its hashes.
+pub fn reference_from_hashes(
+    card: &ScryfallCard,
+    hashes: &CardHashes,
+    art_hash: u64,
+    source: &str,
+) -> ReferenceHash {
+    ReferenceHash {
+        scryfall_id: card.id.clone(),
+        name: card.name.clone(),
+        set_code: card.set.clone(),
+        collector_number: card.collector_number.clone(),
+        image_uri: card.image_uris.as_ref().map(|u| u.normal.clone()),
+        phash: format!("{:x}", hashes.dhash),
+        art_phash: Some(format!("{:x}", art_hash)),
+        hashes: Some(hashes.to_hex()),
+        phash_algo: Some(HASH_ALGORITHM.to_string()),
+        source: source.to_string(),
+    }
+}
+
+/// Saves a reference entry and adds it to the in-memory reference index.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `reference` - The hashed printing.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if successful, or an error message.
+pub fn store_reference(state: &AppState, reference: &ReferenceHash) -> Result<(), String> {
+    use crate::hash_index::parse_hash;
+
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    operations::upsert_reference_hash(&db, reference).map_err(|e| e.to_string())?;
+
+    if let Some(hash) = parse_hash(&reference.phash) {
+        state
+            .reference_index
+            .lock()
+            .map_err(|_| "Failed to lock reference index".to_string())?
+            .insert(
+                reference.scryfall_id.clone(),
+                hash,
+                reference.art_phash.as_deref().and_then(parse_hash),
+            );
+    }
+    Ok(())
+}
+
+/// Downloads and hashes the image of every printing in the given sets, so
+/// those cards can be recognized offline without being in the collection.
+/// Printings that already have reference hashes are skipped.
+/// Emits `reference-progress` events to the frontend.
+///
+/// # Arguments
+///
+/// * `app` - The application handle to emit events.
+/// * `state` - The application state.
+/// * `set_code` - The set to hash, or None for every set in the local database.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - A summary message or an error string.
+#[tauri::command]
+pub async fn build_reference_hashes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    set_code: Option<String>,
+) -> Result<String, String> {
+    let set_codes = match set_code {
+        Some(code) => vec![code],
+        None => {
+            let db = state
+                .db
+                .lock()
+                .map_err(|_| "Failed to lock db".to_string())?;
+            operations::get_all_sets(&db)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|set| set.code)
+                .collect()
+        }
+    };
+
+    if set_codes.is_empty() {
+        return Err("No sets found, import sets first".to_string());
+    }
+
+    let client = reqwest::Client::new();
+    let total = set_codes.len();
+    let mut success_count = 0;
+    let mut fail_count = 0;
+
+    for (i, set_code) in set_codes.iter().enumerate() {
+        let already_hashed = {
+            let db = state
+                .db
+                .lock()
+                .map_err(|_| "Failed to lock db".to_string())?;
+            operations::get_hashed_reference_ids(&db, set_code).map_err(|e| e.to_string())?
+        };
+
+        app.emit(
+            "reference-progress",
+            ProgressPayload {
+                current: i + 1,
+                total,
+                message: format!("Hashing set: {}", set_code),
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut page = 1;
+        loop {
+            let list = state
+                .card_data
+                .fetch_cards_by_set(set_code, page)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for card in list.data.iter().filter(|c| !already_hashed.contains(&c.id)) {
+                // Double-faced cards have no top-level image
+                let Some(image_uris) = &card.image_uris else {
+                    continue;
+                };
+
+                match download_image(&client, &image_uris.small).await {
+                    Ok(image) => {
+                        store_reference(&state, &reference_from_image(card, &image, "scryfall"))?;
+                        success_count += 1;
+                    }
+                    Err(e) => {
+                        println!("Failed to hash {}: {}", card.name, e);
+                        fail_count += 1;
+                    }
+                }
+
+                // Be nice to Scryfall's image servers
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+
+            if !list.has_more {
+                break;
+            }
+            page += 1;
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    Ok(format!("Hashed {} printings, failed {}", success_count, fail_count))
+}
+
+/// Hashes a local reference image archive, for setting up offline recognition
+/// without downloading every image from Scryfall.
+///
+/// The archive is a directory holding a `cards.json` file with an array of
+/// Scryfall card objects (such as the Scryfall bulk data), and one image per
+/// card named after its Scryfall ID (`<id>.jpg`, `.jpeg` or `.png`).
+/// Cards without an image are skipped.
+/// Emits `reference-progress` events to the frontend.
+///
+/// # Arguments
+///
+/// * `app` - The application handle to emit events.
+/// * `state` - The application state.
+/// * `path` - The archive directory.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - A summary message or an error string.
+#[tauri::command]
+pub async fn import_reference_archive(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<String, String> {
+    let dir = PathBuf::from(path);
+    let manifest_dir = dir.clone();
+    let cards = tokio::task::spawn_blocking(move || read_archive_manifest(&manifest_dir))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let total = cards.len();
+    let mut success_count = 0;
+    let mut missing_count = 0;
+
+    for (i, card) in cards.iter().enumerate() {
+        let Some(image_path) = find_archive_image(&dir, &card.id) else {
+            missing_count += 1;
+            continue;
+        };
+
+        // Decoding is CPU-bound, keep it off the async workers
+        let image = tokio::task::spawn_blocking(move || image::open(&image_path))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match image {
+            Ok(image) => {
+                store_reference(&state, &reference_from_image(card, &image, "archive"))?;
+                success_count += 1;
+            }
+            Err(e) => {
+                println!("Failed to decode image for {}: {}", card.name, e);
+                missing_count += 1;
+            }
+        }
+
+        if i % 100 == 0 || i == total - 1 {
+            app.emit(
+                "reference-progress",
+                ProgressPayload {
+                    current: i + 1,
+                    total,
+                    message: format!("Hashing: {}", card.name),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(format!(
+        "Hashed {} printings, {} without a usable image",
+        success_count, missing_count
+    ))
+}
+
+/// Counts the printings in the reference hash catalog.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `Result<usize, String>` - The number of hashed printings or an error message.
+#[tauri::command]
+pub async fn get_reference_hash_count(state: State<'_, AppState>) -> Result<usize, String> {
+    let index = state
+        .reference_index
+        .lock()
+        .map_err(|_| "Failed to lock reference index".to_string())?;
+    Ok(index.len())
+}
+
+/// Downloads and decodes an image
+async fn download_image(client: &reqwest::Client, url: &str) -> Result<DynamicImage, String> {
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .bytes()
+        .await
+        .map_err(|e| e.to_string())?;
+    image::load_from_memory(&bytes).map_err(|e| e.to_string())
+}
+
+/// Reads the card list of a reference archive, parsing it as it is read
+/// rather than loading the whole file first
+pub fn read_archive_manifest(dir: &Path) -> Result<Vec<ScryfallCard>, String> {
+    let reader = std::fs::File::open(dir.join(ARCHIVE_MANIFEST_FILE))
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to read {}: {}", ARCHIVE_MANIFEST_FILE, e))?;
+    Vec::<ScryfallCard>::deserialize(&mut serde_json::Deserializer::from_reader(reader))
+        .map_err(|e| format!("Failed to parse {}: {}", ARCHIVE_MANIFEST_FILE, e))
+}
+
+/// Finds the image file for a card in a reference archive
+pub fn find_archive_image(dir: &Path, scryfall_id: &str) -> Option<PathBuf> {
+    ARCHIVE_IMAGE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", scryfall_id, ext)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::scryfall::Prices;
+
+    #[test]
+    fn test_reference_from_image() {
+        let card = ScryfallCard {
+            id: "test-id".to_string(),
+            oracle_id: None,
+            name: "Test Card".to_string(),
+            lang: None,
+            set: "tst".to_string(),
+            set_name: "Test Set".to_string(),
+            collector_number: "7".to_string(),
+            released_at: String::new(),
+            artist: None,
+            image_uris: None,
+            prices: Prices {
+                usd: None,
+                usd_foil: None,
+                eur: None,
+                eur_foil: None,
+            },
+            rarity: "common".to_string(),
+            similarity: None,
+            match_confidence: None,
+        };
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(146, 204, |x, y| {
+            image::Rgb([(x * 255 / 146) as u8, (y * 255 / 204) as u8, 0])
+        }));
+
+        let reference = reference_from_image(&card, &image, "archive");
+        assert_eq!(reference.scryfall_id, "test-id");
+        assert_eq!(reference.collector_number, "7");
+        assert_eq!(
+            reference.phash,
+            format!("{:x}", crate::card_features::calculate_phash(&image))
+        );
+        assert!(reference.art_phash.is_some());
+        let hashes = CardHashes::from_hex(reference.hashes.as_deref().unwrap()).unwrap();
+        assert_eq!(format!("{:x}", hashes.dhash), reference.phash);
+        assert_eq!(reference.phash_algo.as_deref(), Some(HASH_ALGORITHM));
+        assert_eq!(reference.source, "archive");
+    }
+
+    #[test]
+    fn test_read_archive_manifest() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scryfall");
+        let cards = read_archive_manifest(&dir).unwrap();
+        assert!(cards.iter().any(|card| card.name == "Lightning Bolt"));
+
+        let missing = std::env::temp_dir().join(format!("mtgia-archive-{}", uuid::Uuid::new_v4()));
+        assert!(read_archive_manifest(&missing).is_err());
+    }
+}
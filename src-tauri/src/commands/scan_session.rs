@@ -0,0 +1,385 @@
+use crate::database::operations;
+use crate::hash_index::parse_hash;
+use crate::models::scan_session::{ScanItem, ScanSession};
+use crate::models::scryfall::ScryfallCard;
+use crate::AppState;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+/// Number of recognition candidates kept with each staged scan
+const STAGED_CANDIDATES: usize = 5;
+
+#[derive(serde::Deserialize)]
+pub struct StageScanArgs {
+    pub session_id: String,
+    /// Base64-encoded photo of the card, saved with the session
+    pub image_data: Option<String>,
+    /// Recognition candidates, best first
+    pub candidates: Vec<ScryfallCard>,
+    /// The chosen match, if already known
+    pub selected: Option<ScryfallCard>,
+    pub condition: Option<String>,
+    pub language: Option<String>,
+    pub finish: Option<String>,
+    pub purchase_price: Option<f64>,
+    pub quantity: Option<i32>,
+    pub phash: Option<String>, // Hex string of pHash
+}
+
+/// Starts a new scan session.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `name` - A label for the session, defaults to the current date and time.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The UUID of the new session or an error message.
+#[tauri::command]
+pub async fn create_scan_session(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<String, String> {
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| format!("Scan {}", operations::scan_timestamp()));
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    operations::create_scan_session(&db, &name).map_err(|e| e.to_string())
+}
+
+/// Lists scan sessions, so an open one can be resumed.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `Result<Vec<ScanSession>, String>` - The sessions, most recently updated first.
+#[tauri::command]
+pub async fn get_scan_sessions(state: State<'_, AppState>) -> Result<Vec<ScanSession>, String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    operations::get_scan_sessions(&db).map_err(|e| e.to_string())
+}
+
+/// Lists the staged scans of a session.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `session_id` - The UUID of the session.
+///
+/// # Returns
+///
+/// * `Result<Vec<ScanItem>, String>` - The staged scans in scan order.
+#[tauri::command]
+pub async fn get_scan_items(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ScanItem>, String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    operations::get_scan_items(&db, &session_id).map_err(|e| e.to_string())
+}
+
+/// Stages a scan in an open session, saving its photo and top candidates.
+/// A repeated scan of an already staged card increases that entry's quantity.
+///
+/// # Arguments
+///
+/// * `app` - The application handle, for the app data directory.
+/// * `state` - The application state.
+/// * `args` - The scan to stage.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The UUID of the entry holding the scan or an error message.
+#[tauri::command]
+pub async fn stage_scan(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    args: StageScanArgs,
+) -> Result<String, String> {
+    use base64::prelude::*;
+
+    ensure_open(&state, &args.session_id)?;
+
+    let id = Uuid::new_v4().to_string();
+    let photo = match &args.image_data {
+        Some(data) => {
+            let bytes = BASE64_STANDARD
+                .decode(data)
+                .map_err(|e| format!("Failed to decode base64: {}", e))?;
+            let extension = image::guess_format(&bytes)
+                .ok()
+                .and_then(|format| format.extensions_str().first().copied())
+                .unwrap_or("jpg");
+            let path = session_dir(&app, &args.session_id)?.join(format!("{}.{}", id, extension));
+            Some((path, bytes))
+        }
+        None => None,
+    };
+
+    let mut candidates = args.candidates;
+    candidates.truncate(STAGED_CANDIDATES);
+
+    let item = ScanItem {
+        id,
+        session_id: args.session_id,
+        image_path: photo.as_ref().map(|(path, _)| path.to_string_lossy().to_string()),
+        candidates,
+        selected: args.selected,
+        condition: args.condition.unwrap_or_else(|| "NM".to_string()),
+        language: args.language.unwrap_or_else(|| "English".to_string()),
+        finish: args.finish.unwrap_or_else(|| "nonfoil".to_string()),
+        purchase_price: args.purchase_price.unwrap_or(0.0),
+        quantity: args.quantity.unwrap_or(1).max(1),
+        phash: args.phash,
+        created_at: operations::scan_timestamp(),
+    };
+
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    let entry_id = operations::stage_scan_item(&db, &item).map_err(|e| e.to_string())?;
+
+    // The photo is saved once its entry exists, so a failed insert leaves no
+    // file behind. An entry the scan was merged into keeps its own photo.
+    if let Some((path, bytes)) = photo.filter(|_| entry_id == item.id) {
+        if let Err(e) = save_photo(&path, &bytes) {
+            operations::remove_scan_item(&db, &item.id).map_err(|e| e.to_string())?;
+            return Err(e);
+        }
+    }
+    Ok(entry_id)
+}
+
+/// Writes a scan photo, creating the session's directory if needed
+fn save_photo(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::create_dir_all(path.parent().unwrap_or(path))
+        .map_err(|e| format!("Failed to create scan directory: {}", e))?;
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to save scan: {}", e))
+}
+
+/// Chooses the match of a staged scan, merging it with an entry that already
+/// holds the same printing, condition, language and finish.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `id` - The UUID of the staged scan.
+/// * `card` - The chosen printing.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The UUID of the entry now holding the scan or an error message.
+#[tauri::command]
+pub async fn select_scan_match(
+    state: State<'_, AppState>,
+    id: String,
+    card: ScryfallCard,
+) -> Result<String, String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    let item = operations::get_scan_item(&db, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Scan not found".to_string())?;
+    check_open(&db, &item.session_id)?;
+
+    let entry_id = operations::select_scan_item_match(&db, &id, &card)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Scan not found".to_string())?;
+    if entry_id != id {
+        if let Some(path) = &item.image_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    Ok(entry_id)
+}
+
+/// Updates the quantity and details of a staged scan of an open session.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `id` - The UUID of the staged scan.
+/// * `quantity` - The new quantity.
+/// * `condition` - The new condition.
+/// * `language` - The new language.
+/// * `finish` - The new finish.
+/// * `purchase_price` - The new purchase price.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if successful, or an error message.
+#[tauri::command]
+pub async fn update_scan_item(
+    state: State<'_, AppState>,
+    id: String,
+    quantity: i32,
+    condition: String,
+    language: String,
+    finish: String,
+    purchase_price: f64,
+) -> Result<(), String> {
+    if quantity < 1 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    let item = operations::get_scan_item(&db, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Scan not found".to_string())?;
+    check_open(&db, &item.session_id)?;
+
+    operations::update_scan_item(&db, &id, quantity, &condition, &language, &finish, purchase_price)
+        .map_err(|e| e.to_string())
+}
+
+/// Removes a staged scan of an open session and its photo.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `id` - The UUID of the staged scan.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if successful, or an error message.
+#[tauri::command]
+pub async fn remove_scan_item(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    if let Some(item) = operations::get_scan_item(&db, &id).map_err(|e| e.to_string())? {
+        check_open(&db, &item.session_id)?;
+        operations::remove_scan_item(&db, &id).map_err(|e| e.to_string())?;
+        if let Some(path) = item.image_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
+
+/// Adds every staged scan of a session to the collection in one transaction.
+/// Fails without changes if a scan has no selected match.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `session_id` - The UUID of the session.
+/// * `currency_preference` - The user's currency, as for `add_card`.
+///
+/// # Returns
+///
+/// * `Result<usize, String>` - The number of collection entries added or an error message.
+#[tauri::command]
+pub async fn commit_scan_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    currency_preference: String,
+) -> Result<usize, String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    check_open(&db, &session_id)?;
+
+    let unresolved = operations::get_scan_items(&db, &session_id)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter(|item| item.selected.is_none())
+        .count();
+    if unresolved > 0 {
+        return Err(format!(
+            "{} scans have no selected match, choose one or remove them first",
+            unresolved
+        ));
+    }
+
+    let added = operations::commit_scan_session(&db, &session_id, &currency_preference).map_err(|e| e.to_string())?;
+
+    let mut index = state
+        .phash_index
+        .lock()
+        .map_err(|_| "Failed to lock hash index".to_string())?;
+    for (id, phash) in &added {
+        if let Some(hash) = phash.as_deref().and_then(parse_hash) {
+            index.insert(id.clone(), hash);
+        }
+    }
+    Ok(added.len())
+}
+
+/// Deletes a scan session, its staged scans and their photos.
+///
+/// # Arguments
+///
+/// * `app` - The application handle, for the app data directory.
+/// * `state` - The application state.
+/// * `session_id` - The UUID of the session.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if successful, or an error message.
+#[tauri::command]
+pub async fn delete_scan_session(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<(), String> {
+    {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| "Failed to lock db".to_string())?;
+        operations::delete_scan_session(&db, &session_id).map_err(|e| e.to_string())?;
+    }
+
+    let dir = session_dir(&app, &session_id)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete scans: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Directory holding the photos of a session
+fn session_dir(app: &AppHandle, session_id: &str) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("scans").join(session_id))
+}
+
+fn ensure_open(state: &AppState, session_id: &str) -> Result<(), String> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| "Failed to lock db".to_string())?;
+    check_open(&db, session_id)
+}
+
+fn check_open(conn: &rusqlite::Connection, session_id: &str) -> Result<(), String> {
+    match operations::get_scan_session_status(conn, session_id)
+        .map_err(|e| e.to_string())?
+        .as_deref()
+    {
+        Some("open") => Ok(()),
+        Some(_) => Err("Scan session is already committed".to_string()),
+        None => Err("Scan session not found".to_string()),
+    }
+}
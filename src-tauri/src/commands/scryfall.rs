@@ -1,16 +1,15 @@
 use tauri::{AppHandle, State};
 
 use crate::database::operations;
-use crate::services::scryfall::ScryfallService;
 use crate::AppState;
 
 use tauri::Emitter;
 
 #[derive(Clone, serde::Serialize)]
-struct ProgressPayload {
-    current: usize,
-    total: usize,
-    message: String,
+pub(crate) struct ProgressPayload {
+    pub current: usize,
+    pub total: usize,
+    pub message: String,
 }
 
 /// Imports all sets from Scryfall into the local database.
@@ -26,8 +25,7 @@ struct ProgressPayload {
 /// * `Result<String, String>` - A summary message or an error string.
 #[tauri::command]
 pub async fn import_sets(app: AppHandle, state: State<'_, AppState>) -> Result<String, String> {
-    let service = ScryfallService::new();
-    let sets = service.fetch_sets().await.map_err(|e| e.to_string())?;
+    let sets = state.card_data.fetch_sets().await.map_err(|e| e.to_string())?;
 
     let db = state
         .db
@@ -91,8 +89,7 @@ pub async fn get_sets(
 
     // If database is empty, fetch from Scryfall
     if is_empty {
-        let service = ScryfallService::new();
-        let scryfall_sets = service.fetch_sets().await.map_err(|e| e.to_string())?;
+        let scryfall_sets = state.card_data.fetch_sets().await.map_err(|e| e.to_string())?;
 
         // Store in database
         {
@@ -1,12 +1,16 @@
-use crate::services::scryfall::ScryfallService;
+use crate::commands::scryfall::ProgressPayload;
+use crate::database::operations;
+use crate::set_symbols::render_set_icon;
 use crate::AppState;
-use tauri::State;
+use reqwest::header::CONTENT_TYPE;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, State};
 
 /// Fetches cards from a specific set with pagination.
 ///
 /// # Arguments
 ///
-/// * `_state` - The application state (unused).
+/// * `state` - The application state.
 /// * `set_code` - The set code (e.g., "dom").
 /// * `page` - The page number (1-indexed).
 ///
@@ -15,13 +19,150 @@ use tauri::State;
 /// * `Result<ScryfallCardList, String>` - A paginated list of cards or an error message.
 #[tauri::command]
 pub async fn get_set_cards(
-    _state: State<'_, AppState>,
+    state: State<'_, AppState>,
     set_code: String,
     page: u32,
 ) -> Result<crate::models::scryfall::ScryfallCardList, String> {
-    let service = ScryfallService::new();
-    service
+    state
+        .card_data
         .fetch_cards_by_set(&set_code, page)
         .await
         .map_err(|e| e.to_string())
 }
+
+/// Downloads and renders the icon of every cached set that doesn't have a
+/// symbol mask yet, for recognizing a card's set from its expansion symbol.
+/// Emits `symbol-progress` events to the frontend.
+///
+/// # Arguments
+///
+/// * `app` - The application handle to emit events.
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - A summary message or an error string.
+#[tauri::command]
+pub async fn cache_set_symbols(app: AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    let sets = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| "Failed to lock db".to_string())?;
+        operations::get_sets_without_symbols(&db).map_err(|e| e.to_string())?
+    };
+
+    let client = reqwest::Client::new();
+    let total = sets.len();
+    let mut success_count = 0;
+    let mut fail_count = 0;
+    // Token and promo sets share their parent's icon, so render each icon once
+    let mut rendered: HashMap<String, Vec<u8>> = HashMap::new();
+
+    for (i, (set_code, icon_uri)) in sets.iter().enumerate() {
+        let mask = match rendered.get(icon_uri) {
+            Some(mask) => Some(mask.clone()),
+            None => match download_icon(&client, icon_uri).await {
+                Ok(svg) => match render_set_icon(&svg) {
+                    Ok(mask) => {
+                        let bytes = mask.to_bytes();
+                        rendered.insert(icon_uri.clone(), bytes.clone());
+                        Some(bytes)
+                    }
+                    Err(e) => {
+                        println!("Failed to render icon for {}: {}", set_code, e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    println!("Failed to download icon for {}: {}", set_code, e);
+                    None
+                }
+            },
+        };
+
+        match mask {
+            Some(bytes) => {
+                let db = state
+                    .db
+                    .lock()
+                    .map_err(|_| "Failed to lock db".to_string())?;
+                operations::upsert_set_symbol(&db, set_code, icon_uri, &bytes)
+                    .map_err(|e| e.to_string())?;
+                if let Some(mask) = crate::set_symbols::SymbolMask::from_bytes(&bytes) {
+                    state
+                        .set_symbols
+                        .lock()
+                        .map_err(|_| "Failed to lock set symbols".to_string())?
+                        .insert(set_code.clone(), mask);
+                }
+                success_count += 1;
+            }
+            None => fail_count += 1,
+        }
+
+        if i % 10 == 0 || i == total - 1 {
+            app.emit(
+                "symbol-progress",
+                ProgressPayload {
+                    current: i + 1,
+                    total,
+                    message: format!("Caching set symbol: {}", set_code),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(format!("Cached {} set symbols, failed {}", success_count, fail_count))
+}
+
+/// Downloads a set icon SVG. Error pages are rejected, so they are never
+/// rendered and stored as the set's symbol.
+async fn download_icon(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .error_for_status()
+        .map_err(|e| e.to_string())?;
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    if !is_svg(content_type.as_deref(), &bytes) {
+        return Err(format!("Not an SVG image ({})", content_type.as_deref().unwrap_or("no content type")));
+    }
+    Ok(bytes.to_vec())
+}
+
+/// Whether a response is an SVG image, by its content type or, when the
+/// server sends a generic one, by its first tag
+fn is_svg(content_type: Option<&str>, body: &[u8]) -> bool {
+    if content_type.is_some_and(|value| value.starts_with("image/svg")) {
+        return true;
+    }
+    if content_type.is_some_and(|value| !value.starts_with("application/octet-stream") && !value.contains("xml")) {
+        return false;
+    }
+    let head = String::from_utf8_lossy(&body[..body.len().min(512)]).to_lowercase();
+    head.trim_start().starts_with("<svg") || (head.contains("<?xml") && head.contains("<svg"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_svg() {
+        let svg = br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"></svg>"#;
+        assert!(is_svg(Some("image/svg+xml"), svg));
+        assert!(is_svg(None, svg));
+        assert!(is_svg(Some("application/octet-stream"), b"<svg></svg>"));
+        assert!(!is_svg(Some("text/html; charset=utf-8"), b"<html><body>Not Found</body></html>"));
+        assert!(!is_svg(None, b"<!DOCTYPE html><html></html>"));
+    }
+}
@@ -203,6 +203,113 @@ pub fn get_all_cards(conn: &Connection) -> Result<Vec<crate::models::collection:
     Ok(cards)
 }
 
+/// Retrieves a single collection card by its UUID, without tags.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the card.
+///
+/// # Returns
+///
+/// * `Result<Option<CollectionCard>>` - The card, or None if it doesn't exist.
+pub fn get_card_by_id(
+    conn: &Connection,
+    id: &str,
+) -> Result<Option<crate::models::collection::CollectionCard>> {
+    conn.query_row(
+        "SELECT id, scryfall_id, name, set_code, collector_number, condition, purchase_price, current_price, quantity, is_foil, image_uri, language, finish, phash
+         FROM cards WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(crate::models::collection::CollectionCard {
+                id: row.get(0)?,
+                scryfall_id: row.get(1)?,
+                name: row.get(2)?,
+                set_code: row.get(3)?,
+                collector_number: row.get(4)?,
+                condition: row.get(5)?,
+                purchase_price: row.get(6)?,
+                current_price: row.get(7)?,
+                quantity: row.get(8)?,
+                is_foil: row.get(9)?,
+                image_uri: row.get(10)?,
+                language: row.get(11)?,
+                finish: row
+                    .get::<_, Option<String>>(12)?
+                    .unwrap_or_else(|| "nonfoil".to_string()),
+                tags: None,
+                phash: row.get(13)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// Retrieves the perceptual hash of every collection card that has one.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, String)>>` - Pairs of card UUID and hex hash.
+pub fn get_card_hashes(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT id, phash FROM cards WHERE phash IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Retrieves the full hash set of a collection card, if it was computed with
+/// the current hash algorithm.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the card.
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The encoded hash set, or None if missing or outdated.
+pub fn get_card_hash_set(conn: &Connection, id: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT hashes FROM cards WHERE id = ?1 AND phash_algo = ?2",
+        params![id, crate::card_hash::HASH_ALGORITHM],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+/// Stores freshly computed hashes for a collection card.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the card.
+/// * `hashes` - The card's hash set.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn update_card_hashes(
+    conn: &Connection,
+    id: &str,
+    hashes: &crate::card_hash::CardHashes,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE cards SET phash = ?1, hashes = ?2, phash_algo = ?3 WHERE id = ?4",
+        params![
+            format!("{:x}", hashes.dhash),
+            hashes.to_hex(),
+            crate::card_hash::HASH_ALGORITHM,
+            id
+        ],
+    )?;
+    Ok(())
+}
+
 /// Retrieves cards from the collection with filtering and sorting.
 ///
 /// # Arguments
@@ -396,6 +503,27 @@ pub fn insert_price_history(
     Ok(())
 }
 
+/// Updates the current price of many cards and records each in the price
+/// history, in one transaction.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `prices` - The UUID and new price of each card.
+/// * `currency` - The currency code (e.g., "USD", "EUR").
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn update_card_prices(conn: &Connection, prices: &[(String, f64)], currency: &str) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    for (card_id, price) in prices {
+        update_card_price(&tx, card_id, *price)?;
+        insert_price_history(&tx, card_id, *price, currency)?;
+    }
+    tx.commit()
+}
+
 /// Updates details of an existing card (condition, language, purchase price, finish).
 ///
 /// # Arguments
@@ -717,6 +845,63 @@ pub fn get_collection_sets(conn: &Connection) -> Result<Vec<String>> {
     Ok(sets)
 }
 
+// ============ Set Symbol Operations ============
+
+/// Stores the rendered symbol mask of a set's icon.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `set_code` - The set code.
+/// * `icon_uri` - The icon the mask was rendered from.
+/// * `mask` - The serialized symbol mask.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn upsert_set_symbol(conn: &Connection, set_code: &str, icon_uri: &str, mask: &[u8]) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO set_symbols (set_code, icon_uri, mask) VALUES (?1, ?2, ?3)",
+        params![set_code, icon_uri, mask],
+    )?;
+    Ok(())
+}
+
+/// Retrieves every cached set symbol mask.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, Vec<u8>)>>` - Pairs of set code and serialized mask.
+pub fn get_set_symbols(conn: &Connection) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut stmt = conn.prepare("SELECT set_code, mask FROM set_symbols")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Retrieves the sets whose icon hasn't been rendered yet, or has changed since.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, String)>>` - Pairs of set code and icon URI.
+pub fn get_sets_without_symbols(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.code, s.icon_uri FROM sets s
+         LEFT JOIN set_symbols sym ON sym.set_code = s.code
+         WHERE s.icon_uri IS NOT NULL AND s.icon_uri != ''
+         AND (sym.set_code IS NULL OR sym.icon_uri != s.icon_uri)",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 // ============ Tag Operations ============
 
 // Tag struct moved to models::tags::Tag
@@ -792,6 +977,686 @@ pub fn get_card_tags(conn: &Connection, card_id: &str) -> Result<Vec<crate::mode
     Ok(tags)
 }
 
+// ============ Reference Hash Operations ============
+
+/// Inserts or replaces the reference hashes of a printing.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `reference` - The printing and its hashes.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn upsert_reference_hash(
+    conn: &Connection,
+    reference: &crate::models::reference::ReferenceHash,
+) -> Result<()> {
+    let updated_at = chrono::Local::now().format("%Y-%m-%d").to_string();
+    conn.execute(
+        "INSERT OR REPLACE INTO reference_hashes (scryfall_id, name, set_code, collector_number, image_uri, phash, art_phash, hashes, phash_algo, source, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            reference.scryfall_id,
+            reference.name,
+            reference.set_code,
+            reference.collector_number,
+            reference.image_uri,
+            reference.phash,
+            reference.art_phash,
+            reference.hashes,
+            reference.phash_algo,
+            reference.source,
+            updated_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the reference hashes of a single printing.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `scryfall_id` - The Scryfall ID of the printing.
+///
+/// # Returns
+///
+/// * `Result<Option<ReferenceHash>>` - The reference, or None if it hasn't been hashed.
+pub fn get_reference_hash(
+    conn: &Connection,
+    scryfall_id: &str,
+) -> Result<Option<crate::models::reference::ReferenceHash>> {
+    conn.query_row(
+        "SELECT scryfall_id, name, set_code, collector_number, image_uri, phash, art_phash, hashes, phash_algo, source
+         FROM reference_hashes WHERE scryfall_id = ?1",
+        params![scryfall_id],
+        reference_hash_from_row,
+    )
+    .optional()
+}
+
+/// Retrieves a printing from the reference catalog by set code and collector number.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `set_code` - The set code (case-insensitive).
+/// * `collector_number` - The collector number.
+///
+/// # Returns
+///
+/// * `Result<Option<ReferenceHash>>` - The reference, or None if the printing isn't in the catalog.
+pub fn get_reference_by_printing(
+    conn: &Connection,
+    set_code: &str,
+    collector_number: &str,
+) -> Result<Option<crate::models::reference::ReferenceHash>> {
+    conn.query_row(
+        "SELECT scryfall_id, name, set_code, collector_number, image_uri, phash, art_phash, hashes, phash_algo, source
+         FROM reference_hashes WHERE set_code = ?1 COLLATE NOCASE AND collector_number = ?2
+         LIMIT 1",
+        params![set_code, collector_number],
+        reference_hash_from_row,
+    )
+    .optional()
+}
+
+fn reference_hash_from_row(row: &rusqlite::Row) -> Result<crate::models::reference::ReferenceHash> {
+    Ok(crate::models::reference::ReferenceHash {
+        scryfall_id: row.get(0)?,
+        name: row.get(1)?,
+        set_code: row.get(2)?,
+        collector_number: row.get(3)?,
+        image_uri: row.get(4)?,
+        phash: row.get(5)?,
+        art_phash: row.get(6)?,
+        hashes: row.get(7)?,
+        phash_algo: row.get(8)?,
+        source: row.get(9)?,
+    })
+}
+
+/// Retrieves the hashes of every reference printing, for building the in-memory index.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, String, Option<String>)>>` - Scryfall ID, card hash and art hash.
+pub fn get_reference_hash_entries(
+    conn: &Connection,
+) -> Result<Vec<(String, String, Option<String>)>> {
+    let mut stmt = conn.prepare("SELECT scryfall_id, phash, art_phash FROM reference_hashes")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    rows.collect()
+}
+
+/// Returns which printings of a set already have up-to-date reference hashes.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `set_code` - The set to check.
+///
+/// # Returns
+///
+/// * `Result<HashSet<String>>` - The Scryfall IDs in the set hashed with the current algorithm.
+pub fn get_hashed_reference_ids(
+    conn: &Connection,
+    set_code: &str,
+) -> Result<std::collections::HashSet<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT scryfall_id FROM reference_hashes WHERE set_code = ?1 AND phash_algo = ?2",
+    )?;
+    let rows = stmt.query_map(params![set_code, crate::card_hash::HASH_ALGORITHM], |row| {
+        row.get(0)
+    })?;
+    rows.collect()
+}
+
+// ============ Image Cache Operations ============
+
+/// Records which cached image a URL was downloaded as.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `url` - The URL the image was downloaded from.
+/// * `digest` - The SHA-256 of the image, its name in the cache.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn insert_cached_image(conn: &Connection, url: &str, digest: &str) -> Result<()> {
+    let fetched_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.execute(
+        "INSERT OR REPLACE INTO image_cache (url, digest, fetched_at) VALUES (?1, ?2, ?3)",
+        params![url, digest, fetched_at],
+    )?;
+    Ok(())
+}
+
+/// Looks up the cached image of a URL.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `url` - The image URL.
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The digest of the cached image, or None if it was never downloaded.
+pub fn get_cached_image_digest(conn: &Connection, url: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT digest FROM image_cache WHERE url = ?1",
+        params![url],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// ============ Recognition Feedback Operations ============
+
+/// Records a user photo as confirming or rejecting a printing.
+/// Recording the same photo and verdict twice is a no-op.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `card` - The printing the verdict is about.
+/// * `hashes` - The hashes of the user's photo.
+/// * `confirmed` - True if the user picked the printing, false if it was a wrong match.
+///
+/// # Returns
+///
+/// * `Result<Option<i64>>` - The ID of the new feedback row, or None if it was already recorded.
+pub fn insert_recognition_feedback(
+    conn: &Connection,
+    card: &ScryfallCard,
+    hashes: &crate::card_hash::CardHashes,
+    confirmed: bool,
+) -> Result<Option<i64>> {
+    let image_uri = card.image_uris.as_ref().map(|u| u.normal.clone());
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO recognition_feedback (scryfall_id, name, set_code, collector_number, image_uri, phash, hashes, phash_algo, confirmed, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            card.id,
+            card.name,
+            card.set,
+            card.collector_number,
+            image_uri,
+            format!("{:x}", hashes.dhash),
+            hashes.to_hex(),
+            crate::card_hash::HASH_ALGORITHM,
+            confirmed,
+            chrono::Local::now().format("%Y-%m-%d").to_string()
+        ],
+    )?;
+    Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+}
+
+/// Retrieves a single feedback row.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The ID of the feedback row.
+///
+/// # Returns
+///
+/// * `Result<Option<RecognitionFeedback>>` - The feedback, or None if not found.
+pub fn get_recognition_feedback(
+    conn: &Connection,
+    id: i64,
+) -> Result<Option<crate::models::feedback::RecognitionFeedback>> {
+    conn.query_row(
+        "SELECT id, scryfall_id, name, set_code, collector_number, image_uri, phash, hashes, phash_algo, confirmed, created_at
+         FROM recognition_feedback WHERE id = ?1",
+        params![id],
+        feedback_from_row,
+    )
+    .optional()
+}
+
+/// Retrieves all feedback about a printing, for tuning its acceptance threshold.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `scryfall_id` - The Scryfall ID of the printing.
+///
+/// # Returns
+///
+/// * `Result<Vec<RecognitionFeedback>>` - Confirmed and rejected photos of the printing.
+pub fn get_printing_feedback(
+    conn: &Connection,
+    scryfall_id: &str,
+) -> Result<Vec<crate::models::feedback::RecognitionFeedback>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, scryfall_id, name, set_code, collector_number, image_uri, phash, hashes, phash_algo, confirmed, created_at
+         FROM recognition_feedback WHERE scryfall_id = ?1",
+    )?;
+    let rows = stmt.query_map(params![scryfall_id], feedback_from_row)?;
+    rows.collect()
+}
+
+/// Retrieves the dHash of every feedback photo, for building the in-memory index.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<(i64, String, String, bool)>>` - (ID, Scryfall ID, hex dHash, confirmed) rows.
+pub fn get_feedback_entries(conn: &Connection) -> Result<Vec<(i64, String, String, bool)>> {
+    let mut stmt =
+        conn.prepare("SELECT id, scryfall_id, phash, confirmed FROM recognition_feedback")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+    rows.collect()
+}
+
+fn feedback_from_row(row: &rusqlite::Row) -> Result<crate::models::feedback::RecognitionFeedback> {
+    Ok(crate::models::feedback::RecognitionFeedback {
+        id: row.get(0)?,
+        scryfall_id: row.get(1)?,
+        name: row.get(2)?,
+        set_code: row.get(3)?,
+        collector_number: row.get(4)?,
+        image_uri: row.get(5)?,
+        phash: row.get(6)?,
+        hashes: row.get(7)?,
+        phash_algo: row.get(8)?,
+        confirmed: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+// ============ Scan Session Operations ============
+
+/// Creates a new open scan session.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `name` - A label for the session (e.g. "Binder 3").
+///
+/// # Returns
+///
+/// * `Result<String>` - The UUID of the new session.
+pub fn create_scan_session(conn: &Connection, name: &str) -> Result<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = scan_timestamp();
+    conn.execute(
+        "INSERT INTO scan_sessions (id, name, status, created_at, updated_at)
+         VALUES (?1, ?2, 'open', ?3, ?3)",
+        params![id, name, now],
+    )?;
+    Ok(id)
+}
+
+/// Retrieves all scan sessions with their entry and card counts, most recently updated first.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+///
+/// # Returns
+///
+/// * `Result<Vec<ScanSession>>` - The sessions.
+pub fn get_scan_sessions(conn: &Connection) -> Result<Vec<crate::models::scan_session::ScanSession>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.name, s.status, s.created_at, s.updated_at,
+                COUNT(i.id), COALESCE(SUM(i.quantity), 0)
+         FROM scan_sessions s
+         LEFT JOIN scan_items i ON i.session_id = s.id
+         GROUP BY s.id
+         ORDER BY s.updated_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(crate::models::scan_session::ScanSession {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            status: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            item_count: row.get(5)?,
+            card_count: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Retrieves the status of a scan session.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `session_id` - The UUID of the session.
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The status, or None if the session doesn't exist.
+pub fn get_scan_session_status(conn: &Connection, session_id: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT status FROM scan_sessions WHERE id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Retrieves the staged scans of a session in the order they were scanned.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `session_id` - The UUID of the session.
+///
+/// # Returns
+///
+/// * `Result<Vec<ScanItem>>` - The staged scans.
+pub fn get_scan_items(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Vec<crate::models::scan_session::ScanItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, image_path, candidates, selected_card, condition, language, finish, purchase_price, quantity, phash, created_at
+         FROM scan_items WHERE session_id = ?1
+         ORDER BY created_at, rowid",
+    )?;
+    let rows = stmt.query_map(params![session_id], scan_item_from_row)?;
+    rows.collect()
+}
+
+/// Retrieves a single staged scan.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the staged scan.
+///
+/// # Returns
+///
+/// * `Result<Option<ScanItem>>` - The staged scan, or None if not found.
+pub fn get_scan_item(conn: &Connection, id: &str) -> Result<Option<crate::models::scan_session::ScanItem>> {
+    conn.query_row(
+        "SELECT id, session_id, image_path, candidates, selected_card, condition, language, finish, purchase_price, quantity, phash, created_at
+         FROM scan_items WHERE id = ?1",
+        params![id],
+        scan_item_from_row,
+    )
+    .optional()
+}
+
+/// Stages a scan in a session. If the session already holds the same selected
+/// printing with the same condition, language and finish, that entry's
+/// quantity is increased instead.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `item` - The scan to stage (its `id` is used for a new entry).
+///
+/// # Returns
+///
+/// * `Result<String>` - The UUID of the entry holding the scan.
+pub fn stage_scan_item(conn: &Connection, item: &crate::models::scan_session::ScanItem) -> Result<String> {
+    if let Some(existing) = find_mergeable_scan_item(conn, item)? {
+        conn.execute(
+            "UPDATE scan_items SET quantity = quantity + ?1 WHERE id = ?2",
+            params![item.quantity, existing],
+        )?;
+        touch_scan_session(conn, &item.session_id)?;
+        return Ok(existing);
+    }
+
+    conn.execute(
+        "INSERT INTO scan_items (id, session_id, image_path, candidates, scryfall_id, selected_card, condition, language, finish, purchase_price, quantity, phash, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            item.id,
+            item.session_id,
+            item.image_path,
+            to_json(&item.candidates)?,
+            item.selected.as_ref().map(|card| card.id.clone()),
+            item.selected.as_ref().map(to_json).transpose()?,
+            item.condition,
+            item.language,
+            item.finish,
+            item.purchase_price,
+            item.quantity,
+            item.phash,
+            item.created_at
+        ],
+    )?;
+    touch_scan_session(conn, &item.session_id)?;
+    Ok(item.id.clone())
+}
+
+/// Sets the chosen match of a staged scan. If another entry of the session
+/// already holds that printing with the same condition, language and finish,
+/// the two are merged into the older one.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the staged scan.
+/// * `card` - The chosen printing.
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The UUID of the entry now holding the scan, or
+///   None if the staged scan doesn't exist.
+pub fn select_scan_item_match(conn: &Connection, id: &str, card: &ScryfallCard) -> Result<Option<String>> {
+    let Some(mut item) = get_scan_item(conn, id)? else {
+        return Ok(None);
+    };
+    item.selected = Some(card.clone());
+
+    if let Some(existing) = find_mergeable_scan_item(conn, &item)? {
+        conn.execute(
+            "UPDATE scan_items SET quantity = quantity + ?1 WHERE id = ?2",
+            params![item.quantity, existing],
+        )?;
+        conn.execute("DELETE FROM scan_items WHERE id = ?1", params![id])?;
+        touch_scan_session(conn, &item.session_id)?;
+        return Ok(Some(existing));
+    }
+
+    conn.execute(
+        "UPDATE scan_items SET scryfall_id = ?1, selected_card = ?2 WHERE id = ?3",
+        params![card.id, to_json(card)?, id],
+    )?;
+    touch_scan_session(conn, &item.session_id)?;
+    Ok(Some(id.to_string()))
+}
+
+/// Updates the quantity and details of a staged scan.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the staged scan.
+/// * `quantity` - The new quantity.
+/// * `condition` - The new condition.
+/// * `language` - The new language.
+/// * `finish` - The new finish.
+/// * `purchase_price` - The new purchase price.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn update_scan_item(
+    conn: &Connection,
+    id: &str,
+    quantity: i32,
+    condition: &str,
+    language: &str,
+    finish: &str,
+    purchase_price: f64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE scan_items SET quantity = ?1, condition = ?2, language = ?3, finish = ?4, purchase_price = ?5 WHERE id = ?6",
+        params![quantity, condition, language, finish, purchase_price, id],
+    )?;
+    conn.execute(
+        "UPDATE scan_sessions SET updated_at = ?1 WHERE id = (SELECT session_id FROM scan_items WHERE id = ?2)",
+        params![scan_timestamp(), id],
+    )?;
+    Ok(())
+}
+
+/// Removes a staged scan.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `id` - The UUID of the staged scan.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn remove_scan_item(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute("DELETE FROM scan_items WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Adds every staged scan of a session to the collection in one transaction
+/// and marks the session as committed. Nothing is written if any insert fails.
+/// Scans without a selected match must be resolved or removed first, and
+/// fail the commit otherwise.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `session_id` - The UUID of the session.
+/// * `currency` - The user's currency preference, as for `insert_card`.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, Option<String>)>>` - The UUID and pHash of every card added.
+pub fn commit_scan_session(
+    conn: &Connection,
+    session_id: &str,
+    currency: &str,
+) -> Result<Vec<(String, Option<String>)>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut added = Vec::new();
+
+    for item in get_scan_items(&tx, session_id)? {
+        let Some(card) = &item.selected else {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+                Some(format!("Scan {} has no selected match", item.id)),
+            ));
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        let args = AddCardArgs {
+            scryfall_id: card.id.clone(),
+            condition: item.condition,
+            purchase_price: item.purchase_price,
+            quantity: item.quantity,
+            is_foil: item.finish != "nonfoil",
+            language: item.language,
+            finish: Some(item.finish),
+            tags: None,
+            phash: item.phash.clone(),
+        };
+        insert_card(&tx, &id, card, &args, currency)?;
+        added.push((id, item.phash));
+    }
+
+    tx.execute(
+        "UPDATE scan_sessions SET status = 'committed', updated_at = ?1 WHERE id = ?2",
+        params![scan_timestamp(), session_id],
+    )?;
+    tx.commit()?;
+    Ok(added)
+}
+
+/// Deletes a scan session and its staged scans.
+///
+/// # Arguments
+///
+/// * `conn` - A reference to the database connection.
+/// * `session_id` - The UUID of the session.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if successful, Err otherwise.
+pub fn delete_scan_session(conn: &Connection, session_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM scan_items WHERE session_id = ?1", params![session_id])?;
+    conn.execute("DELETE FROM scan_sessions WHERE id = ?1", params![session_id])?;
+    Ok(())
+}
+
+/// Finds another entry of the item's session holding the same selected
+/// printing, condition, language and finish
+fn find_mergeable_scan_item(
+    conn: &Connection,
+    item: &crate::models::scan_session::ScanItem,
+) -> Result<Option<String>> {
+    let Some(card) = &item.selected else {
+        return Ok(None);
+    };
+    conn.query_row(
+        "SELECT id FROM scan_items
+         WHERE session_id = ?1 AND scryfall_id = ?2 AND condition = ?3 AND language = ?4 AND finish = ?5 AND id != ?6
+         ORDER BY created_at, rowid LIMIT 1",
+        params![item.session_id, card.id, item.condition, item.language, item.finish, item.id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn touch_scan_session(conn: &Connection, session_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE scan_sessions SET updated_at = ?1 WHERE id = ?2",
+        params![scan_timestamp(), session_id],
+    )?;
+    Ok(())
+}
+
+/// Timestamp with seconds, so sessions and scans sort in the order they happened
+pub fn scan_timestamp() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(column: usize, json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn scan_item_from_row(row: &rusqlite::Row) -> Result<crate::models::scan_session::ScanItem> {
+    let candidates: String = row.get(3)?;
+    let selected: Option<String> = row.get(4)?;
+    Ok(crate::models::scan_session::ScanItem {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        image_path: row.get(2)?,
+        candidates: from_json(3, &candidates)?,
+        selected: selected.as_deref().map(|json| from_json(4, json)).transpose()?,
+        condition: row.get(5)?,
+        language: row.get(6)?,
+        finish: row.get(7)?,
+        purchase_price: row.get(8)?,
+        quantity: row.get(9)?,
+        phash: row.get(10)?,
+        created_at: row.get(11)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -831,6 +1696,7 @@ mod tests {
                 eur_foil: Some("18.00".to_string()),
             },
             similarity: None,
+            match_confidence: None,
         }
     }
 
@@ -1004,6 +1870,48 @@ mod tests {
         assert_eq!(cards.len(), 0);
     }
 
+    #[test]
+    fn test_get_card_hashes_and_by_id() {
+        let conn = setup_test_db();
+        insert_test_set(&conn);
+        let card = create_test_card();
+        let mut args = AddCardArgs {
+            scryfall_id: card.id.clone(),
+            condition: "NM".to_string(),
+            purchase_price: 10.0,
+            quantity: 1,
+            is_foil: false,
+            language: "English".to_string(),
+            finish: None,
+            tags: None,
+            phash: Some("ff00ff00ff00ff00".to_string()),
+        };
+        insert_card(&conn, "hashed", &card, &args, "USD").unwrap();
+        args.phash = None;
+        insert_card(&conn, "unhashed", &card, &args, "USD").unwrap();
+
+        let hashes = get_card_hashes(&conn).unwrap();
+        assert_eq!(
+            hashes,
+            vec![("hashed".to_string(), "ff00ff00ff00ff00".to_string())]
+        );
+
+        let found = get_card_by_id(&conn, "hashed").unwrap().unwrap();
+        assert_eq!(found.name, card.name);
+        assert!(get_card_by_id(&conn, "missing").unwrap().is_none());
+
+        // Hashes from before multi-algorithm hashing count as outdated
+        assert!(get_card_hash_set(&conn, "hashed").unwrap().is_none());
+        let image = image::DynamicImage::new_rgb8(32, 32);
+        let hashes = crate::card_hash::CardHashes::compute(&image);
+        update_card_hashes(&conn, "unhashed", &hashes).unwrap();
+        assert_eq!(
+            get_card_hash_set(&conn, "unhashed").unwrap(),
+            Some(hashes.to_hex())
+        );
+        assert_eq!(get_card_hashes(&conn).unwrap().len(), 2);
+    }
+
     #[test]
     fn test_remove_card_with_price_history() {
         let conn = setup_test_db();
@@ -1122,6 +2030,39 @@ mod tests {
         assert_eq!(latest_price, 20.0);
     }
 
+    #[test]
+    fn test_update_card_prices() {
+        let conn = setup_test_db();
+        insert_test_set(&conn);
+        let card = create_test_card();
+        let args = AddCardArgs {
+            scryfall_id: card.id.clone(),
+            condition: "NM".to_string(),
+            purchase_price: 10.0,
+            quantity: 1,
+            is_foil: false,
+            language: "English".to_string(),
+            finish: None,
+            tags: None,
+            phash: None,
+        };
+        insert_card(&conn, "uuid-1", &card, &args, "USD").unwrap();
+        insert_card(&conn, "uuid-2", &card, &args, "USD").unwrap();
+
+        let prices = vec![("uuid-1".to_string(), 12.0), ("uuid-2".to_string(), 14.0)];
+        update_card_prices(&conn, &prices, "USD").unwrap();
+        update_card_prices(&conn, &prices[..1], "USD").unwrap();
+
+        let current: f64 = conn
+            .query_row("SELECT current_price FROM cards WHERE id = 'uuid-2'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(current, 14.0);
+        let history: i64 = conn
+            .query_row("SELECT COUNT(*) FROM price_history", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(history, 2);
+    }
+
     #[test]
     fn test_update_card_details() {
         let conn = setup_test_db();
@@ -1404,4 +2345,165 @@ mod tests {
         assert_eq!(results.len(), 2);
         assert_eq!(results[0].name, "Blue Lotus"); // 2 > 1
     }
+
+    #[test]
+    fn test_reference_hash_upsert() {
+        let conn = setup_test_db();
+        let mut reference = crate::models::reference::ReferenceHash {
+            scryfall_id: "ref-1".to_string(),
+            name: "Test Card".to_string(),
+            set_code: "tst".to_string(),
+            collector_number: "1".to_string(),
+            image_uri: None,
+            phash: "00ff".to_string(),
+            art_phash: None,
+            hashes: None,
+            phash_algo: None,
+            source: "search".to_string(),
+        };
+        upsert_reference_hash(&conn, &reference).unwrap();
+
+        reference.art_phash = Some("ff00".to_string());
+        reference.phash_algo = Some(crate::card_hash::HASH_ALGORITHM.to_string());
+        reference.source = "scryfall".to_string();
+        upsert_reference_hash(&conn, &reference).unwrap();
+
+        assert_eq!(get_reference_hash(&conn, "ref-1").unwrap(), Some(reference.clone()));
+        assert_eq!(get_reference_by_printing(&conn, "TST", "1").unwrap(), Some(reference));
+        assert_eq!(get_reference_by_printing(&conn, "tst", "2").unwrap(), None);
+        assert_eq!(
+            get_reference_hash_entries(&conn).unwrap(),
+            vec![("ref-1".to_string(), "00ff".to_string(), Some("ff00".to_string()))]
+        );
+        assert!(get_hashed_reference_ids(&conn, "tst").unwrap().contains("ref-1"));
+        assert!(get_hashed_reference_ids(&conn, "other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_symbol_operations() {
+        let conn = setup_test_db();
+        insert_test_set(&conn); // No icon, never listed
+        let set = ScryfallSet {
+            id: "icon-set-id".to_string(),
+            code: "ico".to_string(),
+            name: "Icon Set".to_string(),
+            released_at: Some("2024-01-01".to_string()),
+            icon_svg_uri: Some("https://example.com/ico.svg".to_string()),
+            set_type: None,
+            card_count: None,
+        };
+        insert_set(&conn, &set).unwrap();
+        assert_eq!(get_sets_without_symbols(&conn).unwrap().len(), 1);
+
+        let (code, icon_uri) = get_sets_without_symbols(&conn).unwrap().remove(0);
+        upsert_set_symbol(&conn, &code, &icon_uri, &[1, 2, 3]).unwrap();
+        assert!(get_sets_without_symbols(&conn).unwrap().is_empty());
+        assert_eq!(get_set_symbols(&conn).unwrap(), vec![(code.clone(), vec![1, 2, 3])]);
+
+        // A changed icon needs rendering again
+        upsert_set_symbol(&conn, &code, "https://example.com/old.svg", &[1, 2, 3]).unwrap();
+        assert_eq!(get_sets_without_symbols(&conn).unwrap().len(), 1);
+    }
+
+    fn staged_scan(session_id: &str, id: &str, selected: Option<ScryfallCard>) -> crate::models::scan_session::ScanItem {
+        crate::models::scan_session::ScanItem {
+            id: id.to_string(),
+            session_id: session_id.to_string(),
+            image_path: None,
+            candidates: vec![create_test_card()],
+            selected,
+            condition: "NM".to_string(),
+            language: "English".to_string(),
+            finish: "nonfoil".to_string(),
+            purchase_price: 0.0,
+            quantity: 1,
+            phash: Some("00ff".to_string()),
+            created_at: scan_timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_scan_session_merges_repeated_scans() {
+        let conn = setup_test_db();
+        let session = create_scan_session(&conn, "Binder 1").unwrap();
+        let card = create_test_card();
+
+        let first = stage_scan_item(&conn, &staged_scan(&session, "scan-1", Some(card.clone()))).unwrap();
+        let second = stage_scan_item(&conn, &staged_scan(&session, "scan-2", Some(card.clone()))).unwrap();
+        assert_eq!(first, "scan-1");
+        assert_eq!(second, "scan-1");
+
+        // An unresolved scan stays separate until its match is chosen
+        stage_scan_item(&conn, &staged_scan(&session, "scan-3", None)).unwrap();
+        assert_eq!(get_scan_items(&conn, &session).unwrap().len(), 2);
+        assert_eq!(
+            select_scan_item_match(&conn, "scan-3", &card).unwrap(),
+            Some("scan-1".to_string())
+        );
+
+        let items = get_scan_items(&conn, &session).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].quantity, 3);
+        assert_eq!(items[0].selected.as_ref().map(|c| c.id.as_str()), Some("test-id-123"));
+
+        let sessions = get_scan_sessions(&conn).unwrap();
+        assert_eq!((sessions[0].item_count, sessions[0].card_count), (1, 3));
+    }
+
+    #[test]
+    fn test_commit_scan_session() {
+        let conn = setup_test_db();
+        insert_test_set(&conn);
+        let session = create_scan_session(&conn, "Intake").unwrap();
+        let mut foil = create_test_card();
+        foil.id = "foil-id".to_string();
+
+        stage_scan_item(&conn, &staged_scan(&session, "scan-1", Some(create_test_card()))).unwrap();
+        stage_scan_item(&conn, &staged_scan(&session, "scan-2", Some(foil))).unwrap();
+        update_scan_item(&conn, "scan-2", 2, "LP", "Japanese", "foil", 5.0).unwrap();
+
+        // An unresolved scan fails the whole commit
+        stage_scan_item(&conn, &staged_scan(&session, "scan-3", None)).unwrap();
+        let error = commit_scan_session(&conn, &session, "EUR").unwrap_err();
+        assert!(error.to_string().contains("scan-3"), "{}", error);
+        assert!(get_all_cards(&conn).unwrap().is_empty());
+        assert_eq!(get_scan_session_status(&conn, &session).unwrap().as_deref(), Some("open"));
+        remove_scan_item(&conn, "scan-3").unwrap();
+
+        let added = commit_scan_session(&conn, &session, "EUR").unwrap();
+        assert_eq!(added.len(), 2);
+        assert_eq!(get_scan_session_status(&conn, &session).unwrap().as_deref(), Some("committed"));
+
+        let cards = get_all_cards(&conn).unwrap();
+        let foil_card = cards.iter().find(|c| c.scryfall_id == "foil-id").unwrap();
+        assert_eq!(foil_card.quantity, 2);
+        assert_eq!(foil_card.language, "Japanese");
+        assert!(foil_card.is_foil);
+    }
+
+    #[test]
+    fn test_recognition_feedback() {
+        let conn = setup_test_db();
+        let card = create_test_card();
+        let hashes = crate::card_hash::CardHashes {
+            dhash: 0xff,
+            phash: 1,
+            ahash: 2,
+            dhash256: [3, 4, 5, 6],
+        };
+
+        let id = insert_recognition_feedback(&conn, &card, &hashes, true).unwrap().unwrap();
+        // The same photo and verdict is only stored once
+        assert_eq!(insert_recognition_feedback(&conn, &card, &hashes, true).unwrap(), None);
+        insert_recognition_feedback(&conn, &card, &hashes, false).unwrap().unwrap();
+
+        let feedback = get_recognition_feedback(&conn, id).unwrap().unwrap();
+        assert!(feedback.confirmed);
+        assert_eq!(feedback.stored_hashes().unwrap().full, Some(hashes));
+        assert_eq!(get_printing_feedback(&conn, "test-id-123").unwrap().len(), 2);
+        assert_eq!(
+            get_feedback_entries(&conn).unwrap()[0],
+            (id, "test-id-123".to_string(), "ff".to_string(), true)
+        );
+    }
 }
@@ -29,6 +29,8 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             language TEXT DEFAULT 'English',
             finish TEXT DEFAULT 'nonfoil',
             phash TEXT,
+            hashes TEXT,
+            phash_algo TEXT,
             FOREIGN KEY(set_code) REFERENCES sets(code)
         )",
         [],
@@ -86,6 +88,97 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Set icons rendered to symbol masks, for recognizing a card's set
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS set_symbols (
+            set_code TEXT PRIMARY KEY,
+            icon_uri TEXT NOT NULL,
+            mask BLOB NOT NULL,
+            FOREIGN KEY(set_code) REFERENCES sets(code)
+        )",
+        [],
+    )?;
+
+    // Reference hashes for every known printing, owned or not
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reference_hashes (
+            scryfall_id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            set_code TEXT NOT NULL,
+            collector_number TEXT NOT NULL,
+            image_uri TEXT,
+            phash TEXT NOT NULL,
+            art_phash TEXT,
+            hashes TEXT,
+            phash_algo TEXT,
+            source TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Downloaded card images, by URL, stored in the app data dir under their SHA-256
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_cache (
+            url TEXT PRIMARY KEY,
+            digest TEXT NOT NULL,
+            fetched_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // User photos confirmed or rejected as a printing, used as recognition exemplars
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recognition_feedback (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scryfall_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            set_code TEXT NOT NULL,
+            collector_number TEXT NOT NULL,
+            image_uri TEXT,
+            phash TEXT NOT NULL,
+            hashes TEXT NOT NULL,
+            phash_algo TEXT NOT NULL,
+            confirmed BOOLEAN NOT NULL,
+            created_at TEXT NOT NULL,
+            UNIQUE(scryfall_id, hashes, confirmed)
+        )",
+        [],
+    )?;
+
+    // Scan sessions: batches of scans staged before they reach the collection
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_sessions (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'open',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Staged scans with their candidates (JSON) and the chosen match
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_items (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            image_path TEXT,
+            candidates TEXT NOT NULL DEFAULT '[]',
+            scryfall_id TEXT,
+            selected_card TEXT,
+            condition TEXT DEFAULT 'NM',
+            language TEXT DEFAULT 'English',
+            finish TEXT DEFAULT 'nonfoil',
+            purchase_price REAL DEFAULT 0,
+            quantity INTEGER DEFAULT 1,
+            phash TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(session_id) REFERENCES scan_sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     Ok(())
 }
 
@@ -168,5 +261,30 @@ pub fn migrate_database(conn: &Connection) -> Result<()> {
         }
     }
 
+    // Full hash sets and the algorithm version that produced them, so hashes
+    // from older algorithms can be found and recomputed
+    add_column_if_missing(conn, "cards", "hashes", "TEXT")?;
+    add_column_if_missing(conn, "cards", "phash_algo", "TEXT")?;
+    add_column_if_missing(conn, "reference_hashes", "hashes", "TEXT")?;
+    add_column_if_missing(conn, "reference_hashes", "phash_algo", "TEXT")?;
+
+    Ok(())
+}
+
+/// Adds a column to a table unless it already exists.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists: i32 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+        [column],
+        |row| row.get(0),
+    )?;
+
+    if exists == 0 {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+        println!("Migration: Added '{}' column to {} table", column, table);
+    }
     Ok(())
 }
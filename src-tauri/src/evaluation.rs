@@ -0,0 +1,489 @@
+use crate::card_hash::{CardHashes, StoredHashes};
+use crate::database::operations;
+use crate::recognition::{recognize_photo, StageTimings};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Name of the label list inside an evaluation directory
+pub const LABELS_FILE: &str = "labels.json";
+
+/// Number of ranked printings checked for top-5 accuracy
+const TOP_K: usize = 5;
+
+/// A labeled photo. Feature labels are optional and use the enum variant
+/// names (`"Black"`, `"M15Frame"`, ...).
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabeledPhoto {
+    /// Photo path, relative to the evaluation directory
+    pub image: String,
+    /// Scryfall ID of the printing in the photo
+    pub scryfall_id: String,
+    pub border_type: Option<String>,
+    pub frame_color: Option<String>,
+    pub frame_style: Option<String>,
+    pub set_code: Option<String>,
+}
+
+/// Counts of predicted values for each expected value
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfusionMatrix {
+    /// expected -> predicted -> count
+    pub counts: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl ConfusionMatrix {
+    pub fn record(&mut self, expected: &str, predicted: &str) {
+        *self
+            .counts
+            .entry(expected.to_string())
+            .or_default()
+            .entry(predicted.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.values().flat_map(|row| row.values()).sum()
+    }
+
+    pub fn correct(&self) -> usize {
+        self.counts
+            .iter()
+            .filter_map(|(expected, row)| row.get(expected))
+            .sum()
+    }
+}
+
+/// Durations of one pipeline stage over all photos, in milliseconds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimingStats {
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+}
+
+impl TimingStats {
+    fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return TimingStats::default();
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        TimingStats {
+            mean_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+            median_ms: samples[samples.len() / 2],
+            max_ms: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// A photo whose printing wasn't the top-ranked one
+#[derive(Debug, Clone, Serialize)]
+pub struct Miss {
+    pub image: String,
+    pub expected: String,
+    /// The top-ranked printing, None if nothing was found
+    pub predicted: Option<String>,
+}
+
+/// Accuracy and timing of the recognition pipeline over a labeled directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvaluationReport {
+    /// Photos evaluated
+    pub samples: usize,
+    /// Photos that couldn't be loaded or recognized, with the reason
+    pub failures: Vec<String>,
+    /// Photos whose printing was the pipeline's answer
+    pub top1: usize,
+    /// Photos whose printing was among the pipeline's first five candidates
+    pub top5: usize,
+    /// Photos whose printing was among the five reference printings with the
+    /// closest hashes, whatever the pipeline answered
+    pub reference_top5: usize,
+    pub misses: Vec<Miss>,
+    pub border_type: ConfusionMatrix,
+    pub frame_color: ConfusionMatrix,
+    pub frame_style: ConfusionMatrix,
+    pub set_code: ConfusionMatrix,
+    /// Time of each pipeline stage, see [`StageTimings`]
+    pub decode: TimingStats,
+    pub prepare: TimingStats,
+    pub features: TimingStats,
+    pub ocr: TimingStats,
+    pub matching: TimingStats,
+}
+
+impl EvaluationReport {
+    /// A plain-text report for the console
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("Samples: {} ({} failed)", self.samples, self.failures.len()),
+            format!("Top-1: {}", ratio(self.top1, self.samples)),
+            format!("Top-{}: {}", TOP_K, ratio(self.top5, self.samples)),
+            format!("Reference top-{}: {}", TOP_K, ratio(self.reference_top5, self.samples)),
+        ];
+
+        for (name, matrix) in [
+            ("Border type", &self.border_type),
+            ("Frame color", &self.frame_color),
+            ("Frame style", &self.frame_style),
+            ("Set code", &self.set_code),
+        ] {
+            if matrix.total() == 0 {
+                continue;
+            }
+            lines.push(format!("{}: {}", name, ratio(matrix.correct(), matrix.total())));
+            for (expected, row) in &matrix.counts {
+                let predicted: Vec<String> = row.iter().map(|(p, n)| format!("{}: {}", p, n)).collect();
+                lines.push(format!("  {} -> {}", expected, predicted.join(", ")));
+            }
+        }
+
+        for (name, timing) in [
+            ("Decode", &self.decode),
+            ("Prepare", &self.prepare),
+            ("Features", &self.features),
+            ("OCR", &self.ocr),
+            ("Matching", &self.matching),
+        ] {
+            lines.push(format!(
+                "{} (ms): mean {:.1}, median {:.1}, max {:.1}",
+                name, timing.mean_ms, timing.median_ms, timing.max_ms
+            ));
+        }
+        for miss in &self.misses {
+            lines.push(format!(
+                "Miss: {} expected {}, got {}",
+                miss.image,
+                miss.expected,
+                miss.predicted.as_deref().unwrap_or("nothing")
+            ));
+        }
+        lines.extend(self.failures.iter().map(|failure| format!("Failed: {}", failure)));
+        lines.join("\n")
+    }
+}
+
+fn ratio(count: usize, total: usize) -> String {
+    let percent = if total == 0 { 0.0 } else { count as f64 * 100.0 / total as f64 };
+    format!("{}/{} ({:.1}%)", count, total, percent)
+}
+
+/// Runs the offline recognition pipeline over a directory of labeled photos.
+///
+/// The directory holds a `labels.json` array of [`LabeledPhoto`]s. Recognition
+/// uses whatever collection, feedback and reference catalog `state` holds and
+/// never queries Scryfall, so results are reproducible. Top-1 and top-5 come
+/// from the pipeline's candidates alone, so a photo it rejects is a miss.
+/// The closest reference printings by hash are ranked separately, to tell a
+/// catalog that lacks the printing from a pipeline that fails to accept it.
+///
+/// # Arguments
+///
+/// * `state` - The application state to recognize with.
+/// * `dir` - The evaluation directory.
+///
+/// # Returns
+///
+/// * `Result<EvaluationReport, String>` - The report, or an error if the labels can't be read.
+pub async fn evaluate(state: &AppState, dir: &Path) -> Result<EvaluationReport, String> {
+    let labels = std::fs::read_to_string(dir.join(LABELS_FILE))
+        .map_err(|e| format!("Failed to read {}: {}", LABELS_FILE, e))?;
+    let photos: Vec<LabeledPhoto> = serde_json::from_str(&labels)
+        .map_err(|e| format!("Failed to parse {}: {}", LABELS_FILE, e))?;
+
+    let mut report = EvaluationReport::default();
+    let mut timings = Vec::new();
+
+    for photo in photos {
+        let recognized = match std::fs::read(dir.join(&photo.image)) {
+            Ok(bytes) => recognize_photo(state, bytes, false).await,
+            Err(e) => Err(e.to_string()),
+        };
+        let result = match recognized {
+            Ok(result) => result,
+            Err(e) => {
+                report.failures.push(format!("{}: {}", photo.image, e));
+                continue;
+            }
+        };
+        timings.push(result.timings);
+
+        let ranked: Vec<&String> = result.candidates.iter().take(TOP_K).map(|card| &card.id).collect();
+        report.samples += 1;
+        if ranked.first() == Some(&&photo.scryfall_id) {
+            report.top1 += 1;
+        } else {
+            report.misses.push(Miss {
+                image: photo.image.clone(),
+                expected: photo.scryfall_id.clone(),
+                predicted: ranked.first().map(|id| id.to_string()),
+            });
+        }
+        if ranked.contains(&&photo.scryfall_id) {
+            report.top5 += 1;
+        }
+
+        if let Some(hashes) = CardHashes::from_hex(&result.image_hashes) {
+            if rank_references(state, &hashes)?.contains(&photo.scryfall_id) {
+                report.reference_top5 += 1;
+            }
+        }
+
+        let features = &result.features;
+        if let Some(expected) = &photo.border_type {
+            report.border_type.record(expected, &format!("{:?}", features.border_type));
+        }
+        if let Some(expected) = &photo.frame_color {
+            report.frame_color.record(expected, &format!("{:?}", features.frame_color));
+        }
+        if let Some(expected) = &photo.frame_style {
+            report.frame_style.record(expected, &format!("{:?}", features.frame_style));
+        }
+        if let Some(expected) = &photo.set_code {
+            report.set_code.record(expected, features.set_code.as_deref().unwrap_or("none"));
+        }
+    }
+
+    let stage = |ms: fn(&StageTimings) -> f64| TimingStats::from_samples(timings.iter().map(ms).collect());
+    report.decode = stage(|t| t.decode_ms);
+    report.prepare = stage(|t| t.prepare_ms);
+    report.features = stage(|t| t.features_ms);
+    report.ocr = stage(|t| t.ocr_ms);
+    report.matching = stage(|t| t.matching_ms);
+    Ok(report)
+}
+
+/// Reference printings near the photo, best fused score first
+fn rank_references(state: &AppState, hashes: &CardHashes) -> Result<Vec<String>, String> {
+    let candidates = state
+        .reference_index
+        .lock()
+        .map_err(|_| "Failed to lock reference index".to_string())?
+        .candidates(hashes.dhash, None, TOP_K * 4);
+
+    let user_hashes = StoredHashes::from(*hashes);
+    let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+    let mut scored = Vec::new();
+    for candidate in candidates {
+        let reference = operations::get_reference_hash(&db, &candidate.id).map_err(|e| e.to_string())?;
+        let stored = reference.as_ref().and_then(|r| {
+            StoredHashes::parse(&r.phash, r.hashes.as_deref(), r.phash_algo.as_deref())
+        });
+        if let Some(stored) = stored {
+            scored.push((candidate.id, user_hashes.similarity(&stored)));
+        }
+    }
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(scored.into_iter().take(TOP_K).map(|(id, _)| id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::reference::{find_archive_image, read_archive_manifest, reference_from_image, store_reference};
+    use crate::hash_index::{FeedbackIndex, HashIndex, ReferenceIndex};
+    use crate::image_cache::ImageCache;
+    use crate::models::scryfall::{Prices, ScryfallCard};
+    use crate::services::scryfall::ScryfallService;
+    use crate::set_symbols::SetSymbolIndex;
+    use image::{DynamicImage, Rgb, RgbImage};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Subdirectory of an evaluation directory holding its reference archive
+    /// (same layout as `import_reference_archive`)
+    const REFERENCE_DIR: &str = "reference";
+
+    /// An app state whose catalog and image cache live in a temporary
+    /// directory, removed when it is dropped
+    struct TestState {
+        state: AppState,
+        dir: PathBuf,
+    }
+
+    impl std::ops::Deref for TestState {
+        type Target = AppState;
+
+        fn deref(&self) -> &AppState {
+            &self.state
+        }
+    }
+
+    impl Drop for TestState {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn test_state() -> TestState {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::database::schema::create_tables(&conn).unwrap();
+        let dir = std::env::temp_dir().join(format!("mtgia-eval-state-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let catalog = crate::catalog::Catalog::new(dir.join("catalog.db"));
+        let state = AppState {
+            db: Mutex::new(conn),
+            ocr: None,
+            phash_index: Mutex::new(HashIndex::new()),
+            reference_index: Mutex::new(ReferenceIndex::new()),
+            set_symbols: Mutex::new(SetSymbolIndex::new()),
+            feedback_index: Mutex::new(FeedbackIndex::new()),
+            image_cache: ImageCache::new(dir.join("image-cache")),
+            image_client: crate::recognition::image_client(),
+            card_data: std::sync::Arc::new(ScryfallService::new().with_catalog(catalog.clone())),
+            catalog,
+            live_scans: Mutex::new(std::collections::HashMap::new()),
+        };
+        TestState { state, dir }
+    }
+
+    /// Hashes every card of a reference archive into the state
+    fn load_reference_archive(state: &AppState, dir: &Path) -> usize {
+        let mut loaded = 0;
+        for card in read_archive_manifest(dir).unwrap() {
+            if let Some(path) = find_archive_image(dir, &card.id) {
+                let image = image::open(path).unwrap();
+                store_reference(state, &reference_from_image(&card, &image, "archive")).unwrap();
+                loaded += 1;
+            }
+        }
+        loaded
+    }
+
+    fn test_card(id: &str) -> ScryfallCard {
+        ScryfallCard {
+            id: id.to_string(),
+            oracle_id: None,
+            name: format!("Card {}", id),
+            lang: None,
+            set: "tst".to_string(),
+            set_name: "Test Set".to_string(),
+            collector_number: "1".to_string(),
+            released_at: String::new(),
+            artist: None,
+            image_uris: None,
+            prices: Prices {
+                usd: None,
+                usd_foil: None,
+                eur: None,
+                eur_foil: None,
+            },
+            rarity: "common".to_string(),
+            similarity: None,
+            match_confidence: None,
+        }
+    }
+
+    /// A card-proportioned image with a pattern unique to `seed`
+    fn card_image(seed: u32, brightness: i32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(488, 680, |x, y| {
+            let cell = ((x / (40 + seed * 7)) + (y / (55 + seed * 11)) * (seed + 2)) % 4;
+            let value = (cell as i32 * 60 + brightness).clamp(0, 255) as u8;
+            Rgb([value, 255 - value, (value / 2).wrapping_add(seed as u8 * 40)])
+        }))
+    }
+
+    fn fixture_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mtgia-eval-{}", uuid::Uuid::new_v4()));
+        let reference = dir.join(REFERENCE_DIR);
+        std::fs::create_dir_all(&reference).unwrap();
+
+        let cards: Vec<ScryfallCard> = (0..3).map(|i| test_card(&format!("card-{}", i))).collect();
+        std::fs::write(reference.join("cards.json"), serde_json::to_string(&cards).unwrap()).unwrap();
+        let mut labels = Vec::new();
+        for (i, card) in cards.iter().enumerate() {
+            card_image(i as u32, 0).save(reference.join(format!("{}.png", card.id))).unwrap();
+            // The "photo" is a slightly brighter copy of the reference image
+            let photo = format!("photo-{}.png", i);
+            card_image(i as u32, 12).save(dir.join(&photo)).unwrap();
+            labels.push(serde_json::json!({ "image": photo, "scryfall_id": card.id }));
+        }
+        labels.push(serde_json::json!({ "image": "missing.png", "scryfall_id": "card-0" }));
+        std::fs::write(dir.join(LABELS_FILE), serde_json::to_string(&labels).unwrap()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let mut matrix = ConfusionMatrix::default();
+        matrix.record("Black", "Black");
+        matrix.record("Black", "White");
+        matrix.record("White", "White");
+        assert_eq!((matrix.correct(), matrix.total()), (2, 3));
+        assert_eq!(matrix.counts["Black"]["White"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_synthetic_fixtures() {
+        let dir = fixture_dir();
+        let state = test_state();
+        assert_eq!(load_reference_archive(&state, &dir.join(REFERENCE_DIR)), 3);
+
+        let report = evaluate(&state, &dir).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.samples, 3);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.top1, 3, "{}", report.summary());
+        assert_eq!((report.top5, report.reference_top5), (3, 3));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_counts_runners_up_in_top5() {
+        let dir = fixture_dir();
+        let state = test_state();
+        assert_eq!(load_reference_archive(&state, &dir.join(REFERENCE_DIR)), 3);
+
+        // A reprint that looks even more like photo-0 than card-0 does
+        store_reference(&state, &reference_from_image(&test_card("card-0-reprint"), &card_image(0, 12), "archive"))
+            .unwrap();
+
+        let report = evaluate(&state, &dir).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((report.top1, report.top5), (2, 3), "{}", report.summary());
+        assert_eq!(report.misses[0].predicted.as_deref(), Some("card-0-reprint"));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_counts_rejected_photos_as_misses() {
+        let dir = fixture_dir();
+        let state = test_state();
+        assert_eq!(load_reference_archive(&state, &dir.join(REFERENCE_DIR)), 3);
+
+        // The user said this photo is not card-0, so the pipeline no longer
+        // accepts it, though card-0 is still its closest reference printing
+        let bytes = std::fs::read(dir.join("photo-0.png")).unwrap();
+        let hashes = CardHashes::from_hex(&recognize_photo(&state, bytes, false).await.unwrap().image_hashes).unwrap();
+        let id = {
+            let db = state.db.lock().unwrap();
+            operations::insert_recognition_feedback(&db, &test_card("card-0"), &hashes, false).unwrap().unwrap()
+        };
+        state.feedback_index.lock().unwrap().insert(id, "card-0".to_string(), hashes.dhash, false);
+
+        let report = evaluate(&state, &dir).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.samples, 3);
+        assert_eq!((report.top1, report.reference_top5), (2, 3), "{}", report.summary());
+        assert_eq!(report.misses[0].image, "photo-0.png");
+        assert_ne!(report.misses[0].predicted.as_deref(), Some("card-0"));
+    }
+
+    /// Evaluates a real photo set: `MTGIA_EVAL_DIR=/path/to/photos cargo test evaluate_labeled_photos -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore = "needs a labeled photo directory in MTGIA_EVAL_DIR"]
+    async fn evaluate_labeled_photos() {
+        let dir = PathBuf::from(std::env::var("MTGIA_EVAL_DIR").expect("MTGIA_EVAL_DIR is not set"));
+        let state = test_state();
+        let reference_dir = dir.join(REFERENCE_DIR);
+        if reference_dir.is_dir() {
+            println!("Loaded {} reference printings", load_reference_archive(&state, &reference_dir));
+        }
+
+        let report = evaluate(&state, &dir).await.unwrap();
+        println!("{}", report.summary());
+    }
+}
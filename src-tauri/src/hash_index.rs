@@ -0,0 +1,477 @@
+use crate::card_features::hamming_distance;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Hashes within this distance are similar enough to consider as candidates
+pub const CANDIDATE_RADIUS: u32 = 11;
+/// Hashes within this distance are treated as a confident match
+pub const MATCH_DISTANCE: u32 = 5;
+
+/// A node of the BK-tree. All entries with the same hash share one node.
+struct Node {
+    hash: u64,
+    ids: Vec<String>,
+    /// Children keyed by their Hamming distance to this node's hash
+    children: HashMap<u32, Node>,
+}
+
+impl Node {
+    fn new(hash: u64, id: String) -> Self {
+        Node {
+            hash,
+            ids: vec![id],
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// A hash match returned by [`HashIndex::query`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashMatch {
+    pub id: String,
+    pub distance: u32,
+}
+
+/// In-memory BK-tree over 64-bit perceptual hashes, keyed by an id
+/// (a collection row id, a Scryfall id, ...).
+///
+/// Lookups only visit subtrees whose distance band can contain a match, so a
+/// radius query touches a small part of the tree instead of every hash.
+/// Removed ids leave their node in place as a routing point; the tree is
+/// rebuilt once more than half of its nodes are empty.
+pub struct HashIndex {
+    root: Option<Node>,
+    /// Current hash for every id, used for removal and re-insertion
+    hashes: HashMap<String, u64>,
+    node_count: usize,
+    empty_nodes: usize,
+}
+
+impl Default for HashIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashIndex {
+    pub fn new() -> Self {
+        HashIndex {
+            root: None,
+            hashes: HashMap::new(),
+            node_count: 0,
+            empty_nodes: 0,
+        }
+    }
+
+    /// Builds an index from (id, hex hash) pairs, skipping unparseable hashes
+    pub fn from_hex_entries(entries: Vec<(String, String)>) -> Self {
+        let mut index = HashIndex::new();
+        for (id, hex) in entries {
+            if let Some(hash) = parse_hash(&hex) {
+                index.insert(id, hash);
+            }
+        }
+        index
+    }
+
+    /// Number of ids in the index
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Adds an id, replacing its previous hash if it was already indexed
+    pub fn insert(&mut self, id: String, hash: u64) {
+        if let Some(&existing) = self.hashes.get(&id) {
+            if existing == hash {
+                return;
+            }
+            self.remove(&id);
+        }
+        self.hashes.insert(id.clone(), hash);
+
+        let mut node = match self.root.as_mut() {
+            Some(root) => root,
+            None => {
+                self.root = Some(Node::new(hash, id));
+                self.node_count += 1;
+                return;
+            }
+        };
+
+        loop {
+            let distance = hamming_distance(node.hash, hash);
+            if distance == 0 {
+                if node.ids.is_empty() {
+                    self.empty_nodes -= 1;
+                }
+                node.ids.push(id);
+                return;
+            }
+
+            match node.children.entry(distance) {
+                Entry::Occupied(child) => node = child.into_mut(),
+                Entry::Vacant(slot) => {
+                    slot.insert(Node::new(hash, id));
+                    self.node_count += 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Removes an id from the index. Returns false if it wasn't indexed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let hash = match self.hashes.remove(id) {
+            Some(hash) => hash,
+            None => return false,
+        };
+
+        let mut node = self.root.as_mut();
+        while let Some(current) = node {
+            let distance = hamming_distance(current.hash, hash);
+            if distance == 0 {
+                current.ids.retain(|existing| existing != id);
+                if current.ids.is_empty() {
+                    self.empty_nodes += 1;
+                }
+                break;
+            }
+            node = current.children.get_mut(&distance);
+        }
+
+        if self.empty_nodes * 2 > self.node_count {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// Finds up to `k` ids whose hash is within `radius` of `hash`, closest first
+    pub fn query(&self, hash: u64, radius: u32, k: usize) -> Vec<HashMatch> {
+        let mut matches = Vec::new();
+        let mut stack: Vec<&Node> = self.root.iter().collect();
+
+        while let Some(node) = stack.pop() {
+            let distance = hamming_distance(node.hash, hash);
+            if distance <= radius {
+                matches.extend(node.ids.iter().map(|id| HashMatch {
+                    id: id.clone(),
+                    distance,
+                }));
+            }
+
+            // Triangle inequality: only children in [d - r, d + r] can match
+            let low = distance.saturating_sub(radius);
+            let high = distance + radius;
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(edge, _)| (low..=high).contains(*edge))
+                    .map(|(_, child)| child),
+            );
+        }
+
+        matches.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.id.cmp(&b.id)));
+        matches.truncate(k);
+        matches
+    }
+
+    /// Rebuilds the tree from the live entries, dropping empty nodes
+    fn rebuild(&mut self) {
+        let entries: Vec<(String, u64)> = self.hashes.drain().collect();
+        self.root = None;
+        self.node_count = 0;
+        self.empty_nodes = 0;
+        for (id, hash) in entries {
+            self.insert(id, hash);
+        }
+    }
+}
+
+/// A reference printing whose whole-card hash is within [`CANDIDATE_RADIUS`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceCandidate {
+    pub id: String,
+    /// Whole-card dHash distance
+    pub distance: u32,
+    /// Whether the art-box hash is within [`MATCH_DISTANCE`]
+    pub art_confirmed: bool,
+}
+
+/// Index over the reference hash catalog, keyed by Scryfall id
+#[derive(Default)]
+pub struct ReferenceIndex {
+    cards: HashIndex,
+    art: HashMap<String, u64>,
+}
+
+impl ReferenceIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the index from (Scryfall id, card hash, art hash) rows
+    pub fn from_hex_entries(entries: Vec<(String, String, Option<String>)>) -> Self {
+        let mut index = ReferenceIndex::new();
+        for (id, hex, art_hex) in entries {
+            if let Some(hash) = parse_hash(&hex) {
+                index.insert(id, hash, art_hex.as_deref().and_then(parse_hash));
+            }
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Adds or replaces the hashes of a printing
+    pub fn insert(&mut self, id: String, hash: u64, art_hash: Option<u64>) {
+        match art_hash {
+            Some(art_hash) => {
+                self.art.insert(id.clone(), art_hash);
+            }
+            None => {
+                self.art.remove(&id);
+            }
+        }
+        self.cards.insert(id, hash);
+    }
+
+    /// Finds up to `k` printings whose whole-card hash is within
+    /// [`CANDIDATE_RADIUS`], closest first.
+    ///
+    /// A close art-box hash marks a candidate as confirmed, which catches photos
+    /// where glare or the frame throw off the whole-card hash but the art is intact.
+    pub fn candidates(&self, hash: u64, art_hash: Option<u64>, k: usize) -> Vec<ReferenceCandidate> {
+        self.cards
+            .query(hash, CANDIDATE_RADIUS, k)
+            .into_iter()
+            .map(|m| {
+                let art_confirmed = match (art_hash, self.art.get(&m.id)) {
+                    (Some(query), Some(stored)) => hamming_distance(query, *stored) <= MATCH_DISTANCE,
+                    _ => false,
+                };
+                ReferenceCandidate {
+                    id: m.id,
+                    distance: m.distance,
+                    art_confirmed,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A feedback photo whose hash is within [`CANDIDATE_RADIUS`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackMatch {
+    /// Row ID of the feedback
+    pub id: i64,
+    /// The printing the photo was confirmed or rejected as
+    pub scryfall_id: String,
+    pub distance: u32,
+}
+
+/// Index over the recognition feedback photos, confirmed and rejected kept apart
+#[derive(Default)]
+pub struct FeedbackIndex {
+    confirmed: HashIndex,
+    rejected: HashIndex,
+    printings: HashMap<i64, String>,
+}
+
+impl FeedbackIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the index from (ID, Scryfall ID, hex hash, confirmed) rows
+    pub fn from_hex_entries(entries: Vec<(i64, String, String, bool)>) -> Self {
+        let mut index = FeedbackIndex::new();
+        for (id, scryfall_id, hex, confirmed) in entries {
+            if let Some(hash) = parse_hash(&hex) {
+                index.insert(id, scryfall_id, hash, confirmed);
+            }
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.printings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.printings.is_empty()
+    }
+
+    /// Adds a feedback photo
+    pub fn insert(&mut self, id: i64, scryfall_id: String, hash: u64, confirmed: bool) {
+        let tree = if confirmed {
+            &mut self.confirmed
+        } else {
+            &mut self.rejected
+        };
+        tree.insert(id.to_string(), hash);
+        self.printings.insert(id, scryfall_id);
+    }
+
+    /// Finds up to `k` confirmed (or rejected) photos within [`CANDIDATE_RADIUS`], closest first
+    pub fn query(&self, hash: u64, confirmed: bool, k: usize) -> Vec<FeedbackMatch> {
+        let tree = if confirmed { &self.confirmed } else { &self.rejected };
+        tree.query(hash, CANDIDATE_RADIUS, k)
+            .into_iter()
+            .filter_map(|m| {
+                let id: i64 = m.id.parse().ok()?;
+                Some(FeedbackMatch {
+                    id,
+                    scryfall_id: self.printings.get(&id)?.clone(),
+                    distance: m.distance,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parses a hash stored as a hex string in the database
+pub fn parse_hash(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random hashes for comparing against a linear scan
+    fn test_hashes(count: usize) -> Vec<u64> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_query_matches_linear_scan() {
+        let hashes = test_hashes(2000);
+        let mut index = HashIndex::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            index.insert(format!("card-{}", i), *hash);
+        }
+
+        let target = hashes[42] ^ 0b1011; // 3 bits away from card-42
+        let results = index.query(target, 24, usize::MAX);
+
+        let mut expected: Vec<(u32, String)> = hashes
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (hamming_distance(*h, target), format!("card-{}", i)))
+            .filter(|(d, _)| *d <= 24)
+            .collect();
+        expected.sort();
+
+        assert_eq!(results.len(), expected.len());
+        assert_eq!(results[0].id, "card-42");
+        assert_eq!(results[0].distance, 3);
+        for (result, (distance, id)) in results.iter().zip(expected.iter()) {
+            assert_eq!(result.distance, *distance);
+            assert_eq!(&result.id, id);
+        }
+    }
+
+    #[test]
+    fn test_query_top_k() {
+        let mut index = HashIndex::new();
+        index.insert("exact".to_string(), 0xff00);
+        index.insert("one-off".to_string(), 0xff01);
+        index.insert("far".to_string(), 0x00ff);
+
+        let results = index.query(0xff00, 5, 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "exact");
+
+        let results = index.query(0xff00, 5, 10);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_and_reinsert() {
+        let mut index = HashIndex::new();
+        index.insert("a".to_string(), 1);
+        index.insert("b".to_string(), 1);
+        index.insert("c".to_string(), 3);
+
+        assert!(index.remove("a"));
+        assert!(!index.remove("a"));
+        let results = index.query(1, 0, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "b");
+
+        // Re-inserting with a new hash moves the id
+        index.insert("b".to_string(), 3);
+        assert!(index.query(1, 0, 10).is_empty());
+        assert_eq!(index.query(3, 0, 10).len(), 2);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_rebuild_after_many_removals() {
+        let hashes = test_hashes(100);
+        let mut index = HashIndex::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            index.insert(i.to_string(), *hash);
+        }
+        for i in 0..90 {
+            index.remove(&i.to_string());
+        }
+
+        assert_eq!(index.len(), 10);
+        assert!(index.node_count <= 20);
+        let results = index.query(hashes[95], 0, 10);
+        assert_eq!(results[0].id, "95");
+    }
+
+    #[test]
+    fn test_reference_index_art_confirmation() {
+        let mut index = ReferenceIndex::new();
+        let card_hash = 0x0f0f_0f0f_0f0f_0f0fu64;
+        let art_hash = 0xaaaa_5555_aaaa_5555u64;
+        index.insert("printing".to_string(), card_hash, Some(art_hash));
+
+        // 8 bits off is still a candidate; only a matching art box confirms it
+        let glared = card_hash ^ 0xff;
+        let candidates = index.candidates(glared, Some(art_hash ^ 1), 5);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].distance, 8);
+        assert!(candidates[0].art_confirmed);
+        assert!(!index.candidates(glared, Some(!art_hash), 5)[0].art_confirmed);
+        assert!(!index.candidates(glared, None, 5)[0].art_confirmed);
+
+        // Outside the candidate radius nothing is returned
+        assert!(index.candidates(!card_hash, Some(art_hash), 5).is_empty());
+    }
+
+    #[test]
+    fn test_feedback_index_keeps_verdicts_apart() {
+        let mut index = FeedbackIndex::new();
+        index.insert(1, "right".to_string(), 0xf0f0, true);
+        index.insert(2, "wrong".to_string(), 0xf0f1, false);
+
+        let confirmed = index.query(0xf0f0, true, 5);
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].scryfall_id, "right");
+        let rejected = index.query(0xf0f0, false, 5);
+        assert_eq!((rejected[0].id, rejected[0].distance), (2, 1));
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::image_cache::ImageCache;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// On-disk cache of API responses, keyed by method, URL (with its query)
+/// and request body. Entries keep their `ETag` and `Last-Modified` so stale
+/// ones can be revalidated instead of downloaded again.
+///
+/// Like the catalog, the cache lives in its own database file, so caching a
+/// response never waits on the collection's lock. The database is opened on
+/// first use, and clones share that connection.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    path: PathBuf,
+    conn: Arc<Mutex<Option<Connection>>>,
+    /// Whether a stale entry is served when the API can't be reached
+    stale_if_error: bool,
+}
+
+/// A cached response body and what is needed to revalidate it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix time the response was fetched or last revalidated
+    pub fetched_at: i64,
+}
+
+impl CachedResponse {
+    /// Time since the response was fetched or last revalidated
+    pub fn age(&self) -> Duration {
+        let seconds = chrono::Utc::now().timestamp() - self.fetched_at;
+        Duration::from_secs(seconds.max(0) as u64)
+    }
+}
+
+impl ResponseCache {
+    pub fn new(path: PathBuf) -> Self {
+        ResponseCache {
+            path,
+            conn: Arc::new(Mutex::new(None)),
+            stale_if_error: false,
+        }
+    }
+
+    /// Serves stale entries when the API fails or can't be reached, so the
+    /// app keeps working offline with what it has seen before.
+    pub fn with_stale_if_error(self, stale_if_error: bool) -> Self {
+        ResponseCache { stale_if_error, ..self }
+    }
+
+    pub fn stale_if_error(&self) -> bool {
+        self.stale_if_error
+    }
+
+    /// Hex SHA-256 of a request, its key in the cache
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The HTTP method.
+    /// * `url` - The full URL, with its query.
+    /// * `body` - The request body, empty for GET.
+    pub fn key(method: &str, url: &str, body: &[u8]) -> String {
+        let mut request = format!("{} {}\n", method, url).into_bytes();
+        request.extend_from_slice(body);
+        ImageCache::digest(&request)
+    }
+
+    /// Runs a statement on the cache database, opening it and creating its
+    /// table on first use
+    fn with_connection<T>(&self, run: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+        let mut guard = self.conn.lock().map_err(|_| "Response cache lock poisoned".to_string())?;
+        let conn = match &mut *guard {
+            Some(conn) => conn,
+            slot => slot.insert(open_cache(&self.path)?),
+        };
+        run(conn).map_err(|e| e.to_string())
+    }
+
+    /// Looks up a cached response, however old.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The request's key from [`ResponseCache::key`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<CachedResponse>, String>` - The entry, None if the request was never cached, or an error message.
+    pub fn get(&self, key: &str) -> Result<Option<CachedResponse>, String> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        self.with_connection(|conn| {
+            conn.query_row(
+                "SELECT body, etag, last_modified, fetched_at FROM responses WHERE key = ?1",
+                params![key],
+                |row| {
+                    Ok(CachedResponse {
+                        body: row.get(0)?,
+                        etag: row.get(1)?,
+                        last_modified: row.get(2)?,
+                        fetched_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+        })
+    }
+
+    /// Stores a response, replacing any earlier one for the same request.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The request's key from [`ResponseCache::key`].
+    /// * `url` - The request URL, kept for inspecting the cache.
+    /// * `body` - The response body.
+    /// * `etag` - The response's `ETag` header.
+    /// * `last_modified` - The response's `Last-Modified` header.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if stored, or an error message.
+    pub fn put(
+        &self,
+        key: &str,
+        url: &str,
+        body: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<(), String> {
+        self.with_connection(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO responses (key, url, body, etag, last_modified, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![key, url, body, etag, last_modified, chrono::Utc::now().timestamp()],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Marks an entry as fresh again, after the server said it hasn't changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The request's key from [`ResponseCache::key`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if updated, or an error message.
+    pub fn touch(&self, key: &str) -> Result<(), String> {
+        self.with_connection(|conn| {
+            conn.execute(
+                "UPDATE responses SET fetched_at = ?1 WHERE key = ?2",
+                params![chrono::Utc::now().timestamp(), key],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Removes entries that weren't fetched or revalidated within `max_age`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - How long an entry is kept, stale or not.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, String>` - The number of entries removed, or an error message.
+    pub fn prune(&self, max_age: Duration) -> Result<usize, String> {
+        if !self.path.exists() {
+            return Ok(0);
+        }
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+        self.with_connection(|conn| conn.execute("DELETE FROM responses WHERE fetched_at < ?1", params![cutoff]))
+    }
+
+    /// Makes every entry look `age` old, for tests of expiry
+    #[cfg(test)]
+    pub fn age_entries(&self, age: Duration) {
+        let fetched_at = chrono::Utc::now().timestamp() - age.as_secs() as i64;
+        self.with_connection(|conn| conn.execute("UPDATE responses SET fetched_at = ?1", params![fetched_at]))
+            .unwrap();
+    }
+}
+
+/// Opens the cache database, creating its table if needed
+fn open_cache(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| format!("Failed to open response cache: {}", e))?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS responses (
+            key TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            body BLOB NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create response cache table: {}", e))?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_cache() {
+        let path = std::env::temp_dir().join(format!("mtgia-http-cache-{}.db", uuid::Uuid::new_v4()));
+        let cache = ResponseCache::new(path.clone());
+
+        let key = ResponseCache::key("GET", "https://api.scryfall.com/sets", b"");
+        assert_ne!(key, ResponseCache::key("POST", "https://api.scryfall.com/sets", b""));
+        assert_eq!(cache.get(&key).unwrap(), None);
+
+        cache.put(&key, "https://api.scryfall.com/sets", b"{}", Some("\"v1\""), None).unwrap();
+        let entry = cache.get(&key).unwrap().unwrap();
+        assert_eq!((entry.body.as_slice(), entry.etag.as_deref()), (&b"{}"[..], Some("\"v1\"")));
+        assert!(entry.age() < Duration::from_secs(5));
+
+        cache.age_entries(Duration::from_secs(3600));
+        assert!(cache.get(&key).unwrap().unwrap().age() >= Duration::from_secs(3600));
+        cache.touch(&key).unwrap();
+        assert!(cache.get(&key).unwrap().unwrap().age() < Duration::from_secs(5));
+
+        cache.age_entries(Duration::from_secs(3600));
+        assert_eq!(cache.prune(Duration::from_secs(7200)).unwrap(), 0);
+        assert_eq!(cache.prune(Duration::from_secs(60)).unwrap(), 1);
+        assert_eq!(cache.get(&key).unwrap(), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Content-addressed store of downloaded card images. Each file is named by
+/// the SHA-256 of its bytes; the `image_cache` table maps URLs to digests.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ImageCache { dir }
+    }
+
+    /// Hex SHA-256 of an image, its key in the cache
+    pub fn digest(bytes: &[u8]) -> String {
+        Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Files are spread over subdirectories named by the first two hex digits
+    fn path(&self, digest: &str) -> PathBuf {
+        self.dir.join(&digest[..2.min(digest.len())]).join(digest)
+    }
+
+    /// Reads a cached image, None if it is missing or its file was damaged
+    pub fn read(&self, digest: &str) -> Option<Vec<u8>> {
+        let bytes = std::fs::read(self.path(digest)).ok()?;
+        (Self::digest(&bytes) == digest).then_some(bytes)
+    }
+
+    /// Stores an image and returns its digest. Identical images share one file.
+    pub fn write(&self, bytes: &[u8]) -> Result<String, String> {
+        let digest = Self::digest(bytes);
+        let path = self.path(&digest);
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create image cache: {}", e))?;
+            }
+            // Write then rename, so a crash never leaves a truncated image under
+            // a valid name. Each writer has its own partial file, so two writing
+            // the same image don't rename one another's.
+            let partial = path.with_extension(format!("{}.part", uuid::Uuid::new_v4()));
+            std::fs::write(&partial, bytes).map_err(|e| format!("Failed to cache image: {}", e))?;
+            std::fs::rename(&partial, &path).map_err(|e| format!("Failed to cache image: {}", e))?;
+        }
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mtgia-cache-{}", uuid::Uuid::new_v4()));
+        let cache = ImageCache::new(dir.clone());
+
+        let digest = cache.write(b"thumbnail").unwrap();
+        assert_eq!(digest.len(), 64);
+        assert_eq!(cache.write(b"thumbnail").unwrap(), digest);
+        assert_eq!(cache.read(&digest).as_deref(), Some(&b"thumbnail"[..]));
+
+        // A damaged file is a miss, not a wrong image
+        std::fs::write(cache.path(&digest), b"truncated").unwrap();
+        assert_eq!(cache.read(&digest), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writes_of_one_image() {
+        let dir = std::env::temp_dir().join(format!("mtgia-cache-{}", uuid::Uuid::new_v4()));
+        let cache = ImageCache::new(dir.clone());
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                std::thread::spawn(move || cache.write(b"same thumbnail"))
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        let digest = ImageCache::digest(b"same thumbnail");
+        assert_eq!(cache.read(&digest).as_deref(), Some(&b"same thumbnail"[..]));
+        let files = std::fs::read_dir(cache.path(&digest).parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -13,228 +13,393 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-use serde::Serialize;
-
-#[derive(Debug, Clone, Serialize)]
-pub struct RecognitionResult {
-    pub features: card_features::CardFeatures,
-    pub detected_name: String,
-    pub feature_description: String,
-    pub search_query: String,
-    pub candidates: Vec<models::scryfall::ScryfallCard>,
-}
-
-/// Recognize a card using visual features and optional OCR
+/// Recognize a card using visual features and optional OCR.
+/// With a burst of photos of the card tilted a little between shots, its
+/// finish is estimated too. The photo itself is the burst's first frame,
+/// `burst` holds the ones after it.
 #[tauri::command]
 async fn recognize_card_with_features(
     state: tauri::State<'_, AppState>,
     image_data: String,
-) -> Result<RecognitionResult, String> {
-    use crate::card_features::extract_card_features;
-    use crate::card_filter::{build_search_query, describe_features};
+    burst: Option<Vec<String>>,
+) -> Result<recognition::RecognitionResult, String> {
+    let image_bytes = decode_image_data(&image_data)?;
+    let (card_image, timings) = recognition::prepare_photo(image_bytes).await?;
+    let has_burst = burst.as_ref().is_some_and(|frames| !frames.is_empty());
+    let first_frame = (has_burst && card_image.rectified).then(|| card_image.image.clone());
+    let mut result = recognition::recognize_prepared(&state, card_image, timings, true).await?;
+
+    if let Some(burst) = burst.filter(|frames| !frames.is_empty()) {
+        let frames = burst
+            .iter()
+            .map(|frame| decode_image_data(frame))
+            .collect::<Result<Vec<_>, _>>()?;
+        result.finish = recognition::estimate_burst_finish(first_frame, frames).await?;
+        if let Some(estimate) = &result.finish {
+            result.features.is_foil = estimate.finish != card_finish::Finish::Nonfoil;
+        }
+    }
+    Ok(result)
+}
+
+/// Decodes a base64 photo, refusing one over the size limit before decoding it
+fn decode_image_data(image_data: &str) -> Result<Vec<u8>, String> {
     use base64::prelude::*;
-    
-    // Decode base64 image
-    let image_bytes = BASE64_STANDARD.decode(&image_data)
-        .map_err(|e| format!("Failed to decode base64: {}", e))?;
-    
-    // Extract visual features
-    let features = extract_card_features(&image_bytes)?;
-
-    // --- Phase 2: Offline Recognition (Local DB) ---
-    // Check if we have this card in our collection already using pHash
-    // This is instant and works offline
-    let user_image = image::load_from_memory(&image_bytes)
-        .map_err(|e| format!("Failed to load image for hashing: {}", e))?;
-    let user_hash = crate::card_features::calculate_phash(&user_image);
-    let user_hash_str = format!("{:x}", user_hash);
-    
-    // Check local DB for matches
-    let local_match = {
-        let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
-        let mut stmt = db.prepare(
-            "SELECT scryfall_id, name, set_code, collector_number, image_uri, phash FROM cards WHERE phash IS NOT NULL"
-        ).map_err(|e| e.to_string())?;
-        
-        let card_iter = stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, Option<String>>(4)?,
-                row.get::<_, String>(5)?,
-            ))
-        }).map_err(|e| e.to_string())?;
-        
-        let mut best_match: Option<(String, u32)> = None; // (scryfall_id, distance)
-        let mut best_card_data = None;
-        
-        for card in card_iter {
-            if let Ok((sid, name, set, cn, img, hash_str)) = card {
-                if let Ok(hash) = u64::from_str_radix(&hash_str, 16) {
-                    let dist = crate::card_features::hamming_distance(user_hash, hash);
-                    
-                    if dist < 12 { // Threshold for "similar enough" to consider
-                        if best_match.is_none() || dist < best_match.as_ref().unwrap().1 {
-                            best_match = Some((sid.clone(), dist));
-                            best_card_data = Some((sid, name, set, cn, img));
-                        }
-                    }
-                }
-            }
+
+    if image_data.len() / 4 * 3 > recognition::MAX_PHOTO_BYTES {
+        return Err(format!(
+            "Image is too large (the limit is {} MB)",
+            recognition::MAX_PHOTO_BYTES / (1024 * 1024)
+        ));
+    }
+    BASE64_STANDARD.decode(image_data)
+        .map_err(|e| format!("Failed to decode base64: {}", e))
+}
+
+/// Recognize every card in a photo of a binder page or a spread of cards.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `image_data` - The base64-encoded photo.
+/// * `rows` - Rows of the page, used when card outlines can't be found (default 3).
+/// * `columns` - Columns of the page, used when card outlines can't be found (default 3).
+///
+/// # Returns
+///
+/// * `Result<Vec<SlotRecognition>, String>` - One result per card in reading order, or an error message.
+#[tauri::command]
+async fn recognize_binder_page(
+    state: tauri::State<'_, AppState>,
+    image_data: String,
+    rows: Option<u32>,
+    columns: Option<u32>,
+) -> Result<Vec<recognition::SlotRecognition>, String> {
+    let image_bytes = decode_image_data(&image_data)?;
+    let rows = rows.unwrap_or(card_segmentation::BINDER_ROWS);
+    let columns = columns.unwrap_or(card_segmentation::BINDER_COLUMNS);
+
+    // Decoding and splitting a whole page takes a while, off the async executor
+    let (cards, exif_rotation, decode_ms, prepare_ms) = tokio::task::spawn_blocking(move || {
+        let started = std::time::Instant::now();
+        let (photo, exif_rotation) = recognition::decode_photo(&image_bytes)?;
+        let decode_ms = recognition::elapsed_ms(started);
+
+        let started = std::time::Instant::now();
+        let cards = card_segmentation::segment_cards(&photo, rows, columns);
+        Ok::<_, String>((cards, exif_rotation, decode_ms, recognition::elapsed_ms(started)))
+    })
+    .await
+    .map_err(|e| format!("Failed to split page: {}", e))??;
+    println!("Segmented {} cards in {:.0} ms", cards.len(), prepare_ms);
+
+    // One unreadable card shouldn't lose the rest of the page
+    let mut results = Vec::with_capacity(cards.len());
+    for mut card in cards {
+        card.image.rotation = (card.image.rotation + exif_rotation) % 360;
+        let recognized = recognition::recognize_card(&state, card.image, true).await;
+        if let Err(e) = &recognized {
+            println!("Recognition failed at row {}, column {}: {}", card.slot.row, card.slot.column, e);
         }
-        
-        if let Some((_, dist)) = best_match {
-            if dist <= 5 { // High confidence match
-                best_card_data.map(|(sid, name, set, cn, img)| (sid, name, set, cn, img, dist))
-            } else {
-                None
+        let (result, error) = match recognized {
+            Ok(mut result) => {
+                result.timings.decode_ms = decode_ms;
+                result.timings.prepare_ms = prepare_ms;
+                (Some(result), None)
             }
-        } else {
-            None
-        }
-    };
-
-    if let Some((sid, name, set, cn, img, dist)) = local_match {
-        println!("Found local match: {} (dist={})", name, dist);
-        
-        // Construct a ScryfallCard from local data to return
-        let card = crate::models::scryfall::ScryfallCard {
-            id: sid,
-            oracle_id: None,
-            name: name,
-            lang: Some("en".to_string()),
-            set: set,
-            set_name: String::new(), // We don't store set name in cards table, could fetch from sets table
-            collector_number: cn,
-            released_at: String::new(),
-            artist: None,
-            image_uris: Some(crate::models::scryfall::ImageUris {
-                small: img.clone().unwrap_or_default(),
-                normal: img.clone().unwrap_or_default(),
-                large: img.clone().unwrap_or_default(),
-                png: String::new(),
-                art_crop: String::new(),
-                border_crop: String::new(),
-            }),
-            prices: crate::models::scryfall::Prices {
-                usd: None, usd_foil: None, eur: None, eur_foil: None
-            },
-            rarity: String::new(),
-            similarity: Some(dist),
+            Err(e) => (None, Some(e)),
         };
-        
-        return Ok(RecognitionResult {
-            features: features.clone(),
-            detected_name: String::new(),
-            feature_description: describe_features(&features),
-            search_query: String::new(),
-            candidates: vec![card],
-        });
+        results.push(recognition::SlotRecognition { slot: card.slot, result, error });
     }
+    Ok(results)
+}
+
+/// Start recognizing cards from a live camera feed. Frames are pushed with
+/// `push_live_frame`; once the view is still and sharp with a card in it the
+/// card is recognized, and the result holds until the card leaves the view.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `on_event` - Channel that receives the scan's `LiveScanEvent`s.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The ID of the live scan, or an error message.
+#[tauri::command]
+async fn start_live_scan(
+    state: tauri::State<'_, AppState>,
+    on_event: tauri::ipc::Channel<live_scan::LiveScanEvent>,
+) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    state
+        .live_scans
+        .lock()
+        .map_err(|_| "Failed to lock live scans".to_string())?
+        .insert(
+            id.clone(),
+            live_scan::LiveScan {
+                stabilizer: Arc::new(Mutex::new(live_scan::FrameStabilizer::new())),
+                channel: on_event,
+            },
+        );
+    Ok(id)
+}
+
+/// Push one raw RGBA camera frame to a live scan. The body is the pixels; the
+/// `x-live-scan`, `x-frame-width` and `x-frame-height` headers give the scan ID
+/// and frame size. Results are sent over the scan's channel.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `request` - The raw frame and its headers.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok once the frame was handled, or an error message.
+#[tauri::command]
+async fn push_live_frame(
+    state: tauri::State<'_, AppState>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<(), String> {
+    use live_scan::{FrameStep, LiveScanEvent};
 
-    // Build search query based on features
-    let search_query = build_search_query(&features, None);
-    let feature_description = describe_features(&features);
-    
-    // Search Scryfall with the filtered query
-    let scryfall_service = services::scryfall::ScryfallService::new();
-    let mut candidates = scryfall_service.search_cards(&search_query, 1)
-        .await
-        .map_err(|e| format!("Scryfall search failed: {}", e))?
-        .data;
-    
-    // --- Image Comparison & Ranking ---
-    // Take top 30 candidates to compare (to keep it fast)
-    let num_to_compare = 30.min(candidates.len());
-    if num_to_compare > 0 {
-        use crate::card_features::{calculate_phash, hamming_distance};
-        use image::load_from_memory;
-        
-        // Calculate hash of the user's image
-        // We need to reload the image because extract_card_features consumes it or we just re-decode
-        // Actually extract_card_features returns features, but we need the image for hashing
-        // Let's re-decode for simplicity (it's fast)
-        let user_image = image::load_from_memory(&image_bytes)
-            .map_err(|e| format!("Failed to load image for hashing: {}", e))?;
-        let user_hash = calculate_phash(&user_image);
-        
-        println!("User Image Hash: {:x}", user_hash);
-        
-        // We'll process candidates in parallel using futures
-        let client = reqwest::Client::new();
-        let mut scored_candidates = Vec::new();
-        
-        for mut card in candidates.drain(..num_to_compare) {
-            if let Some(image_uris) = &card.image_uris {
-                let thumb_url = image_uris.small.clone();
-                let client = client.clone();
-                let user_hash = user_hash;
-                
-                // Download and hash
-                // Note: In a real async loop we'd join_all, but for simplicity/borrow checker
-                // we'll do it sequentially or use a simple loop. 
-                // For 30 small images, sequential is acceptable (~2-3s total) and safer to implement quickly.
-                // Parallelizing would require more complex async handling.
-                
-                match client.get(&thumb_url).send().await {
-                    Ok(resp) => {
-                        if let Ok(bytes) = resp.bytes().await {
-                            if let Ok(img) = load_from_memory(&bytes) {
-                                let card_hash = calculate_phash(&img);
-                                let dist = hamming_distance(user_hash, card_hash);
-                                card.similarity = Some(dist);
-                                println!("Compared with {}: dist={}", card.name, dist);
-                            }
-                        }
-                    }
-                    Err(e) => println!("Failed to download image for {}: {}", card.name, e),
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Missing {} header", name))
+    };
+    let scan_id = header("x-live-scan")?;
+    let width = header("x-frame-width")?.parse::<u32>().map_err(|e| e.to_string())?;
+    let height = header("x-frame-height")?.parse::<u32>().map_err(|e| e.to_string())?;
+    let tauri::ipc::InvokeBody::Raw(pixels) = request.body() else {
+        return Err("Expected raw RGBA pixels".to_string());
+    };
+    // The request only lends its body, so the pixels are copied out once and
+    // the frame is moved from there on
+    let frame = live_scan::frame_from_rgba(width, height, pixels.to_vec())?;
+
+    let (stabilizer, channel) = {
+        let scans = state
+            .live_scans
+            .lock()
+            .map_err(|_| "Failed to lock live scans".to_string())?;
+        let scan = scans.get(&scan_id).ok_or("Unknown live scan")?;
+        (scan.stabilizer.clone(), scan.channel.clone())
+    };
+
+    // Measuring a frame (and finding the card outline in it) is too slow for
+    // the async executor
+    let measured = stabilizer.clone();
+    let (frame, step) = tokio::task::spawn_blocking(move || {
+        let step = live_scan::observe_frame(&measured, &frame);
+        (frame, step)
+    })
+    .await
+    .map_err(|e| format!("Failed to measure frame: {}", e))?;
+
+    let event = match step {
+        FrameStep::Ignored => return Ok(()),
+        FrameStep::Waiting(status) => LiveScanEvent::Status(status),
+        FrameStep::Cleared => LiveScanEvent::Cleared,
+        FrameStep::Recognize => {
+            if let Err(e) = channel.send(LiveScanEvent::Recognizing) {
+                // Without the page listening the card would stay in recognition forever
+                stabilizer
+                    .lock()
+                    .map_err(|_| "Failed to lock live scan".to_string())?
+                    .reset();
+                return Err(e.to_string());
+            }
+            let recognized = recognition::recognize_image(&state, frame, 0, true).await;
+            stabilizer
+                .lock()
+                .map_err(|_| "Failed to lock live scan".to_string())?
+                .finish();
+            match recognized {
+                Ok(result) => LiveScanEvent::Locked(Box::new(result)),
+                Err(e) => {
+                    println!("Live recognition failed: {}", e);
+                    LiveScanEvent::Failed(e)
                 }
             }
-            scored_candidates.push(card);
         }
-        
-        // Add back the rest of the candidates (unscored)
-        scored_candidates.extend(candidates);
-        
-        // Sort by similarity (ascending distance)
-        // Cards with no similarity (failed download) go to the end
-        scored_candidates.sort_by(|a, b| {
-            match (a.similarity, b.similarity) {
-                (Some(d1), Some(d2)) => d1.cmp(&d2),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => std::cmp::Ordering::Equal,
-            }
-        });
-        
-        candidates = scored_candidates;
-    }
-    
-    Ok(RecognitionResult {
-        features,
-        detected_name: String::new(), // TODO: Add OCR
-        feature_description,
-        search_query,
-        candidates,
-    })
+    };
+    channel.send(event).map_err(|e| e.to_string())
+}
+
+/// Stop a live scan and drop its channel.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `scan_id` - The ID returned by `start_live_scan`.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if successful, or an error message.
+#[tauri::command]
+async fn stop_live_scan(state: tauri::State<'_, AppState>, scan_id: String) -> Result<(), String> {
+    state
+        .live_scans
+        .lock()
+        .map_err(|_| "Failed to lock live scans".to_string())?
+        .remove(&scan_id);
+    Ok(())
+}
+
+/// Measure recognition accuracy over a directory of labeled card photos.
+/// Runs offline against the app's collection and reference catalog.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `path` - The evaluation directory, holding `labels.json` and the photos.
+///
+/// # Returns
+///
+/// * `Result<evaluation::EvaluationReport, String>` - Accuracy, confusion matrices and timing, or an error message.
+#[tauri::command]
+async fn evaluate_recognition(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<evaluation::EvaluationReport, String> {
+    let report = evaluation::evaluate(&state, std::path::Path::new(&path)).await?;
+    println!("{}", report.summary());
+    Ok(report)
 }
 
 mod commands;
 mod database;
 mod models;
 mod services;
+mod catalog;
+mod card_condition;
+mod card_detection;
 mod card_features;
+mod card_finish;
+mod card_segmentation;
 mod card_filter;
+mod card_hash;
+mod card_ocr;
+mod evaluation;
+mod http_cache;
+mod image_cache;
+mod live_scan;
+mod orientation;
+mod recognition;
+mod set_symbols;
+mod hash_index;
 
 use rusqlite::Connection;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tauri::Manager;
 
 /// Application state holding the database connection.
 pub struct AppState {
     pub db: Mutex<Connection>,
+    /// Local OCR engine, None when the models are not installed
+    pub ocr: Option<Arc<card_ocr::CardOcr>>,
+    /// BK-tree over the collection's card hashes, keyed by card UUID
+    pub phash_index: Mutex<hash_index::HashIndex>,
+    /// Whole-card and art hashes of every printing in the reference catalog
+    pub reference_index: Mutex<hash_index::ReferenceIndex>,
+    /// Rendered set icons, for recognizing a card's set from its symbol
+    pub set_symbols: Mutex<set_symbols::SetSymbolIndex>,
+    /// Scans the user confirmed or rejected as a printing
+    pub feedback_index: Mutex<hash_index::FeedbackIndex>,
+    /// Downloaded card images, kept in the app data dir
+    pub image_cache: image_cache::ImageCache,
+    /// Downloads candidate thumbnails, one connection pool for every recognition
+    pub image_client: reqwest::Client,
+    /// Scryfall bulk data, for looking cards up offline
+    pub catalog: catalog::Catalog,
+    /// Where sets, cards and printings are fetched from
+    pub card_data: Arc<dyn services::card_data::CardDataProvider>,
+    /// Live camera scans, keyed by scan ID
+    pub live_scans: Mutex<HashMap<String, live_scan::LiveScan>>,
+}
+
+/// Loads the OCR models, preferring a copy in the app data dir over the bundled one.
+///
+/// # Arguments
+///
+/// * `app` - The application being set up.
+///
+/// # Returns
+///
+/// * `Option<CardOcr>` - The OCR engine, or None if no models were found.
+fn load_ocr(app: &tauri::App) -> Option<card_ocr::CardOcr> {
+    let mut model_dirs = Vec::new();
+    if let Ok(dir) = app.path().app_data_dir() {
+        model_dirs.push(dir.join("ocr"));
+    }
+    if let Ok(dir) = app.path().resource_dir() {
+        model_dirs.push(dir.join("resources").join("ocr"));
+    }
+
+    for dir in model_dirs {
+        if !dir.join(card_ocr::DETECTION_MODEL_FILE).exists() {
+            continue;
+        }
+        match card_ocr::CardOcr::load(&dir) {
+            Ok(ocr) => {
+                println!("Loaded OCR models from {:?}", dir);
+                return Some(ocr);
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    println!("OCR models not found, card name recognition is disabled");
+    None
+}
+
+/// Environment variable pointing card data at a Scryfall mirror instead of the public API
+const SCRYFALL_URL_VAR: &str = "MTGIA_SCRYFALL_URL";
+
+/// Environment variable pointing card data at a fixture directory instead of an API
+const SCRYFALL_FIXTURES_VAR: &str = "MTGIA_SCRYFALL_FIXTURES";
+
+/// Picks where card data comes from: a fixture directory if one is set,
+/// otherwise the Scryfall API (or the mirror set in the environment) backed
+/// by the local catalog and a response cache.
+///
+/// # Arguments
+///
+/// * `catalog` - The local bulk data catalog.
+/// * `cache` - The API response cache.
+///
+/// # Returns
+///
+/// * `Arc<dyn CardDataProvider>` - The card data provider.
+fn load_card_data(
+    catalog: &catalog::Catalog,
+    cache: http_cache::ResponseCache,
+) -> Arc<dyn services::card_data::CardDataProvider> {
+    if let Ok(dir) = std::env::var(SCRYFALL_FIXTURES_VAR) {
+        match services::fixtures::FixtureService::load(std::path::Path::new(&dir)) {
+            Ok(fixtures) => {
+                println!("Serving card data from fixtures in {}", dir);
+                return Arc::new(fixtures);
+            }
+            Err(e) => println!("{}, falling back to the Scryfall API", e),
+        }
+    }
+
+    let base_url = std::env::var(SCRYFALL_URL_VAR)
+        .unwrap_or_else(|_| services::scryfall::SCRYFALL_API_URL.to_string());
+    println!("Fetching card data from {}", base_url);
+    Arc::new(
+        services::scryfall::ScryfallService::with_base_url(&base_url)
+            .with_catalog(catalog.clone())
+            .with_cache(cache),
+    )
 }
 
 /// Initializes the database path.
@@ -273,8 +438,50 @@ pub fn run() {
 
             let conn = database::init_db(&db_path).expect("failed to init db");
 
+            let ocr = load_ocr(app).map(Arc::new);
+
+            let hashes =
+                database::operations::get_card_hashes(&conn).expect("failed to load card hashes");
+            let phash_index = hash_index::HashIndex::from_hex_entries(hashes);
+            println!("Indexed {} card hashes", phash_index.len());
+
+            let references = database::operations::get_reference_hash_entries(&conn)
+                .expect("failed to load reference hashes");
+            let reference_index = hash_index::ReferenceIndex::from_hex_entries(references);
+            println!("Indexed {} reference hashes", reference_index.len());
+
+            let symbols = database::operations::get_set_symbols(&conn)
+                .expect("failed to load set symbols");
+            let set_symbols = set_symbols::SetSymbolIndex::from_rows(symbols);
+            println!("Loaded {} set symbols", set_symbols.len());
+
+            let feedback = database::operations::get_feedback_entries(&conn)
+                .expect("failed to load recognition feedback");
+            let feedback_index = hash_index::FeedbackIndex::from_hex_entries(feedback);
+            println!("Indexed {} feedback scans", feedback_index.len());
+
+            let catalog = catalog::Catalog::new(app_dir.join("catalog.db"));
+            // Stale responses keep the app usable offline
+            let response_cache = http_cache::ResponseCache::new(app_dir.join("http_cache.db"))
+                .with_stale_if_error(true);
+            match response_cache.prune(services::scryfall::MAX_STALE) {
+                Ok(removed) => println!("Pruned {} cached responses", removed),
+                Err(e) => println!("Failed to prune response cache: {}", e),
+            }
+            let card_data = load_card_data(&catalog, response_cache);
+
             app.manage(AppState {
                 db: Mutex::new(conn),
+                ocr,
+                phash_index: Mutex::new(phash_index),
+                reference_index: Mutex::new(reference_index),
+                set_symbols: Mutex::new(set_symbols),
+                feedback_index: Mutex::new(feedback_index),
+                image_cache: image_cache::ImageCache::new(app_dir.join("image_cache")),
+                image_client: recognition::image_client(),
+                catalog,
+                card_data,
+                live_scans: Mutex::new(HashMap::new()),
             });
 
             Ok(())
@@ -283,9 +490,15 @@ pub fn run() {
             greet,
             init_db_command,
             recognize_card_with_features,
+            recognize_binder_page,
+            start_live_scan,
+            push_live_frame,
+            stop_live_scan,
+            evaluate_recognition,
             commands::scryfall::import_sets,
             commands::scryfall::get_sets,
             commands::sets::get_set_cards,
+            commands::sets::cache_set_symbols,
             commands::wishlist::add_to_wishlist,
             commands::wishlist::get_wishlist,
             commands::wishlist::remove_from_wishlist,
@@ -306,7 +519,22 @@ pub fn run() {
             commands::collection::calculate_missing_hashes,
             commands::collection::get_collection_sets,
             commands::analytics::get_collection_stats,
+            commands::catalog::import_catalog,
+            commands::catalog::get_catalog_info,
             commands::market::get_market_trends,
+            commands::feedback::record_recognition_feedback,
+            commands::reference::build_reference_hashes,
+            commands::reference::import_reference_archive,
+            commands::reference::get_reference_hash_count,
+            commands::scan_session::create_scan_session,
+            commands::scan_session::get_scan_sessions,
+            commands::scan_session::get_scan_items,
+            commands::scan_session::stage_scan,
+            commands::scan_session::select_scan_match,
+            commands::scan_session::update_scan_item,
+            commands::scan_session::remove_scan_item,
+            commands::scan_session::commit_scan_session,
+            commands::scan_session::delete_scan_session,
             commands::tags::create_tag,
             commands::tags::delete_tag,
             commands::tags::get_all_tags,
@@ -0,0 +1,361 @@
+use crate::recognition::RecognitionResult;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, RgbaImage};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, TryLockError};
+use tauri::ipc::Channel;
+
+/// Side of the thumbnail consecutive frames are compared on
+const SIGNATURE_SIDE: u32 = 64;
+
+/// Largest side frames are measured for sharpness at
+const SHARPNESS_SIDE: u32 = 480;
+
+/// Mean brightness change (0-255) between frames below which the camera and
+/// card count as still. Sensor noise alone is 1-2.
+const STILL_MOTION: f32 = 3.0;
+
+/// Mean brightness change from the recognized frame above which the card
+/// counts as gone, so the next one can be recognized
+const CHANGE_MOTION: f32 = 12.0;
+
+/// Variance of the Laplacian below which a frame is too blurry to read
+const MIN_SHARPNESS: f32 = 50.0;
+
+/// Consecutive still, sharp frames needed before recognizing
+const STILL_FRAMES: u32 = 4;
+
+/// Largest raw frame accepted, in pixels (a 4K camera frame)
+const MAX_FRAME_PIXELS: u64 = 3840 * 2160;
+
+/// Where a live scan is in the cycle of finding, recognizing and releasing a card
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LivePhase {
+    /// The view is moving or blurry
+    Searching,
+    /// The view is still and sharp, waiting for it to stay that way
+    Steadying,
+    /// A card is being recognized, frames are dropped meanwhile
+    Recognizing,
+    /// A card was recognized, waiting for it to leave the view
+    Locked,
+}
+
+/// Measurements of the last frame, for guiding the user
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FrameStatus {
+    pub phase: LivePhase,
+    /// Change from the previous frame (0-255), None for the first frame
+    pub motion: Option<f32>,
+    pub sharpness: f32,
+    pub still_frames: u32,
+}
+
+/// Events sent to the frontend over a live scan's channel
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "camelCase")]
+pub enum LiveScanEvent {
+    /// A frame was measured and the card isn't ready yet
+    Status(FrameStatus),
+    /// A still, sharp card was found and is being recognized
+    Recognizing,
+    /// The card was recognized. Its result holds until the card leaves the view.
+    Locked(Box<RecognitionResult>),
+    /// Recognition of the card failed. Moving the card retries.
+    Failed(String),
+    /// The recognized card left the view
+    Cleared,
+}
+
+/// What to do with a frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameStep {
+    /// Keep sending frames
+    Waiting(FrameStatus),
+    /// The frame shows a still, sharp card: recognize it
+    Recognize,
+    /// Dropped, a card is being recognized or is still in view
+    Ignored,
+    /// The recognized card left the view
+    Cleared,
+}
+
+/// Tracks frames of a live camera feed and decides when one is worth
+/// recognizing, so a card is recognized once per time it is shown.
+#[derive(Debug)]
+pub struct FrameStabilizer {
+    phase: LivePhase,
+    /// Signature of the previous frame
+    previous: Option<GrayImage>,
+    /// Signature of the recognized frame
+    locked: Option<GrayImage>,
+    still_frames: u32,
+}
+
+impl FrameStabilizer {
+    pub fn new() -> Self {
+        FrameStabilizer {
+            phase: LivePhase::Searching,
+            previous: None,
+            locked: None,
+            still_frames: 0,
+        }
+    }
+
+    /// Measures a frame and moves the scan along.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The camera frame.
+    ///
+    /// # Returns
+    ///
+    /// * `FrameStep` - Whether to recognize the frame.
+    pub fn observe(&mut self, frame: &DynamicImage) -> FrameStep {
+        match self.phase {
+            LivePhase::Recognizing => return FrameStep::Ignored,
+            LivePhase::Locked => {
+                let signature = frame_signature(frame);
+                let moved = self
+                    .locked
+                    .as_ref()
+                    .map_or(f32::MAX, |locked| mean_difference(locked, &signature));
+                if moved < CHANGE_MOTION {
+                    return FrameStep::Ignored;
+                }
+                *self = FrameStabilizer::new();
+                self.previous = Some(signature);
+                return FrameStep::Cleared;
+            }
+            LivePhase::Searching | LivePhase::Steadying => {}
+        }
+
+        let signature = frame_signature(frame);
+        let motion = self
+            .previous
+            .as_ref()
+            .map(|previous| mean_difference(previous, &signature));
+        let sharpness = frame_sharpness(frame);
+        let still = motion.is_some_and(|m| m <= STILL_MOTION) && sharpness >= MIN_SHARPNESS;
+        self.still_frames = if still { self.still_frames + 1 } else { 0 };
+
+        // Finding the card outline is the costly check, so it waits for a steady view
+        if self.still_frames >= STILL_FRAMES && crate::card_detection::find_card_quad(frame).is_some() {
+            self.phase = LivePhase::Recognizing;
+            self.locked = Some(signature);
+            self.previous = None;
+            return FrameStep::Recognize;
+        }
+
+        self.previous = Some(signature);
+        self.phase = if self.still_frames > 0 {
+            LivePhase::Steadying
+        } else {
+            LivePhase::Searching
+        };
+        FrameStep::Waiting(FrameStatus {
+            phase: self.phase,
+            motion,
+            sharpness,
+            still_frames: self.still_frames,
+        })
+    }
+
+    /// Locks the scan once the recognized frame has been dealt with, whether
+    /// or not recognition succeeded. The card must leave the view before
+    /// another one is recognized.
+    pub fn finish(&mut self) {
+        if self.phase == LivePhase::Recognizing {
+            self.phase = LivePhase::Locked;
+        }
+    }
+
+    /// Drops the frame being recognized, for when recognition never started.
+    /// The next frames start a fresh search.
+    pub fn reset(&mut self) {
+        *self = FrameStabilizer::new();
+    }
+}
+
+impl Default for FrameStabilizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A live camera scan: its stabilizer and the channel its events go to
+pub struct LiveScan {
+    /// Shared with the frames being measured, which run on blocking threads
+    /// outside the lock of the scans
+    pub stabilizer: Arc<Mutex<FrameStabilizer>>,
+    pub channel: Channel<LiveScanEvent>,
+}
+
+/// Measures a frame with a scan's stabilizer. A frame that arrives while the
+/// previous one is still being measured is ignored, the camera sends plenty.
+///
+/// # Arguments
+///
+/// * `stabilizer` - The scan's stabilizer.
+/// * `frame` - The camera frame.
+///
+/// # Returns
+///
+/// * `FrameStep` - Whether to recognize the frame.
+pub fn observe_frame(stabilizer: &Mutex<FrameStabilizer>, frame: &DynamicImage) -> FrameStep {
+    match stabilizer.try_lock() {
+        Ok(mut stabilizer) => stabilizer.observe(frame),
+        Err(TryLockError::WouldBlock) => FrameStep::Ignored,
+        Err(TryLockError::Poisoned(poisoned)) => {
+            // A frame that panicked mid-measurement left the state half updated
+            let mut stabilizer = poisoned.into_inner();
+            stabilizer.reset();
+            stabilizer.observe(frame)
+        }
+    }
+}
+
+/// Wraps a raw RGBA camera frame as an image.
+///
+/// # Arguments
+///
+/// * `width` - Frame width in pixels.
+/// * `height` - Frame height in pixels.
+/// * `pixels` - RGBA bytes, row by row.
+///
+/// # Returns
+///
+/// * `Result<DynamicImage, String>` - The frame, or an error message if the size doesn't match.
+pub fn frame_from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<DynamicImage, String> {
+    if width as u64 * height as u64 > MAX_FRAME_PIXELS {
+        return Err(format!("Frame is too large ({}x{})", width, height));
+    }
+    let received = pixels.len();
+    RgbaImage::from_raw(width, height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| format!("Expected {}x{} RGBA pixels, got {} bytes", width, height, received))
+}
+
+/// Small grayscale thumbnail of a frame, for measuring motion
+fn frame_signature(frame: &DynamicImage) -> GrayImage {
+    frame
+        .resize_exact(SIGNATURE_SIDE, SIGNATURE_SIDE, FilterType::Triangle)
+        .to_luma8()
+}
+
+/// Mean absolute brightness difference of two signatures
+fn mean_difference(a: &GrayImage, b: &GrayImage) -> f32 {
+    let total: u32 = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(p, q)| p[0].abs_diff(q[0]) as u32)
+        .sum();
+    total as f32 / (SIGNATURE_SIDE * SIGNATURE_SIDE) as f32
+}
+
+/// Variance of the Laplacian of the middle of a frame. Blur removes fine
+/// detail, which is what the Laplacian responds to.
+fn frame_sharpness(frame: &DynamicImage) -> f32 {
+    let small = if frame.width().max(frame.height()) > SHARPNESS_SIDE {
+        frame.resize(SHARPNESS_SIDE, SHARPNESS_SIDE, FilterType::Triangle)
+    } else {
+        frame.clone()
+    };
+    let (width, height) = (small.width(), small.height());
+    let center = small
+        .crop_imm(width / 5, height / 5, width * 3 / 5, height * 3 / 5)
+        .to_luma8();
+    let laplacian = imageproc::filter::laplacian_filter(&center);
+
+    let count = laplacian.pixels().len() as f32;
+    if count == 0.0 {
+        return 0.0;
+    }
+    let mean = laplacian.pixels().map(|p| p[0] as f32).sum::<f32>() / count;
+    laplacian
+        .pixels()
+        .map(|p| (p[0] as f32 - mean).powi(2))
+        .sum::<f32>()
+        / count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// A textured card on a plain background
+    fn card_frame() -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(800, 800, |x, y| {
+            let on_card = (200..515).contains(&x) && (100..540).contains(&y);
+            if !on_card {
+                Rgb([30, 90, 40])
+            } else if (x / 4 + y / 4) % 2 == 0 {
+                Rgb([230, 230, 220])
+            } else {
+                Rgb([170, 160, 150])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_stabilizer_recognizes_still_card_once() {
+        let frame = card_frame();
+        let mut stabilizer = FrameStabilizer::new();
+
+        // The first frame has nothing to compare with, then it takes a few still ones
+        for _ in 0..STILL_FRAMES {
+            assert!(matches!(stabilizer.observe(&frame), FrameStep::Waiting(_)));
+        }
+        assert_eq!(stabilizer.observe(&frame), FrameStep::Recognize);
+        assert_eq!(stabilizer.observe(&frame), FrameStep::Ignored);
+
+        // The same card stays locked until it leaves the view
+        stabilizer.finish();
+        assert_eq!(stabilizer.observe(&frame), FrameStep::Ignored);
+        let empty = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(800, 800, Rgb([30, 90, 40])));
+        assert_eq!(stabilizer.observe(&empty), FrameStep::Cleared);
+        assert!(matches!(stabilizer.observe(&empty), FrameStep::Waiting(FrameStatus { phase: LivePhase::Searching, .. })));
+    }
+
+    #[test]
+    fn test_shared_stabilizer_skips_busy_frames_and_resets() {
+        let frame = card_frame();
+        let stabilizer = Mutex::new(FrameStabilizer::new());
+        for _ in 0..STILL_FRAMES {
+            observe_frame(&stabilizer, &frame);
+        }
+
+        // Another frame is still being measured
+        let busy = stabilizer.lock().unwrap();
+        assert_eq!(observe_frame(&stabilizer, &frame), FrameStep::Ignored);
+        drop(busy);
+        assert_eq!(observe_frame(&stabilizer, &frame), FrameStep::Recognize);
+
+        // Recognition couldn't be announced, so the card is searched for again
+        stabilizer.lock().unwrap().reset();
+        assert!(matches!(observe_frame(&stabilizer, &frame), FrameStep::Waiting(_)));
+    }
+
+    #[test]
+    fn test_stabilizer_waits_for_sharp_frames() {
+        let blurred = card_frame().blur(4.0);
+        let mut stabilizer = FrameStabilizer::new();
+        for _ in 0..STILL_FRAMES * 2 {
+            match stabilizer.observe(&blurred) {
+                FrameStep::Waiting(status) => {
+                    assert_eq!(status.phase, LivePhase::Searching);
+                    assert!(status.sharpness < MIN_SHARPNESS);
+                }
+                step => panic!("unexpected step {:?}", step),
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_from_rgba_checks_size() {
+        assert!(frame_from_rgba(2, 2, vec![0; 16]).is_ok());
+        assert!(frame_from_rgba(2, 2, vec![0; 15]).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// A user photo labeled as showing (or not showing) a printing.
+/// Confirmed photos act as extra exemplars for the local lookup, and both
+/// kinds tune the printing's acceptance threshold.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecognitionFeedback {
+    pub id: i64,
+    /// Scryfall ID of the printing
+    pub scryfall_id: String,
+    /// Card name
+    pub name: String,
+    /// Set code (e.g., "dom", "neo")
+    pub set_code: String,
+    /// Collector number in the set
+    pub collector_number: String,
+    /// URI for the card image
+    pub image_uri: Option<String>,
+    /// dHash of the photo (hex string)
+    pub phash: String,
+    /// Full hash set of the photo (see `card_hash::CardHashes::to_hex`)
+    pub hashes: String,
+    /// Algorithm version that produced `hashes`
+    pub phash_algo: String,
+    /// True if the user picked this printing, false if it was ranked above the pick
+    pub confirmed: bool,
+    pub created_at: String,
+}
+
+impl RecognitionFeedback {
+    /// The photo's hashes, for scoring against candidates
+    pub fn stored_hashes(&self) -> Option<crate::card_hash::StoredHashes> {
+        crate::card_hash::StoredHashes::parse(&self.phash, Some(&self.hashes), Some(&self.phash_algo))
+    }
+}
@@ -1,5 +1,8 @@
 pub mod analytics;
 pub mod collection;
+pub mod feedback;
+pub mod reference;
+pub mod scan_session;
 pub mod scryfall;
 pub mod tags;
 pub mod wishlist;
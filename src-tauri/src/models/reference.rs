@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Perceptual hashes of one printing, used for offline recognition of
+/// cards that aren't in the collection.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReferenceHash {
+    /// Scryfall ID of the printing
+    pub scryfall_id: String,
+    /// Card name
+    pub name: String,
+    /// Set code (e.g., "dom", "neo")
+    pub set_code: String,
+    /// Collector number in the set
+    pub collector_number: String,
+    /// URI of the image the hashes were computed from
+    pub image_uri: Option<String>,
+    /// Hash of the whole card (hex string)
+    pub phash: String,
+    /// Hash of the art box only (hex string)
+    pub art_phash: Option<String>,
+    /// Full multi-algorithm hash set (see `card_hash::CardHashes::to_hex`)
+    pub hashes: Option<String>,
+    /// Algorithm version that produced `hashes`
+    pub phash_algo: Option<String>,
+    /// Where the hashes came from ("scryfall", "archive" or "search")
+    pub source: String,
+}
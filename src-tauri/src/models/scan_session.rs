@@ -0,0 +1,42 @@
+use crate::models::scryfall::ScryfallCard;
+use serde::{Deserialize, Serialize};
+
+/// A batch of scans staged before they are added to the collection.
+/// Sessions stay open until committed, so an intake can be resumed later.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScanSession {
+    pub id: String,
+    pub name: String,
+    /// "open" while scanning, "committed" once added to the collection
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Number of staged entries
+    pub item_count: i32,
+    /// Total quantity across the staged entries
+    pub card_count: i32,
+}
+
+/// A staged scan: the photo, the recognizer's top candidates and the chosen match.
+/// Repeated scans of the same printing, condition, language and finish are
+/// merged into one entry with a higher quantity.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScanItem {
+    pub id: String,
+    pub session_id: String,
+    /// Where the scanned photo was saved, if one was provided
+    pub image_path: Option<String>,
+    /// Top recognition candidates, best first
+    pub candidates: Vec<ScryfallCard>,
+    /// The match chosen by the user, None until one is selected
+    pub selected: Option<ScryfallCard>,
+    pub condition: String,
+    /// Language name (e.g. "English"), as stored in the collection
+    pub language: String,
+    pub finish: String,
+    pub purchase_price: f64,
+    pub quantity: i32,
+    /// Perceptual hash of the scanned card (hex string)
+    pub phash: Option<String>,
+    pub created_at: String,
+}
@@ -27,6 +27,15 @@ pub struct ScryfallCardList {
     pub total_cards: Option<i32>,
 }
 
+/// Represents the cards returned by Scryfall's `/cards/collection` endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScryfallCollection {
+    pub data: Vec<ScryfallCard>,
+    /// Identifiers that matched no card
+    #[serde(default)]
+    pub not_found: Vec<serde_json::Value>,
+}
+
 /// Represents a single Magic: The Gathering card from Scryfall.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ScryfallCard {
@@ -42,8 +51,10 @@ pub struct ScryfallCard {
     pub image_uris: Option<ImageUris>,
     pub prices: Prices,
     pub rarity: String,
-    #[serde(default, skip_deserializing)]
+    #[serde(default)]
     pub similarity: Option<u32>, // Hamming distance (lower is better)
+    #[serde(default)]
+    pub match_confidence: Option<f32>, // Calibrated fused-hash confidence (0-1)
 }
 
 /// Represents the image URIs for a card.
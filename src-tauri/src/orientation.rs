@@ -0,0 +1,166 @@
+use crate::card_features::CardImage;
+use image::metadata::Orientation;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageReader};
+use std::io::Cursor;
+
+/// How much brighter and plainer the art half must look than the text box
+/// half before a card is turned around. Full-art and borderless cards score
+/// near zero and are left alone.
+const UPSIDE_DOWN_MARGIN: f32 = 0.06;
+
+/// Decodes a photo and applies its EXIF orientation, so a phone photo taken
+/// sideways comes out the way it was framed.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded photo.
+/// * `limits` - Decoding limits, an image over them is an error.
+///
+/// # Returns
+///
+/// * `Result<(DynamicImage, u32), String>` - The photo and the clockwise degrees it was turned, or an error message.
+pub fn load_photo(bytes: &[u8], limits: image::Limits) -> Result<(DynamicImage, u32), String> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    reader.limits(limits);
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut photo =
+        DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to load image: {}", e))?;
+
+    photo.apply_orientation(orientation);
+    Ok((photo, orientation_degrees(orientation)))
+}
+
+/// Clockwise rotation of an EXIF orientation (a mirrored photo is still turned)
+fn orientation_degrees(orientation: Orientation) -> u32 {
+    match orientation {
+        Orientation::NoTransforms | Orientation::FlipHorizontal => 0,
+        Orientation::Rotate90 | Orientation::Rotate90FlipH => 90,
+        Orientation::Rotate180 | Orientation::FlipVertical => 180,
+        Orientation::Rotate270 | Orientation::Rotate270FlipH => 270,
+    }
+}
+
+/// Turns an image clockwise by a multiple of 90 degrees
+pub fn rotate(image: &DynamicImage, degrees: u32) -> DynamicImage {
+    match degrees % 360 {
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        _ => image.clone(),
+    }
+}
+
+/// Turns a rectified card upside down if its layout says it is.
+///
+/// Rectification always yields a portrait card, so a card is either upright
+/// or upside down. Upright cards have the art in the top half and the pale,
+/// plain text box in the bottom half.
+///
+/// # Arguments
+///
+/// * `card` - The prepared card image.
+///
+/// # Returns
+///
+/// * `CardImage` - The card, upright if its layout could be read.
+pub fn orient_card(card: CardImage) -> CardImage {
+    if !card.rectified || upright_score(&card.image) > -UPSIDE_DOWN_MARGIN {
+        return card;
+    }
+
+    println!("Card is upside down, turning it around");
+    CardImage {
+        image: card.image.rotate180(),
+        rectified: true,
+        rotation: (card.rotation + 180) % 360,
+    }
+}
+
+/// How much brighter and less saturated the bottom half of a card is than
+/// the top half. Positive for an upright card with a text box.
+fn upright_score(image: &DynamicImage) -> f32 {
+    let (art_luma, art_saturation) = band_stats(image, 0.15, 0.45);
+    let (text_luma, text_saturation) = band_stats(image, 0.58, 0.88);
+    (text_luma - art_luma) + (art_saturation - text_saturation)
+}
+
+/// Mean brightness and saturation (0-1) of a horizontal band, inside the frame
+fn band_stats(image: &DynamicImage, from: f32, to: f32) -> (f32, f32) {
+    let (width, height) = image.dimensions();
+    let (x0, x1) = ((width as f32 * 0.12) as u32, (width as f32 * 0.88) as u32);
+    let (y0, y1) = ((height as f32 * from) as u32, (height as f32 * to) as u32);
+
+    let mut luma = 0.0;
+    let mut saturation = 0.0;
+    let mut pixel_count = 0u32;
+    for y in (y0..y1).step_by(4) {
+        for x in (x0..x1).step_by(4) {
+            let pixel = image.get_pixel(x, y);
+            let max = pixel[0].max(pixel[1]).max(pixel[2]) as f32 / 255.0;
+            let min = pixel[0].min(pixel[1]).min(pixel[2]) as f32 / 255.0;
+            luma += (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.0;
+            saturation += if max > 0.0 { (max - min) / max } else { 0.0 };
+            pixel_count += 1;
+        }
+    }
+
+    if pixel_count == 0 {
+        return (0.0, 0.0);
+    }
+    (luma / pixel_count as f32, saturation / pixel_count as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// A card with dark, colorful art over a pale text box
+    fn upright_card() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(630, 880, |_, y| {
+            if y < 440 {
+                Rgb([40, 90, 150])
+            } else {
+                Rgb([230, 225, 215])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_orient_card_turns_upside_down_card() {
+        let upright = upright_card();
+        let card = CardImage {
+            image: upright.rotate180(),
+            rectified: true,
+            rotation: 90,
+        };
+
+        let oriented = orient_card(card);
+        assert_eq!(oriented.rotation, 270);
+        assert_eq!(oriented.image.to_rgb8(), upright.to_rgb8());
+
+        // Already upright, and a photo without a card outline, are left alone
+        let card = CardImage { image: upright.clone(), rectified: true, rotation: 0 };
+        assert_eq!(orient_card(card).rotation, 0);
+        let card = CardImage { image: upright.rotate180(), rectified: false, rotation: 0 };
+        assert_eq!(orient_card(card).rotation, 0);
+    }
+
+    #[test]
+    fn test_load_photo_without_exif() {
+        let mut bytes = Vec::new();
+        upright_card()
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+
+        let (photo, rotation) = load_photo(&bytes, image::Limits::default()).unwrap();
+        assert_eq!(rotation, 0);
+        assert_eq!(photo.dimensions(), (630, 880));
+        assert_eq!(orientation_degrees(Orientation::Rotate90FlipH), 90);
+    }
+}
@@ -0,0 +1,1118 @@
+use crate::card_condition::{estimate_condition, ConditionEstimate};
+use crate::card_finish::{estimate_finish, FinishEstimate};
+use crate::card_features::{
+    calculate_phash, extract_features_from_image, prepare_card_image, CardFeatures, CardImage, FrameStyle,
+};
+use crate::card_filter::{describe_features, relaxed_queries};
+use crate::card_hash::{self, CardHashes, StoredHashes};
+use crate::card_ocr::{CardOcr, InfoLine};
+use crate::card_segmentation::CardSlot;
+use crate::models::scryfall::{ImageUris, Prices, ScryfallCard};
+use crate::set_symbols::{extract_symbol_mask, SymbolMask};
+use crate::{commands, database, hash_index, orientation, AppState};
+use crate::image_cache::ImageCache;
+use image::DynamicImage;
+use serde::Serialize;
+use std::time::Instant;
+
+/// What the recognizer found for one card image
+#[derive(Debug, Clone, Serialize)]
+pub struct RecognitionResult {
+    pub features: CardFeatures,
+    pub detected_name: String,
+    pub feature_description: String,
+    pub search_query: String,
+    pub candidates: Vec<ScryfallCard>,
+    /// Scryfall language code read from the info line, if it was legible
+    pub detected_language: Option<String>,
+    /// Hashes of the scanned card (hex), sent back with recognition feedback
+    pub image_hashes: String,
+    /// Clockwise degrees the photo was turned to make the card upright
+    pub rotation: u32,
+    /// Suggested condition, None if the card's outline wasn't found
+    pub condition: Option<ConditionEstimate>,
+    /// Suggested finish, only when a burst of frames was given
+    pub finish: Option<FinishEstimate>,
+    pub timings: StageTimings,
+}
+
+/// Time spent in each stage of recognizing a photo, in milliseconds
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct StageTimings {
+    /// Decoding, EXIF orientation and scaling to the working resolution.
+    /// For a binder page, the whole page's
+    pub decode_ms: f64,
+    /// Finding, rectifying and orienting the card. For a binder page,
+    /// splitting the whole page
+    pub prepare_ms: f64,
+    /// Hash orientation, feature detectors, set symbol, condition and hashes
+    pub features_ms: f64,
+    /// Name and info-line OCR
+    pub ocr_ms: f64,
+    /// Local lookups, and the Scryfall search and ranking when online
+    pub matching_ms: f64,
+}
+
+/// Recognition of one card in a photo of several cards
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotRecognition {
+    #[serde(flatten)]
+    pub slot: CardSlot,
+    /// None if recognition failed for this card, see `error`
+    pub result: Option<RecognitionResult>,
+    pub error: Option<String>,
+}
+
+/// Recognizes a single prepared card image.
+///
+/// Tries, in order: the printing read from the info line, the collection's
+/// hash index, the confirmed feedback scans, the reference catalog, and finally
+/// a Scryfall search built from the visual features, ranked by hash similarity.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `card_image` - The card, rectified if its outline was found.
+/// * `online` - Whether Scryfall may be queried. Offline, only local matches are returned.
+///
+/// # Returns
+///
+/// * `Result<RecognitionResult, String>` - The detected features and ranked candidates, or an error message.
+pub async fn recognize_card(
+    state: &AppState,
+    card_image: CardImage,
+    online: bool,
+) -> Result<RecognitionResult, String> {
+    let mut timings = StageTimings::default();
+    let features_started = Instant::now();
+
+    // A photo that matches a known card better when turned is turned,
+    // before anything is measured or hashed. Hashing and measuring are
+    // too slow for the async executor, so they run on blocking threads.
+    let (card_image, turns) = tokio::task::spawn_blocking(move || {
+        let turns = rotation_hashes(&card_image);
+        (card_image, turns)
+    })
+    .await
+    .map_err(|e| format!("Failed to hash card: {}", e))?;
+    let turn = rotation_by_hash_agreement(state, &turns)?;
+
+    let read_symbol = card_image.rectified
+        && !state
+            .set_symbols
+            .lock()
+            .map_err(|_| "Failed to lock set symbols".to_string())?
+            .is_empty();
+    let ocr = state.ocr.clone();
+    let measured = tokio::task::spawn_blocking(move || {
+        measure_card(turn_card(card_image, turn), read_symbol, ocr.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to measure card: {}", e))?;
+    let CardMeasurements {
+        rotation,
+        mut features,
+        symbol,
+        condition,
+        hashes: user_hashes,
+        art_hash,
+        name_read,
+        info_line,
+        ocr_ms,
+    } = measured;
+
+    // Pin down the edition from the expansion symbol (needs the card's layout)
+    if let Some(symbol) = symbol {
+        if let Some((set_code, confidence)) = state
+            .set_symbols
+            .lock()
+            .map_err(|_| "Failed to lock set symbols".to_string())?
+            .detect_set(&symbol)
+        {
+            features.set_code = Some(set_code);
+            features.confidence.set_symbol = confidence;
+        }
+    }
+
+    // --- Phase 2: Offline Recognition (Local DB) ---
+    // Check if we have this card in our collection or the reference catalog.
+    // This is instant and works offline
+    let user_hash = user_hashes.dhash;
+    timings.ocr_ms = ocr_ms;
+    timings.features_ms = elapsed_ms(features_started) - ocr_ms;
+
+    if let Some((name, confidence)) = &name_read {
+        println!("OCR detected name: {} (confidence={:.2})", name, confidence);
+    }
+    let detected_name = name_read.as_ref().map(|(name, _)| name.clone()).unwrap_or_default();
+    let detected_language = info_line.as_ref().map(|info| info.language.clone());
+    let matching_started = Instant::now();
+
+    if let Some(info) = &info_line {
+        println!(
+            "OCR detected printing: {} #{} ({})",
+            info.set_code, info.collector_number, info.language
+        );
+        let printing_query = format!("set:{} cn:{}", info.set_code, info.collector_number);
+        if let Some(card) = resolve_printing(state, info, &printing_query, online).await? {
+            println!("Resolved printing: {} ({} #{})", card.name, card.set, card.collector_number);
+            return Ok(RecognitionResult {
+                features: features.clone(),
+                detected_name,
+                feature_description: describe_features(&features),
+                search_query: printing_query,
+                candidates: vec![card],
+                detected_language,
+                image_hashes: user_hashes.to_hex(),
+                rotation,
+                condition: condition.clone(),
+                finish: None,
+                timings: StageTimings {
+                    matching_ms: elapsed_ms(matching_started),
+                    ..timings
+                },
+            });
+        }
+    }
+
+    // Photos the user said were not a printing rule it out for a near-identical photo
+    let rejected_printings = rejected_printings(state, &user_hashes)?;
+
+    // Query the in-memory hash index instead of scanning the cards table
+    let collection_matches = state
+        .phash_index
+        .lock()
+        .map_err(|_| "Failed to lock hash index".to_string())?
+        .query(user_hash, hash_index::CANDIDATE_RADIUS, 5);
+
+    let mut local_cards = Vec::new();
+    if !collection_matches.is_empty() {
+        let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+        let mut candidates = Vec::new();
+        for m in collection_matches {
+            let Some(card) = database::operations::get_card_by_id(&db, &m.id).map_err(|e| e.to_string())? else {
+                continue;
+            };
+            let full_hashes = database::operations::get_card_hash_set(&db, &m.id).map_err(|e| e.to_string())?;
+            let Some(hashes) = card.phash.as_deref().and_then(|phash| {
+                StoredHashes::parse(phash, full_hashes.as_deref(), Some(card_hash::HASH_ALGORITHM))
+            }) else {
+                continue;
+            };
+            if rejected_printings.contains(&card.scryfall_id) {
+                continue;
+            }
+
+            let threshold = printing_threshold(&db, &card.scryfall_id, &hashes)?;
+            candidates.push(LocalCandidate {
+                printing: LocalPrinting {
+                    scryfall_id: card.scryfall_id,
+                    name: card.name,
+                    set_code: card.set_code,
+                    collector_number: card.collector_number,
+                    image_uri: card.image_uri,
+                },
+                distance: m.distance,
+                hashes,
+                confirmed: false,
+                threshold,
+            });
+        }
+
+        local_cards = local_scryfall_cards("collection", rank_local_candidates(candidates, &user_hashes));
+    }
+
+    // Photos the user confirmed before, taken under their own lighting
+    if local_cards.is_empty() {
+        let exemplar_matches = state
+            .feedback_index
+            .lock()
+            .map_err(|_| "Failed to lock feedback index".to_string())?
+            .query(user_hash, true, 5);
+
+        if !exemplar_matches.is_empty() {
+            let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+            let mut candidates = Vec::new();
+            for m in exemplar_matches {
+                if rejected_printings.contains(&m.scryfall_id) {
+                    continue;
+                }
+                let Some(feedback) = database::operations::get_recognition_feedback(&db, m.id)
+                    .map_err(|e| e.to_string())?
+                else {
+                    continue;
+                };
+                let Some(hashes) = feedback.stored_hashes() else {
+                    continue;
+                };
+                candidates.push(LocalCandidate {
+                    printing: LocalPrinting {
+                        scryfall_id: feedback.scryfall_id,
+                        name: feedback.name,
+                        set_code: feedback.set_code,
+                        collector_number: feedback.collector_number,
+                        image_uri: feedback.image_uri,
+                    },
+                    distance: m.distance,
+                    hashes,
+                    confirmed: false,
+                    threshold: card_hash::MATCH_SCORE,
+                });
+            }
+
+            local_cards = local_scryfall_cards("exemplar", rank_local_candidates(candidates, &user_hashes));
+        }
+    }
+
+    // Not owned: try the reference catalog of every hashed printing
+    if local_cards.is_empty() {
+        let reference_matches = state
+            .reference_index
+            .lock()
+            .map_err(|_| "Failed to lock reference index".to_string())?
+            .candidates(user_hash, art_hash, 5);
+
+        if !reference_matches.is_empty() {
+            let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+            let mut candidates = Vec::new();
+            for m in reference_matches {
+                if rejected_printings.contains(&m.id) {
+                    continue;
+                }
+                let Some(reference) = database::operations::get_reference_hash(&db, &m.id)
+                    .map_err(|e| e.to_string())?
+                else {
+                    continue;
+                };
+                let Some(hashes) = StoredHashes::parse(
+                    &reference.phash,
+                    reference.hashes.as_deref(),
+                    reference.phash_algo.as_deref(),
+                ) else {
+                    continue;
+                };
+
+                let threshold = printing_threshold(&db, &reference.scryfall_id, &hashes)?;
+                candidates.push(LocalCandidate {
+                    printing: LocalPrinting {
+                        scryfall_id: reference.scryfall_id,
+                        name: reference.name,
+                        set_code: reference.set_code,
+                        collector_number: reference.collector_number,
+                        image_uri: reference.image_uri,
+                    },
+                    distance: m.distance,
+                    hashes,
+                    confirmed: m.art_confirmed,
+                    threshold,
+                });
+            }
+
+            local_cards = local_scryfall_cards("reference", rank_local_candidates(candidates, &user_hashes));
+        }
+    }
+
+    if !local_cards.is_empty() || !online {
+        return Ok(RecognitionResult {
+            features: features.clone(),
+            detected_name,
+            feature_description: describe_features(&features),
+            search_query: String::new(),
+            candidates: local_cards,
+            detected_language,
+            image_hashes: user_hashes.to_hex(),
+            rotation,
+            condition: condition.clone(),
+            finish: None,
+            timings: StageTimings {
+                matching_ms: elapsed_ms(matching_started),
+                ..timings
+            },
+        });
+    }
+
+    // Build search queries based on features and the OCR'd name. One wrong
+    // detection gives zero results, so the next queries drop filters, least
+    // trusted detection first, until one finds cards
+    let feature_description = describe_features(&features);
+    let mut search_query = String::new();
+    let mut candidates = Vec::new();
+    for query in relaxed_queries(&features, name_read) {
+        candidates = state.card_data.search_cards(&query, 1)
+            .await
+            .map_err(|e| format!("Scryfall search failed: {}", e))?
+            .data;
+        search_query = query;
+        if !candidates.is_empty() {
+            break;
+        }
+        println!("No cards for \"{}\", relaxing the query", search_query);
+    }
+
+    // --- Image Comparison & Ranking ---
+    let num_to_compare = CANDIDATES_TO_COMPARE.min(candidates.len());
+    if num_to_compare > 0 {
+        use crate::card_features::hamming_distance;
+
+        println!("User Image Hash: {:x}", user_hash);
+
+        let mut scored_candidates: Vec<ScryfallCard> = candidates.drain(..num_to_compare).collect();
+        let hashes = candidate_hashes(state, &scored_candidates).await;
+        for (card, card_hashes) in scored_candidates.iter_mut().zip(hashes) {
+            if let Some(card_hashes) = card_hashes {
+                let dist = hamming_distance(user_hash, card_hashes.dhash);
+                let score = user_hashes.similarity(&card_hashes);
+                card.similarity = Some(dist);
+                card.match_confidence = Some(card_hash::match_confidence(score));
+                println!("Compared with {}: dist={}, score={:.3}", card.name, dist, score);
+            }
+        }
+
+        // Add back the rest of the candidates (unscored)
+        scored_candidates.extend(candidates);
+
+        // Sort by fused confidence (descending), which breaks the dHash ties
+        // between reprints of the same art
+        // Cards with no confidence (failed download) go to the end
+        scored_candidates.sort_by(|a, b| {
+            match (a.match_confidence, b.match_confidence) {
+                (Some(c1), Some(c2)) => c2.total_cmp(&c1),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        });
+
+        candidates = scored_candidates;
+    }
+    
+    Ok(RecognitionResult {
+        features,
+        detected_name,
+        feature_description,
+        search_query,
+        candidates,
+        detected_language,
+        image_hashes: user_hashes.to_hex(),
+        rotation,
+        condition,
+        finish: None,
+        timings: StageTimings {
+            matching_ms: elapsed_ms(matching_started),
+            ..timings
+        },
+    })
+}
+
+/// Candidates compared against the photo, the rest keep Scryfall's order
+const CANDIDATES_TO_COMPARE: usize = 30;
+
+/// Local matches returned for a photo, best first
+const LOCAL_CANDIDATES: usize = 5;
+
+/// Thumbnails downloaded at once, to stay polite to the image server
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// How long a thumbnail download may take before the candidate is compared
+/// without it
+const DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Largest encoded photo accepted, in bytes
+pub const MAX_PHOTO_BYTES: usize = 40 * 1024 * 1024;
+
+/// Largest photo side accepted, in pixels. A 48MP phone photo is 8000x6000.
+const MAX_PHOTO_SIDE: u32 = 12_000;
+
+/// Longest side photos are scaled down to before any detector runs. Plenty
+/// for a single card (rectified to 630x880) and for the nine of a binder page.
+const WORKING_SIDE: u32 = 2400;
+
+/// Decodes a photo once for the whole pipeline: rejects oversized input,
+/// applies the EXIF orientation and scales it down to the working resolution.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded photo.
+///
+/// # Returns
+///
+/// * `Result<(DynamicImage, u32), String>` - The photo and the clockwise degrees EXIF turned it, or an error message.
+pub fn decode_photo(bytes: &[u8]) -> Result<(DynamicImage, u32), String> {
+    if bytes.len() > MAX_PHOTO_BYTES {
+        return Err(format!(
+            "Image is too large ({} MB, the limit is {} MB)",
+            bytes.len() / (1024 * 1024),
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_PHOTO_SIDE);
+    limits.max_image_height = Some(MAX_PHOTO_SIDE);
+    let (photo, exif_rotation) = orientation::load_photo(bytes, limits)?;
+
+    if photo.width().max(photo.height()) <= WORKING_SIDE {
+        return Ok((photo, exif_rotation));
+    }
+    Ok((photo.thumbnail(WORKING_SIDE, WORKING_SIDE), exif_rotation))
+}
+
+/// Decodes a photo and finds and rectifies the card in it, on a blocking
+/// thread.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded photo.
+///
+/// # Returns
+///
+/// * `Result<(CardImage, StageTimings), String>` - The card with the decoding and preparing timed, or an error message.
+pub async fn prepare_photo(bytes: Vec<u8>) -> Result<(CardImage, StageTimings), String> {
+    tokio::task::spawn_blocking(move || {
+        let started = Instant::now();
+        let (photo, exif_rotation) = decode_photo(&bytes)?;
+        let decode_ms = elapsed_ms(started);
+
+        // Find the card in the photo and correct its perspective, so the
+        // detectors and the hash see the card rather than the table around it
+        let started = Instant::now();
+        let card_image = prepare_rotated(photo, exif_rotation);
+        let timings = StageTimings {
+            decode_ms,
+            prepare_ms: elapsed_ms(started),
+            ..StageTimings::default()
+        };
+        Ok((card_image, timings))
+    })
+    .await
+    .map_err(|e| format!("Failed to prepare photo: {}", e))?
+}
+
+/// Finds and rectifies the card in a photo already turned by `rotation`
+fn prepare_rotated(photo: DynamicImage, rotation: u32) -> CardImage {
+    let mut card_image = prepare_card_image(photo);
+    card_image.rotation = (card_image.rotation + rotation) % 360;
+    if !card_image.rectified {
+        println!("No card outline found, using the full photo");
+    }
+    card_image
+}
+
+/// Recognizes the card in an encoded photo: decodes it once, finds and
+/// rectifies the card, then runs [`recognize_card`].
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `bytes` - The encoded photo.
+/// * `online` - Whether Scryfall may be queried.
+///
+/// # Returns
+///
+/// * `Result<RecognitionResult, String>` - The recognition with every stage timed, or an error message.
+pub async fn recognize_photo(
+    state: &AppState,
+    bytes: Vec<u8>,
+    online: bool,
+) -> Result<RecognitionResult, String> {
+    let (card_image, timings) = prepare_photo(bytes).await?;
+    recognize_prepared(state, card_image, timings, online).await
+}
+
+/// Runs [`recognize_card`] on a card from [`prepare_photo`], keeping its
+/// decoding and preparing times.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `card_image` - The prepared card.
+/// * `timings` - The decoding and preparing times.
+/// * `online` - Whether Scryfall may be queried.
+///
+/// # Returns
+///
+/// * `Result<RecognitionResult, String>` - The recognition with every stage timed, or an error message.
+pub async fn recognize_prepared(
+    state: &AppState,
+    card_image: CardImage,
+    timings: StageTimings,
+    online: bool,
+) -> Result<RecognitionResult, String> {
+    let mut result = recognize_card(state, card_image, online).await?;
+    result.timings.decode_ms = timings.decode_ms;
+    result.timings.prepare_ms = timings.prepare_ms;
+    Ok(result)
+}
+
+/// Recognizes the card in a decoded photo or camera frame: finds and
+/// rectifies the card, then runs [`recognize_card`].
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `photo` - The photo, upright as far as its metadata says.
+/// * `rotation` - Clockwise degrees the photo was already turned.
+/// * `online` - Whether Scryfall may be queried.
+///
+/// # Returns
+///
+/// * `Result<RecognitionResult, String>` - The recognition, or an error message.
+pub async fn recognize_image(
+    state: &AppState,
+    photo: DynamicImage,
+    rotation: u32,
+    online: bool,
+) -> Result<RecognitionResult, String> {
+    let started = Instant::now();
+    let card_image = tokio::task::spawn_blocking(move || prepare_rotated(photo, rotation))
+        .await
+        .map_err(|e| format!("Failed to prepare photo: {}", e))?;
+    let prepare_ms = elapsed_ms(started);
+
+    let mut result = recognize_card(state, card_image, online).await?;
+    result.timings.prepare_ms = prepare_ms;
+    Ok(result)
+}
+
+/// Estimates a card's finish from a burst of photos of it, tilted a little
+/// between shots. Photos whose card outline isn't found are left out. The
+/// photos are decoded and rectified on a blocking thread.
+///
+/// # Arguments
+///
+/// * `first` - The burst's first frame, already rectified for recognition, None if its outline wasn't found.
+/// * `photos` - The rest of the burst, encoded.
+///
+/// # Returns
+///
+/// * `Result<Option<FinishEstimate>, String>` - The suggested finish, None if too few cards were found, or an error message.
+pub async fn estimate_burst_finish(
+    first: Option<DynamicImage>,
+    photos: Vec<Vec<u8>>,
+) -> Result<Option<FinishEstimate>, String> {
+    tokio::task::spawn_blocking(move || {
+        let frames = photos.len() + 1;
+        let mut cards = Vec::with_capacity(frames);
+        cards.extend(first);
+        for bytes in &photos {
+            let (photo, _) = decode_photo(bytes)?;
+            let card_image = prepare_card_image(photo);
+            if card_image.rectified {
+                cards.push(card_image.image);
+            }
+        }
+        if cards.len() < frames {
+            println!("Card outline found in {} of {} burst frames", cards.len(), frames);
+        }
+
+        let estimate = estimate_finish(&cards);
+        if let Some(estimate) = &estimate {
+            println!(
+                "Finish: {:?} (art rainbow {:.3}, art shimmer {:.3}, frame shimmer {:.3})",
+                estimate.finish, estimate.art_rainbow, estimate.art_shimmer, estimate.frame_shimmer
+            );
+        }
+        Ok(estimate)
+    })
+    .await
+    .map_err(|e| format!("Failed to estimate finish: {}", e))?
+}
+
+/// Milliseconds since `since`, for [`StageTimings`]
+pub fn elapsed_ms(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+/// The hash of the card as given and turned by each rotation worth trying.
+/// Rectified cards are portrait, so only a half turn is tried for them.
+fn rotation_hashes(card_image: &CardImage) -> Vec<(u32, u64)> {
+    let rotations: &[u32] = if card_image.rectified { &[0, 180] } else { &[0, 90, 180, 270] };
+    rotations
+        .iter()
+        .map(|&degrees| {
+            let hash = if degrees == 0 {
+                calculate_phash(&card_image.image)
+            } else {
+                calculate_phash(&orientation::rotate(&card_image.image, degrees))
+            };
+            (degrees, hash)
+        })
+        .collect()
+}
+
+/// The rotation whose hash is closest to a collection card or reference
+/// printing, if that is a match and closer than the card as given.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `turns` - The hash at each rotation, from [`rotation_hashes`], unturned first.
+///
+/// # Returns
+///
+/// * `Result<u32, String>` - Clockwise degrees to turn the card, or an error message.
+fn rotation_by_hash_agreement(state: &AppState, turns: &[(u32, u64)]) -> Result<u32, String> {
+    let closest_match = |hash: u64| -> Result<Option<u32>, String> {
+        let collection = state
+            .phash_index
+            .lock()
+            .map_err(|_| "Failed to lock hash index".to_string())?
+            .query(hash, hash_index::CANDIDATE_RADIUS, 1)
+            .first()
+            .map(|m| m.distance);
+        let reference = state
+            .reference_index
+            .lock()
+            .map_err(|_| "Failed to lock reference index".to_string())?
+            .candidates(hash, None, 1)
+            .first()
+            .map(|c| c.distance);
+        Ok(collection.into_iter().chain(reference).min())
+    };
+
+    let Some((&(_, hash), turns)) = turns.split_first() else {
+        return Ok(0);
+    };
+    let mut best_distance = closest_match(hash)?;
+    let mut best_rotation = 0;
+    for &(degrees, hash) in turns {
+        let distance = closest_match(hash)?;
+        if let Some(distance) = distance {
+            let closer = best_distance.is_none_or(|best| distance < best);
+            if distance <= hash_index::MATCH_DISTANCE && closer {
+                best_distance = Some(distance);
+                best_rotation = degrees;
+            }
+        }
+    }
+    Ok(best_rotation)
+}
+
+/// Turns a card clockwise by `degrees`
+fn turn_card(card_image: CardImage, degrees: u32) -> CardImage {
+    if degrees == 0 {
+        return card_image;
+    }
+    println!("Turning the card {} degrees to match a known card", degrees);
+    CardImage {
+        image: orientation::rotate(&card_image.image, degrees),
+        rectified: card_image.rectified,
+        rotation: (card_image.rotation + degrees) % 360,
+    }
+}
+
+/// Everything read off a card image before anything is looked up
+struct CardMeasurements {
+    /// Clockwise degrees the photo was turned to make the card upright
+    rotation: u32,
+    features: CardFeatures,
+    /// Shape of the expansion symbol, when set icons are loaded
+    symbol: Option<SymbolMask>,
+    condition: Option<ConditionEstimate>,
+    hashes: CardHashes,
+    /// Hash of the art box, only where we expect it on a rectified card
+    art_hash: Option<u64>,
+    /// The name read from the title bar and how sure the read is
+    name_read: Option<(String, f32)>,
+    info_line: Option<InfoLine>,
+    ocr_ms: f64,
+}
+
+/// Runs the feature detectors, grading, hashing and OCR on an upright card.
+/// Blocks for a while, so it runs on a blocking thread.
+///
+/// # Arguments
+///
+/// * `card_image` - The upright card.
+/// * `read_symbol` - Whether to cut out the expansion symbol for the set icons.
+/// * `ocr` - The OCR engine, None when the models are not installed.
+///
+/// # Returns
+///
+/// * `CardMeasurements` - What was measured.
+fn measure_card(card_image: CardImage, read_symbol: bool, ocr: Option<&CardOcr>) -> CardMeasurements {
+    let image = &card_image.image;
+    let features = extract_features_from_image(&card_image);
+    let symbol = read_symbol.then(|| extract_symbol_mask(image)).flatten();
+
+    // Grading needs the card's edges, which only a rectified image shows
+    let condition = card_image.rectified.then(|| estimate_condition(image));
+    let hashes = CardHashes::compute(image);
+    let art_hash = card_image.rectified.then(|| crate::card_features::calculate_art_phash(image));
+
+    // Read the card name from the title bar (local OCR, skipped if models are missing)
+    let ocr_started = Instant::now();
+    let name_read = ocr.and_then(|ocr| ocr.read_card_name(image));
+
+    // Cards from 2015 on print set code, collector number and language in the
+    // bottom-left corner. Reading them pins down the exact printing, so hash
+    // ranking isn't needed
+    let info_line = if card_image.rectified && has_info_line(&features.frame_style) {
+        ocr.and_then(|ocr| ocr.read_info_line(image))
+    } else {
+        None
+    };
+
+    CardMeasurements {
+        rotation: card_image.rotation,
+        features,
+        symbol,
+        condition,
+        hashes,
+        art_hash,
+        name_read,
+        info_line,
+        ocr_ms: elapsed_ms(ocr_started),
+    }
+}
+
+/// Hashes of each candidate printing, None where its image couldn't be had.
+///
+/// A printing already in the reference catalog is never downloaded again.
+/// Other thumbnails come from the image cache or are downloaded a few at a
+/// time, and their hashes are stored so the printing is recognized offline
+/// next time.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `cards` - The candidates to compare.
+///
+/// # Returns
+///
+/// * `Vec<Option<CardHashes>>` - One entry per candidate, in order.
+async fn candidate_hashes(state: &AppState, cards: &[ScryfallCard]) -> Vec<Option<CardHashes>> {
+    use std::sync::Arc;
+    use tokio::sync::Semaphore;
+    use tokio::task::JoinSet;
+
+    let mut hashes: Vec<Option<CardHashes>> = vec![None; cards.len()];
+    let mut downloads = Vec::new();
+    {
+        let db = match state.db.lock() {
+            Ok(db) => db,
+            Err(_) => return hashes,
+        };
+        for (index, card) in cards.iter().enumerate() {
+            let stored = database::operations::get_reference_hash(&db, &card.id)
+                .ok()
+                .flatten()
+                .and_then(|r| StoredHashes::parse(&r.phash, r.hashes.as_deref(), r.phash_algo.as_deref()))
+                .and_then(|stored| stored.full);
+            if stored.is_some() {
+                hashes[index] = stored;
+            } else if let Some(image_uris) = &card.image_uris {
+                let url = image_uris.small.clone();
+                let digest = database::operations::get_cached_image_digest(&db, &url).ok().flatten();
+                downloads.push((index, url, digest));
+            }
+        }
+    }
+    println!(
+        "{} candidates already hashed, {} to fetch",
+        hashes.iter().filter(|h| h.is_some()).count(),
+        downloads.len()
+    );
+
+    let client = &state.image_client;
+    let semaphore = Arc::new(Semaphore::new(DOWNLOAD_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    for (index, url, digest) in downloads {
+        let client = client.clone();
+        let cache = state.image_cache.clone();
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let fetched = fetch_candidate_image(&client, &cache, &semaphore, &url, digest).await;
+            (index, url, fetched)
+        });
+    }
+
+    let mut fetched_images = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, url, Ok(fetched))) => fetched_images.push((index, url, fetched)),
+            Ok((index, _, Err(e))) => println!("Failed to fetch image for {}: {}", cards[index].name, e),
+            Err(e) => println!("Image download task failed: {}", e),
+        }
+    }
+
+    if let Ok(db) = state.db.lock() {
+        for (_, url, fetched) in &fetched_images {
+            if let Some(digest) = &fetched.new_digest {
+                if let Err(e) = database::operations::insert_cached_image(&db, url, digest) {
+                    println!("Failed to record cached image {}: {}", url, e);
+                }
+            }
+        }
+    }
+
+    for (index, _, fetched) in fetched_images {
+        // Keep the hashes so this printing is recognized offline next time
+        let card = &cards[index];
+        let reference =
+            commands::reference::reference_from_hashes(card, &fetched.hashes, fetched.art_hash, "search");
+        if let Err(e) = commands::reference::store_reference(state, &reference) {
+            println!("Failed to store reference hash for {}: {}", card.name, e);
+        }
+        hashes[index] = Some(fetched.hashes);
+    }
+    hashes
+}
+
+/// A hashed candidate thumbnail
+struct FetchedImage {
+    hashes: CardHashes,
+    art_hash: u64,
+    /// Digest of a newly downloaded image, to be recorded against its URL
+    new_digest: Option<String>,
+}
+
+/// Builds the client candidate thumbnails are downloaded with, shared by
+/// every recognition so connections to the image server are reused
+pub fn image_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent("MTGCollectionManager/0.1.0")
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// Reads a thumbnail from the image cache, or downloads and caches it, then hashes it
+async fn fetch_candidate_image(
+    client: &reqwest::Client,
+    cache: &ImageCache,
+    semaphore: &tokio::sync::Semaphore,
+    url: &str,
+    digest: Option<String>,
+) -> Result<FetchedImage, String> {
+    let (bytes, new_digest) = match digest.and_then(|digest| cache.read(&digest)) {
+        Some(bytes) => (bytes, None),
+        None => {
+            let _permit = semaphore.acquire().await.map_err(|e| e.to_string())?;
+            let bytes = client
+                .get(url)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| e.to_string())?
+                .bytes()
+                .await
+                .map_err(|e| e.to_string())?
+                .to_vec();
+            let digest = cache.write(&bytes)?;
+            (bytes, Some(digest))
+        }
+    };
+
+    let (hashes, art_hash) = tokio::task::spawn_blocking(move || {
+        image::load_from_memory(&bytes)
+            .map(|img| (CardHashes::compute(&img), crate::card_features::calculate_art_phash(&img)))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to load image: {}", e))?;
+
+    Ok(FetchedImage { hashes, art_hash, new_digest })
+}
+
+/// Whether the frame prints the set code and collector number info line
+fn has_info_line(frame_style: &FrameStyle) -> bool {
+    matches!(
+        frame_style,
+        FrameStyle::M15Frame | FrameStyle::ShowcaseFrame | FrameStyle::BorderlessFrame
+    )
+}
+
+/// Looks up the printing read from the info line, first in the reference
+/// catalog (offline), then on Scryfall when `online`.
+/// A failed Scryfall request isn't an error: recognition falls back to the
+/// visual pipeline.
+async fn resolve_printing(
+    state: &AppState,
+    info: &InfoLine,
+    query: &str,
+    online: bool,
+) -> Result<Option<ScryfallCard>, String> {
+    let reference = {
+        let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+        database::operations::get_reference_by_printing(&db, &info.set_code, &info.collector_number)
+            .map_err(|e| e.to_string())?
+    };
+    if let Some(reference) = reference {
+        let printing = LocalPrinting {
+            scryfall_id: reference.scryfall_id,
+            name: reference.name,
+            set_code: reference.set_code,
+            collector_number: reference.collector_number,
+            image_uri: reference.image_uri,
+        };
+        return Ok(Some(local_scryfall_card(printing, None, None)));
+    }
+    if !online {
+        return Ok(None);
+    }
+
+    match state.card_data.search_cards(query, 1).await {
+        Ok(results) => Ok(results.data.into_iter().next()),
+        Err(e) => {
+            println!("Printing lookup failed for {}: {}", query, e);
+            Ok(None)
+        }
+    }
+}
+
+/// The printing behind a local match, as stored in the database
+struct LocalPrinting {
+    scryfall_id: String,
+    name: String,
+    set_code: String,
+    collector_number: String,
+    image_uri: Option<String>,
+}
+
+/// A collection card, feedback exemplar or reference printing found by the hash indexes
+struct LocalCandidate {
+    printing: LocalPrinting,
+    /// 64-bit dHash distance from the index
+    distance: u32,
+    /// Stored hashes, the full set only if it matches the current hash algorithm
+    hashes: StoredHashes,
+    /// Accepted regardless of score (the art box matched)
+    confirmed: bool,
+    /// Score needed to accept this printing, learned from feedback
+    threshold: f32,
+}
+
+/// Scores local candidates with the fused hash similarity and returns the
+/// accepted ones with their scores, best first and at most
+/// [`LOCAL_CANDIDATES`]. Candidates without an up-to-date hash set are scored
+/// on their dHash distance alone.
+fn rank_local_candidates(
+    candidates: Vec<LocalCandidate>,
+    user_hashes: &CardHashes,
+) -> Vec<(LocalCandidate, f32)> {
+    let user_hashes = StoredHashes::from(*user_hashes);
+    let mut accepted: Vec<(LocalCandidate, f32)> = candidates
+        .into_iter()
+        .map(|candidate| {
+            let score = user_hashes.similarity(&candidate.hashes);
+            (candidate, score)
+        })
+        .filter(|(candidate, score)| candidate.confirmed || *score >= candidate.threshold)
+        .collect();
+    accepted.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(a.distance.cmp(&b.distance)));
+    accepted.truncate(LOCAL_CANDIDATES);
+    accepted
+}
+
+/// Turns ranked local candidates into the cards returned for them
+fn local_scryfall_cards(source: &str, ranked: Vec<(LocalCandidate, f32)>) -> Vec<ScryfallCard> {
+    if let Some((best, score)) = ranked.first() {
+        println!("Found {} match: {} (dist={}, score={:.3})", source, best.printing.name, best.distance, score);
+    }
+    ranked
+        .into_iter()
+        .map(|(candidate, score)| local_scryfall_card(candidate.printing, Some(candidate.distance), Some(score)))
+        .collect()
+}
+
+/// Acceptance score of a printing, tuned from the photos confirmed or rejected as it
+fn printing_threshold(
+    conn: &rusqlite::Connection,
+    scryfall_id: &str,
+    printing_hashes: &StoredHashes,
+) -> Result<f32, String> {
+    let mut confirmed = Vec::new();
+    let mut rejected = Vec::new();
+    for feedback in database::operations::get_printing_feedback(conn, scryfall_id).map_err(|e| e.to_string())? {
+        if let Some(hashes) = feedback.stored_hashes() {
+            let score = hashes.similarity(printing_hashes);
+            if feedback.confirmed {
+                confirmed.push(score);
+            } else {
+                rejected.push(score);
+            }
+        }
+    }
+    Ok(card_hash::learned_threshold(&confirmed, &rejected))
+}
+
+/// Printings the user rejected for a photo that is near-identical to this one
+fn rejected_printings(
+    state: &AppState,
+    user_hashes: &CardHashes,
+) -> Result<std::collections::HashSet<String>, String> {
+    let matches = state
+        .feedback_index
+        .lock()
+        .map_err(|_| "Failed to lock feedback index".to_string())?
+        .query(user_hashes.dhash, false, 10);
+    if matches.is_empty() {
+        return Ok(std::collections::HashSet::new());
+    }
+
+    let user_hashes = StoredHashes::from(*user_hashes);
+    let db = state.db.lock().map_err(|_| "Failed to lock db".to_string())?;
+    let mut rejected = std::collections::HashSet::new();
+    for m in matches {
+        let feedback = database::operations::get_recognition_feedback(&db, m.id).map_err(|e| e.to_string())?;
+        let score = feedback
+            .as_ref()
+            .and_then(|f| f.stored_hashes())
+            .map(|hashes| user_hashes.similarity(&hashes));
+        if score.is_some_and(|score| score >= card_hash::MATCH_SCORE) {
+            rejected.insert(m.scryfall_id);
+        }
+    }
+    Ok(rejected)
+}
+
+/// Builds the card returned for an offline match from locally stored data
+fn local_scryfall_card(printing: LocalPrinting, distance: Option<u32>, score: Option<f32>) -> ScryfallCard {
+    let img = printing.image_uri.unwrap_or_default();
+    ScryfallCard {
+        id: printing.scryfall_id,
+        oracle_id: None,
+        name: printing.name,
+        lang: Some("en".to_string()),
+        set: printing.set_code,
+        set_name: String::new(), // We don't store set name in cards table, could fetch from sets table
+        collector_number: printing.collector_number,
+        released_at: String::new(),
+        artist: None,
+        image_uris: Some(ImageUris {
+            small: img.clone(),
+            normal: img.clone(),
+            large: img,
+            png: String::new(),
+            art_crop: String::new(),
+            border_crop: String::new(),
+        }),
+        prices: Prices {
+            usd: None, usd_foil: None, eur: None, eur_foil: None
+        },
+        rarity: String::new(),
+        similarity: distance,
+        match_confidence: score.map(card_hash::match_confidence),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decode_photo_scales_to_working_resolution() {
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::new(3000, 1500));
+        let mut bytes = Vec::new();
+        photo.write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+
+        let (decoded, rotation) = decode_photo(&bytes).unwrap();
+        assert_eq!(decoded.dimensions(), (WORKING_SIDE, WORKING_SIDE / 2));
+        assert_eq!(rotation, 0);
+
+        assert!(decode_photo(&vec![0u8; MAX_PHOTO_BYTES + 1]).unwrap_err().contains("too large"));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::models::scryfall::{ScryfallCard, ScryfallCardList, ScryfallSet};
+use async_trait::async_trait;
+use std::error::Error;
+
+/// Error returned by a card data provider
+pub type ProviderError = Box<dyn Error + Send + Sync>;
+
+/// Source of sets, cards and printings, queried with Scryfall syntax.
+///
+/// Implemented by `ScryfallService` for the Scryfall API (or a mirror of it)
+/// and by `FixtureService` for local fixture files. The app holds one in
+/// `AppState.card_data`.
+#[async_trait]
+pub trait CardDataProvider: Send + Sync {
+    /// Fetches all Magic: The Gathering sets.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ScryfallSet>, ProviderError>` - A list of sets or an error.
+    async fn fetch_sets(&self) -> Result<Vec<ScryfallSet>, ProviderError>;
+
+    /// Fetches a single card by its Scryfall ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The Scryfall ID of the card.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ScryfallCard, ProviderError>` - The card data or an error.
+    async fn fetch_card(&self, id: &str) -> Result<ScryfallCard, ProviderError>;
+
+    /// Fetches many cards by their Scryfall IDs, in batches as large as the
+    /// source allows. Meant for refreshing prices, so it skips any local catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The Scryfall IDs of the cards.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ScryfallCard>, ProviderError>` - The cards that were found, in no particular order.
+    async fn fetch_cards(&self, ids: &[String]) -> Result<Vec<ScryfallCard>, ProviderError>;
+
+    /// Searches for cards using a Scryfall syntax query.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query (e.g., "t:creature").
+    /// * `page` - The page number (1-indexed).
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ScryfallCardList, ProviderError>` - A paginated list of cards, empty if none match.
+    async fn search_cards(&self, query: &str, page: u32) -> Result<ScryfallCardList, ProviderError>;
+
+    /// Fetches all cards in a specific set, in collector number order.
+    ///
+    /// # Arguments
+    ///
+    /// * `set_code` - The 3-letter code of the set (e.g., "dom").
+    /// * `page` - The page number (1-indexed).
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ScryfallCardList, ProviderError>` - A paginated list of cards or an error.
+    async fn fetch_cards_by_set(&self, set_code: &str, page: u32) -> Result<ScryfallCardList, ProviderError>;
+
+    /// Fetches available languages for a specific card in a set.
+    ///
+    /// # Arguments
+    ///
+    /// * `oracle_id` - The Oracle ID of the card.
+    /// * `set_code` - The set code.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>, ProviderError>` - Sorted language codes or an error.
+    async fn get_card_languages(&self, oracle_id: &str, set_code: &str) -> Result<Vec<String>, ProviderError>;
+
+    /// Fetches top cards based on a query and sort order.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `order` - The sort order (e.g., "usd", "edhrec").
+    /// * `dir` - The sort direction ("asc" or "desc").
+    /// * `limit` - The number of cards to return.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ScryfallCard>, ProviderError>` - At most `limit` cards or an error.
+    async fn get_top_cards(
+        &self,
+        query: &str,
+        order: &str,
+        dir: &str,
+        limit: usize,
+    ) -> Result<Vec<ScryfallCard>, ProviderError>;
+}
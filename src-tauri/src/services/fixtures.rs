@@ -0,0 +1,312 @@
+use crate::models::scryfall::{ScryfallCard, ScryfallCardList, ScryfallSet};
+use crate::services::card_data::{CardDataProvider, ProviderError};
+use async_trait::async_trait;
+use std::cmp::Ordering;
+use std::path::Path;
+
+/// Cards in a fixture directory, same layout as a reference archive manifest
+pub const FIXTURE_CARDS_FILE: &str = "cards.json";
+
+/// Optional sets in a fixture directory
+pub const FIXTURE_SETS_FILE: &str = "sets.json";
+
+/// Cards per search page, as on Scryfall
+pub const PAGE_SIZE: usize = 175;
+
+/// Answers card data queries from a fixed list of sets and cards, for
+/// offline development and deterministic tests.
+///
+/// Queries support bare and quoted name words, `!"exact name"`, `set:`/`s:`/`e:`,
+/// `cn:`, `oracle_id:` and `lang:`. Other terms (formats, types, dates) are
+/// about fields the fixture cards don't have and match every card.
+pub struct FixtureService {
+    sets: Vec<ScryfallSet>,
+    cards: Vec<ScryfallCard>,
+}
+
+/// One term of a search query
+#[derive(Debug, PartialEq)]
+enum QueryTerm {
+    /// Name contains the word
+    Word(String),
+    /// Name is exactly this
+    ExactName(String),
+    Set(String),
+    CollectorNumber(String),
+    OracleId(String),
+    Lang(String),
+    /// A term the fixtures can't evaluate
+    Other,
+}
+
+impl FixtureService {
+    pub fn new(sets: Vec<ScryfallSet>, cards: Vec<ScryfallCard>) -> Self {
+        FixtureService { sets, cards }
+    }
+
+    /// Loads fixtures from a directory holding `cards.json` (a list of
+    /// Scryfall cards) and optionally `sets.json` (a list of Scryfall sets).
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The fixture directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FixtureService, String>` - The service, or an error message if a file can't be read.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let cards = read_json(&dir.join(FIXTURE_CARDS_FILE))?;
+        let sets_path = dir.join(FIXTURE_SETS_FILE);
+        let sets = if sets_path.is_file() {
+            read_json(&sets_path)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self::new(sets, cards))
+    }
+
+    /// Finds the cards matching a Scryfall syntax query, in fixture order.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `include_multilingual` - Whether to include non-English printings without a `lang:` term.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ScryfallCard>` - The matching cards.
+    pub fn matching_cards(&self, query: &str, include_multilingual: bool) -> Vec<ScryfallCard> {
+        let terms = parse_query(query);
+        let any_lang = include_multilingual || terms.iter().any(|term| matches!(term, QueryTerm::Lang(_)));
+        self.cards
+            .iter()
+            .filter(|card| any_lang || card.lang.as_deref().is_none_or(|lang| lang == "en"))
+            .filter(|card| terms.iter().all(|term| term_matches(term, card)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Sorts cards the way a Scryfall `order`/`dir` pair would, with unpriced
+/// cards last when sorting by price. Orders the fixtures have no data for
+/// (e.g. "edhrec") keep the cards as they are.
+///
+/// # Arguments
+///
+/// * `cards` - The cards to sort.
+/// * `order` - "usd", "eur", "released", "name" or "set".
+/// * `dir` - "asc" or "desc".
+pub fn sort_cards(cards: &mut [ScryfallCard], order: &str, dir: &str) {
+    let compare: fn(&ScryfallCard, &ScryfallCard) -> Ordering = match order {
+        "usd" => |a, b| price(&a.prices.usd).partial_cmp(&price(&b.prices.usd)).unwrap_or(Ordering::Equal),
+        "eur" => |a, b| price(&a.prices.eur).partial_cmp(&price(&b.prices.eur)).unwrap_or(Ordering::Equal),
+        "released" => |a, b| a.released_at.cmp(&b.released_at),
+        "name" => |a, b| a.name.cmp(&b.name),
+        "set" => |a, b| (&a.set, collector_sort_key(a)).cmp(&(&b.set, collector_sort_key(b))),
+        _ => return,
+    };
+    if dir == "desc" {
+        cards.sort_by(|a, b| compare(b, a));
+    } else {
+        cards.sort_by(compare);
+    }
+    match order {
+        "usd" => cards.sort_by_key(|card| price(&card.prices.usd).is_none()),
+        "eur" => cards.sort_by_key(|card| price(&card.prices.eur).is_none()),
+        _ => {}
+    }
+}
+
+/// Cuts a page out of a list of cards.
+///
+/// # Arguments
+///
+/// * `cards` - All matching cards.
+/// * `page` - The page number (1-indexed).
+///
+/// # Returns
+///
+/// * `ScryfallCardList` - The cards of the page.
+pub fn card_page(cards: Vec<ScryfallCard>, page: u32) -> ScryfallCardList {
+    let total = cards.len();
+    let start = (page.max(1) as usize - 1) * PAGE_SIZE;
+    let data: Vec<ScryfallCard> = cards.into_iter().skip(start).take(PAGE_SIZE).collect();
+    ScryfallCardList {
+        has_more: start + data.len() < total,
+        total_cards: Some(total as i32),
+        data,
+    }
+}
+
+#[async_trait]
+impl CardDataProvider for FixtureService {
+    async fn fetch_sets(&self) -> Result<Vec<ScryfallSet>, ProviderError> {
+        Ok(self.sets.clone())
+    }
+
+    async fn fetch_card(&self, id: &str) -> Result<ScryfallCard, ProviderError> {
+        self.cards
+            .iter()
+            .find(|card| card.id == id)
+            .cloned()
+            .ok_or_else(|| format!("No card with ID {} in the fixtures", id).into())
+    }
+
+    async fn fetch_cards(&self, ids: &[String]) -> Result<Vec<ScryfallCard>, ProviderError> {
+        Ok(self.cards.iter().filter(|card| ids.contains(&card.id)).cloned().collect())
+    }
+
+    async fn search_cards(&self, query: &str, page: u32) -> Result<ScryfallCardList, ProviderError> {
+        Ok(card_page(self.matching_cards(query, false), page))
+    }
+
+    async fn fetch_cards_by_set(&self, set_code: &str, page: u32) -> Result<ScryfallCardList, ProviderError> {
+        let mut cards = self.matching_cards(&format!("e:{}", set_code), false);
+        sort_cards(&mut cards, "set", "asc");
+        Ok(card_page(cards, page))
+    }
+
+    async fn get_card_languages(&self, oracle_id: &str, set_code: &str) -> Result<Vec<String>, ProviderError> {
+        let query = format!("oracle_id:{} set:{}", oracle_id, set_code);
+        let mut languages: Vec<String> = self
+            .matching_cards(&query, true)
+            .into_iter()
+            .filter_map(|card| card.lang)
+            .collect();
+        languages.sort();
+        languages.dedup();
+        Ok(languages)
+    }
+
+    async fn get_top_cards(
+        &self,
+        query: &str,
+        order: &str,
+        dir: &str,
+        limit: usize,
+    ) -> Result<Vec<ScryfallCard>, ProviderError> {
+        let mut cards = self.matching_cards(query, false);
+        sort_cards(&mut cards, order, dir);
+        cards.truncate(limit);
+        Ok(cards)
+    }
+}
+
+/// The fixtures in `src-tauri/fixtures/scryfall`
+#[cfg(test)]
+pub fn bundled_fixtures() -> FixtureService {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scryfall");
+    FixtureService::load(&dir).expect("failed to load bundled fixtures")
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+}
+
+/// Splits a query into terms, keeping quoted text together
+fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens.into_iter().map(|token| parse_term(&token)).collect()
+}
+
+fn parse_term(token: &str) -> QueryTerm {
+    if let Some(name) = token.strip_prefix('!') {
+        return QueryTerm::ExactName(name.to_lowercase());
+    }
+    let Some(split) = token.find([':', '=', '<', '>']) else {
+        return QueryTerm::Word(token.to_lowercase());
+    };
+    let key = token[..split].to_lowercase();
+    let value = token[split..].trim_start_matches([':', '=', '<', '>']).to_lowercase();
+    match key.as_str() {
+        "set" | "s" | "e" | "edition" => QueryTerm::Set(value),
+        "cn" | "number" => QueryTerm::CollectorNumber(value),
+        "oracle_id" | "oracleid" => QueryTerm::OracleId(value),
+        "lang" | "l" | "language" => QueryTerm::Lang(value),
+        _ => QueryTerm::Other,
+    }
+}
+
+fn term_matches(term: &QueryTerm, card: &ScryfallCard) -> bool {
+    match term {
+        QueryTerm::Word(word) => card.name.to_lowercase().contains(word.as_str()),
+        QueryTerm::ExactName(name) => card.name.to_lowercase() == *name,
+        QueryTerm::Set(set) => card.set.eq_ignore_ascii_case(set),
+        QueryTerm::CollectorNumber(number) => card.collector_number.eq_ignore_ascii_case(number),
+        QueryTerm::OracleId(id) => card.oracle_id.as_deref().is_some_and(|o| o.eq_ignore_ascii_case(id)),
+        QueryTerm::Lang(lang) => lang == "any" || card.lang.as_deref().unwrap_or("en") == lang,
+        QueryTerm::Other => true,
+    }
+}
+
+fn price(value: &Option<String>) -> Option<f64> {
+    value.as_deref().and_then(|p| p.parse().ok())
+}
+
+/// Collector numbers sort by their numeric part first ("9" before "10a")
+fn collector_sort_key(card: &ScryfallCard) -> (u32, &str) {
+    let digits: String = card.collector_number.chars().take_while(|c| c.is_ascii_digit()).collect();
+    (digits.parse().unwrap_or(u32::MAX), card.collector_number.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query(r#"Lightning Bolt set:M10 f:standard !"Black Lotus""#),
+            vec![
+                QueryTerm::Word("lightning".to_string()),
+                QueryTerm::Word("bolt".to_string()),
+                QueryTerm::Set("m10".to_string()),
+                QueryTerm::Other,
+                QueryTerm::ExactName("black lotus".to_string()),
+            ]
+        );
+        assert_eq!(parse_query("date>=now-30days"), vec![QueryTerm::Other]);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_queries() {
+        let fixtures = bundled_fixtures();
+
+        // Non-English printings only show up when asked for
+        let bolts = fixtures.search_cards("lightning bolt", 1).await.unwrap();
+        assert_eq!(bolts.data.len(), 1);
+        assert_eq!(fixtures.matching_cards("lightning bolt", true).len(), 4);
+        assert_eq!(fixtures.search_cards("!\"Lightning Bolt\" lang:ja", 1).await.unwrap().data.len(), 1);
+
+        let set = fixtures.fetch_cards_by_set("dmu", 1).await.unwrap();
+        let numbers: Vec<&str> = set.data.iter().map(|card| card.collector_number.as_str()).collect();
+        assert_eq!(numbers, vec!["107", "168"]);
+
+        let top = fixtures.get_top_cards("game:paper", "usd", "desc", 2).await.unwrap();
+        let names: Vec<&str> = top.iter().map(|card| card.name.as_str()).collect();
+        assert_eq!(names, vec!["Sheoldred, the Apocalypse", "Ragavan, Nimble Pilferer"]);
+
+        let card = fixtures.fetch_card(&bolts.data[0].id).await.unwrap();
+        assert_eq!(card.set, "m10");
+        assert!(fixtures.fetch_card("missing").await.is_err());
+        assert_eq!(fixtures.fetch_sets().await.unwrap().len(), 5);
+    }
+}
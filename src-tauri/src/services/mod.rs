@@ -1,3 +1,6 @@
+pub mod card_data;
+pub mod fixtures;
 pub mod import;
 pub mod prices;
+pub mod rate_limit;
 pub mod scryfall;
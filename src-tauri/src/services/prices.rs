@@ -1,35 +1,159 @@
-use crate::services::scryfall::ScryfallService;
+use crate::services::card_data::CardDataProvider;
+use crate::services::scryfall::COLLECTION_BATCH_SIZE;
 use crate::models::collection::CollectionCard;
-use std::error::Error;
+use crate::models::scryfall::Prices;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 pub struct PriceService {
-    scryfall: ScryfallService,
+    card_data: Arc<dyn CardDataProvider>,
+}
+
+/// Outcome of refreshing the prices of a set of collection cards
+#[derive(Debug, Default)]
+pub struct PriceRefresh {
+    /// New price of each card that has one, by collection card ID
+    pub prices: Vec<(String, f64)>,
+    /// Cards whose printing has no price in the chosen currency and finish
+    pub unpriced: usize,
+    /// Cards whose printing couldn't be looked up
+    pub failed: usize,
 }
 
 impl PriceService {
-    pub fn new() -> Self {
-        PriceService {
-            scryfall: ScryfallService::new(),
-        }
+    pub fn new(card_data: Arc<dyn CardDataProvider>) -> Self {
+        PriceService { card_data }
     }
 
-    pub async fn fetch_and_update_price(&self, card: &CollectionCard, currency_pref: &str) -> Result<Option<f64>, Box<dyn Error>> {
-        match self.scryfall.fetch_card(&card.scryfall_id).await {
-            Ok(scryfall_card) => {
-                let price_str = if currency_pref == "EUR" {
-                    if card.is_foil { scryfall_card.prices.eur_foil } else { scryfall_card.prices.eur }
-                } else {
-                    if card.is_foil { scryfall_card.prices.usd_foil } else { scryfall_card.prices.usd }
-                };
-
-                if let Some(p) = price_str {
-                    if let Ok(price) = p.parse::<f64>() {
-                        return Ok(Some(price));
+    /// Looks up the current prices of collection cards. Each printing is
+    /// looked up once however many cards share it, 75 printings per request.
+    /// A batch that fails only fails its own cards.
+    ///
+    /// # Arguments
+    ///
+    /// * `cards` - The collection cards to price.
+    /// * `currency_pref` - "EUR" for euro prices, anything else for US dollars.
+    ///
+    /// # Returns
+    ///
+    /// * `PriceRefresh` - The new prices and the cards left without one.
+    pub async fn refresh_prices(&self, cards: &[CollectionCard], currency_pref: &str) -> PriceRefresh {
+        let mut rows_by_printing: HashMap<&str, Vec<&CollectionCard>> = HashMap::new();
+        let mut printings: Vec<String> = Vec::new();
+        for card in cards {
+            rows_by_printing
+                .entry(&card.scryfall_id)
+                .or_insert_with(|| {
+                    printings.push(card.scryfall_id.clone());
+                    Vec::new()
+                })
+                .push(card);
+        }
+        println!("Refreshing prices of {} cards ({} printings)", cards.len(), printings.len());
+
+        let mut refresh = PriceRefresh::default();
+        for batch in printings.chunks(COLLECTION_BATCH_SIZE) {
+            let rows = batch.iter().flat_map(|id| &rows_by_printing[id.as_str()]);
+            let found = match self.card_data.fetch_cards(batch).await {
+                Ok(found) => found,
+                Err(e) => {
+                    println!("Failed to fetch a batch of {} printings: {}", batch.len(), e);
+                    refresh.failed += rows.count();
+                    continue;
+                }
+            };
+
+            let prices: HashMap<String, Prices> = found.into_iter().map(|card| (card.id, card.prices)).collect();
+            let mut missing: HashSet<&str> = HashSet::new();
+            for row in rows {
+                match prices.get(&row.scryfall_id) {
+                    Some(p) => match card_price(p, row.is_foil, currency_pref) {
+                        Some(price) => refresh.prices.push((row.id.clone(), price)),
+                        None => refresh.unpriced += 1,
+                    },
+                    None => {
+                        missing.insert(&row.scryfall_id);
+                        refresh.failed += 1;
                     }
                 }
-                Ok(None)
-            },
-            Err(e) => Err(e)
+            }
+            if !missing.is_empty() {
+                println!("No card found for printings: {:?}", missing);
+            }
+        }
+        refresh
+    }
+}
+
+/// Picks the price of a card from its printing's prices.
+///
+/// # Arguments
+///
+/// * `prices` - The prices of the printing.
+/// * `is_foil` - Whether the card is foil.
+/// * `currency_pref` - "EUR" for euro prices, anything else for US dollars.
+///
+/// # Returns
+///
+/// * `Option<f64>` - The price, or None if the printing has none for that currency and finish.
+pub fn card_price(prices: &Prices, is_foil: bool, currency_pref: &str) -> Option<f64> {
+    let price_str = match (currency_pref == "EUR", is_foil) {
+        (true, true) => &prices.eur_foil,
+        (true, false) => &prices.eur,
+        (false, true) => &prices.usd_foil,
+        (false, false) => &prices.usd,
+    };
+    price_str.as_deref().and_then(|p| p.parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::bundled_fixtures;
+
+    fn collection_card(id: &str, scryfall_id: &str, is_foil: bool) -> CollectionCard {
+        CollectionCard {
+            id: id.to_string(),
+            scryfall_id: scryfall_id.to_string(),
+            name: String::new(),
+            set_code: String::new(),
+            collector_number: String::new(),
+            condition: "NM".to_string(),
+            purchase_price: 0.0,
+            current_price: 0.0,
+            quantity: 1,
+            is_foil,
+            image_uri: None,
+            language: "en".to_string(),
+            finish: if is_foil { "foil" } else { "nonfoil" }.to_string(),
+            tags: None,
+            phash: None,
         }
     }
+
+    #[tokio::test]
+    async fn test_refresh_prices_shares_printings() {
+        let fixtures = bundled_fixtures();
+        let bolt = fixtures.search_cards("!\"Lightning Bolt\"", 1).await.unwrap().data.remove(0);
+        let lotus = fixtures.search_cards("!\"Black Lotus\"", 1).await.unwrap().data.remove(0);
+        let cards = vec![
+            collection_card("a", &bolt.id, false),
+            collection_card("b", &bolt.id, true),
+            collection_card("c", &bolt.id, false),
+            collection_card("d", &lotus.id, false),
+            collection_card("e", "unknown-printing", false),
+        ];
+
+        let service = PriceService::new(Arc::new(fixtures));
+        let refresh = service.refresh_prices(&cards, "USD").await;
+        assert_eq!(
+            refresh.prices,
+            vec![("a".to_string(), 1.85), ("b".to_string(), 12.5), ("c".to_string(), 1.85)]
+        );
+        assert_eq!((refresh.unpriced, refresh.failed), (1, 1));
+
+        let refresh = service.refresh_prices(&cards[..2], "EUR").await;
+        assert_eq!(refresh.prices, vec![("a".to_string(), 1.2)]);
+        assert_eq!(refresh.unpriced, 1);
+    }
 }
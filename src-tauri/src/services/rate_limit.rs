@@ -0,0 +1,199 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Average requests per second Scryfall asks clients to stay under
+/// (50-100 milliseconds between requests)
+pub const SCRYFALL_REQUESTS_PER_SECOND: f64 = 10.0;
+
+/// Requests that may go out back to back after a quiet spell
+pub const SCRYFALL_BURST: f64 = 2.0;
+
+/// Attempts a request gets before a 429, 5xx or connection failure is given up on
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Backoff before the first retry, doubled for each one after it
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait before a retry, whether from backoff or `Retry-After`
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Async token bucket shared by every request to an API. Callers that find
+/// the bucket empty reserve the next token and sleep until it is theirs, so
+/// they go out in turn at the bucket's rate.
+pub struct RateLimiter {
+    /// Tokens added per second
+    rate: f64,
+    /// Most tokens the bucket holds
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Negative while callers are waiting for tokens they reserved
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter with a full bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - Requests per second on average.
+    /// * `capacity` - Requests allowed back to back.
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        RateLimiter {
+            rate,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Creates a limiter at Scryfall's documented rate.
+    pub fn scryfall() -> Self {
+        Self::new(SCRYFALL_REQUESTS_PER_SECOND, SCRYFALL_BURST)
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.refill();
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-bucket.tokens / self.rate)
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds every request back for at least `delay`, after the API said
+    /// too many were sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - How long to send nothing.
+    pub fn hold_off(&self, delay: Duration) {
+        let mut bucket = self.refill();
+        bucket.tokens = bucket.tokens.min(-delay.as_secs_f64() * self.rate);
+    }
+
+    /// Locks the bucket and adds the tokens earned since it was last touched
+    fn refill(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let earned = now.duration_since(bucket.updated).as_secs_f64() * self.rate;
+        bucket.tokens = (bucket.tokens + earned).min(self.capacity);
+        bucket.updated = now;
+        bucket
+    }
+}
+
+/// Whether a response means the same request may succeed later
+pub fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Reads a `Retry-After` header, given either in seconds or as an HTTP date.
+///
+/// # Arguments
+///
+/// * `headers` - The response headers.
+///
+/// # Returns
+///
+/// * `Option<Duration>` - How long to wait, capped at 30 seconds, or None without a readable header.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(delay.min(MAX_BACKOFF))
+}
+
+/// Exponential backoff with jitter before retrying a request. Half of the
+/// delay is fixed and half random, so clients that failed together don't
+/// retry together.
+///
+/// # Arguments
+///
+/// * `attempt` - The attempt that just failed (1 for the first).
+///
+/// # Returns
+///
+/// * `Duration` - How long to wait before the next attempt.
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let ceiling = BASE_BACKOFF
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF);
+    let half = ceiling / 2;
+    half + Duration::from_millis(fastrand::u64(0..=half.as_millis() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(50.0, 2.0);
+        let start = std::time::Instant::now();
+        // Two go out at once, the other four wait 20ms each
+        for _ in 0..6 {
+            limiter.acquire().await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(75), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+
+        limiter.hold_off(Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_with_jitter() {
+        for attempt in 1..=4 {
+            let ceiling = BASE_BACKOFF * (1 << (attempt - 1));
+            let delay = backoff_delay(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+        }
+        assert!(backoff_delay(40) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3600"));
+        assert_eq!(retry_after(&headers), Some(MAX_BACKOFF));
+
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+    }
+}
@@ -1,15 +1,68 @@
-use crate::models::scryfall::{ScryfallSet, ScryfallSetList};
-use reqwest::Client;
-use std::error::Error;
+use crate::catalog::{CardLookup, Catalog};
+use crate::http_cache::ResponseCache;
+use crate::models::scryfall::{ScryfallCard, ScryfallCardList, ScryfallCollection, ScryfallSet, ScryfallSetList};
+use crate::services::card_data::{CardDataProvider, ProviderError};
+use crate::services::rate_limit::{backoff_delay, is_retryable, retry_after, RateLimiter, MAX_ATTEMPTS};
+use async_trait::async_trait;
+use reqwest::header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, Request, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// Base URL of the public Scryfall API
+pub const SCRYFALL_API_URL: &str = "https://api.scryfall.com";
+
+/// Most identifiers `/cards/collection` takes per request
+pub const COLLECTION_BATCH_SIZE: usize = 75;
+
+/// How long cached set lists stay fresh. New sets are announced weeks ahead.
+const SETS_TTL: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// How long cached card responses stay fresh. They carry prices, which
+/// Scryfall updates daily.
+const CARDS_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How long a cached response is kept after it was last fetched or
+/// revalidated, for serving when offline
+pub const MAX_STALE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 
 pub struct ScryfallService {
     client: Client,
     base_url: String,
+    /// Local bulk data, asked before the API when set
+    catalog: Option<Catalog>,
+    /// Keeps every request of this service within Scryfall's rate limits
+    limiter: RateLimiter,
+    /// Earlier responses, answered from while fresh and revalidated after
+    cache: Option<ResponseCache>,
+}
+
+/// Status and body of an API response, from the network or the response cache
+struct ApiResponse {
+    status: StatusCode,
+    url: String,
+    body: Vec<u8>,
+}
+
+impl ApiResponse {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, ProviderError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
 }
 
 impl ScryfallService {
-    /// Creates a new instance of ScryfallService.
+    /// Creates a new instance of ScryfallService for the public Scryfall API.
     pub fn new() -> Self {
+        Self::with_base_url(SCRYFALL_API_URL)
+    }
+
+    /// Creates a ScryfallService for a Scryfall-compatible API, such as an
+    /// internal mirror.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The API root, without a trailing slash (e.g., "https://api.scryfall.com").
+    pub fn with_base_url(base_url: &str) -> Self {
         let client = Client::builder()
             .user_agent("MTGCollectionManager/0.1.0")
             .build()
@@ -17,19 +70,182 @@ impl ScryfallService {
 
         ScryfallService {
             client,
-            base_url: "https://api.scryfall.com".to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            catalog: None,
+            limiter: RateLimiter::scryfall(),
+            cache: None,
+        }
+    }
+
+    /// Answers card, printing and exact-name lookups from the local catalog,
+    /// and only asks the API for the rest.
+    pub fn with_catalog(self, catalog: Catalog) -> Self {
+        ScryfallService {
+            catalog: Some(catalog),
+            ..self
+        }
+    }
+
+    /// Caches responses on disk. Sets stay fresh for days and cards for
+    /// hours, after which they are revalidated with `If-None-Match` and
+    /// `If-Modified-Since`.
+    pub fn with_cache(self, cache: ResponseCache) -> Self {
+        ScryfallService {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// How long a response of an endpoint stays fresh in the cache, None for
+    /// endpoints that aren't cached
+    fn cache_ttl(&self, url: &str) -> Option<Duration> {
+        let endpoint = url.strip_prefix(&self.base_url)?;
+        let path = endpoint.split('?').next().unwrap_or(endpoint);
+        if path == "/sets" || path.starts_with("/sets/") {
+            Some(SETS_TTL)
+        } else if path.starts_with("/cards/") {
+            Some(CARDS_TTL)
+        } else {
+            None
         }
     }
 
+    /// Sends a request, answering it from the response cache while the cached
+    /// copy is fresh. A stale copy is revalidated, and served as is if the
+    /// request fails, the cache allows stale entries on error and the copy
+    /// is younger than [`MAX_STALE`]. Only 200
+    /// responses are cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to send.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ApiResponse, ProviderError>` - The response, or an error once the attempts run out.
+    async fn send(&self, request: RequestBuilder) -> Result<ApiResponse, ProviderError> {
+        let mut request = request.build()?;
+        let url = request.url().to_string();
+        let Some((cache, ttl)) = self.cache.as_ref().zip(self.cache_ttl(&url)) else {
+            let resp = self.send_with_retries(request).await?;
+            let status = resp.status();
+            let body = resp.bytes().await?.to_vec();
+            return Ok(ApiResponse { status, url, body });
+        };
+
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+        let key = ResponseCache::key(request.method().as_str(), &url, body);
+        let cached = cache.get(&key).unwrap_or_else(|e| {
+            println!("Response cache lookup failed: {}", e);
+            None
+        });
+        if let Some(entry) = &cached {
+            if entry.age() < ttl {
+                return Ok(ApiResponse { status: StatusCode::OK, url, body: entry.body.clone() });
+            }
+            let headers = request.headers_mut();
+            if let Some(etag) = entry.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = entry.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+
+        let fetched = async {
+            let resp = self.send_with_retries(request).await?;
+            let header = |name| resp.headers().get(name).and_then(|v: &HeaderValue| v.to_str().ok()).map(str::to_string);
+            let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+            let status = resp.status();
+            let body = resp.bytes().await?.to_vec();
+            Ok::<_, ProviderError>((status, etag, last_modified, body))
+        }
+        .await;
+
+        match (fetched, cached) {
+            (Ok((StatusCode::NOT_MODIFIED, ..)), Some(entry)) => {
+                if let Err(e) = cache.touch(&key) {
+                    println!("Failed to refresh cached response: {}", e);
+                }
+                Ok(ApiResponse { status: StatusCode::OK, url, body: entry.body })
+            }
+            (Ok((status, etag, last_modified, body)), _) => {
+                if status == StatusCode::OK {
+                    if let Err(e) = cache.put(&key, &url, &body, etag.as_deref(), last_modified.as_deref()) {
+                        println!("Failed to cache response: {}", e);
+                    }
+                }
+                Ok(ApiResponse { status, url, body })
+            }
+            (Err(e), Some(entry)) if cache.stale_if_error() && entry.age() < MAX_STALE => {
+                println!("{}, serving the cached response from {:?} ago for {}", e, entry.age(), url);
+                Ok(ApiResponse { status: StatusCode::OK, url, body: entry.body })
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Sends a request once the rate limiter allows it. Responses with 429
+    /// or 5xx and failed connections are retried up to [`MAX_ATTEMPTS`]
+    /// times, after the server's `Retry-After` or an exponential backoff.
+    /// A 429 also holds back every other request of this service.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to send.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Response, ProviderError>` - Any other response, or an error once the attempts run out.
+    async fn send_with_retries(&self, request: Request) -> Result<Response, ProviderError> {
+        let mut attempt = 0;
+        loop {
+            let this_try = request.try_clone().ok_or("Request can't be retried")?;
+            self.limiter.acquire().await;
+            attempt += 1;
+
+            let delay = match self.client.execute(this_try).await {
+                Ok(resp) if !is_retryable(resp.status()) => return Ok(resp),
+                Ok(resp) if attempt >= MAX_ATTEMPTS => {
+                    return Err(format!("Scryfall returned {} after {} attempts", resp.status(), attempt).into());
+                }
+                Ok(resp) => {
+                    let delay = retry_after(resp.headers()).unwrap_or_else(|| backoff_delay(attempt));
+                    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                        self.limiter.hold_off(delay);
+                    }
+                    println!("Scryfall returned {}, retrying in {:?}", resp.status(), delay);
+                    delay
+                }
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < MAX_ATTEMPTS => {
+                    let delay = backoff_delay(attempt);
+                    println!("Scryfall request failed ({}), retrying in {:?}", e, delay);
+                    delay
+                }
+                Err(e) => return Err(e.into()),
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl Default for ScryfallService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CardDataProvider for ScryfallService {
     /// Fetches all available Magic: The Gathering sets from Scryfall.
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<ScryfallSet>, Box<dyn Error>>` - A list of sets or an error.
-    pub async fn fetch_sets(&self) -> Result<Vec<ScryfallSet>, Box<dyn Error>> {
+    /// * `Result<Vec<ScryfallSet>, ProviderError>` - A list of sets or an error.
+    async fn fetch_sets(&self) -> Result<Vec<ScryfallSet>, ProviderError> {
         let url = format!("{}/sets", self.base_url);
-        let resp = self.client.get(&url).send().await?;
-        let text = resp.text().await?;
+        let resp = self.send(self.client.get(&url)).await?;
+        let text = String::from_utf8_lossy(&resp.body);
 
         // Try to parse as success
         match serde_json::from_str::<ScryfallSetList>(&text) {
@@ -50,17 +266,58 @@ impl ScryfallService {
     ///
     /// # Returns
     ///
-    /// * `Result<ScryfallCard, Box<dyn Error>>` - The card data or an error.
-    pub async fn fetch_card(
+    /// * `Result<ScryfallCard, ProviderError>` - The card data or an error.
+    async fn fetch_card(
         &self,
         id: &str,
-    ) -> Result<crate::models::scryfall::ScryfallCard, Box<dyn Error>> {
+    ) -> Result<ScryfallCard, ProviderError> {
+        if let Some(catalog) = &self.catalog {
+            match catalog.card(id) {
+                Ok(Some(card)) => return Ok(card),
+                Ok(None) => {}
+                Err(e) => println!("Catalog lookup failed: {}", e),
+            }
+        }
+
         let url = format!("{}/cards/{}", self.base_url, id);
-        let resp = self.client.get(&url).send().await?;
-        let card: crate::models::scryfall::ScryfallCard = resp.json().await?;
+        let resp = self.send(self.client.get(&url)).await?;
+        let card: ScryfallCard = resp.json()?;
         Ok(card)
     }
 
+    /// Fetches many cards by their Scryfall IDs through `/cards/collection`,
+    /// 75 per request. IDs Scryfall doesn't know are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The Scryfall IDs of the cards.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ScryfallCard>, ProviderError>` - The cards that were found or an error.
+    async fn fetch_cards(&self, ids: &[String]) -> Result<Vec<ScryfallCard>, ProviderError> {
+        let url = format!("{}/cards/collection", self.base_url);
+        let mut cards = Vec::with_capacity(ids.len());
+        for batch in ids.chunks(COLLECTION_BATCH_SIZE) {
+            let identifiers: Vec<serde_json::Value> =
+                batch.iter().map(|id| serde_json::json!({ "id": id })).collect();
+            let request = self
+                .client
+                .post(&url)
+                .json(&serde_json::json!({ "identifiers": identifiers }));
+            let resp = self.send(request).await?;
+            if !resp.status.is_success() {
+                return Err(format!("Scryfall returned {} for a card collection", resp.status).into());
+            }
+            let collection: ScryfallCollection = resp.json()?;
+            if !collection.not_found.is_empty() {
+                println!("Scryfall found no card for {} IDs", collection.not_found.len());
+            }
+            cards.extend(collection.data);
+        }
+        Ok(cards)
+    }
+
     /// Searches for cards using a Scryfall syntax query.
     ///
     /// # Arguments
@@ -70,38 +327,52 @@ impl ScryfallService {
     ///
     /// # Returns
     ///
-    /// * `Result<ScryfallCardList, Box<dyn Error>>` - A paginated list of cards or an error.
-    pub async fn search_cards(
+    /// * `Result<ScryfallCardList, ProviderError>` - A paginated list of cards or an error.
+    async fn search_cards(
         &self,
         query: &str,
         page: u32,
-    ) -> Result<crate::models::scryfall::ScryfallCardList, Box<dyn Error>> {
+    ) -> Result<ScryfallCardList, ProviderError> {
+        // Lookups by exact name or printing fit on the first page
+        if let (Some(catalog), Some(lookup), 1) = (&self.catalog, CardLookup::parse(query), page) {
+            match catalog.find(&lookup) {
+                Ok(cards) if !cards.is_empty() => {
+                    println!("Found {} cards in the catalog for: {}", cards.len(), query);
+                    return Ok(ScryfallCardList {
+                        total_cards: Some(cards.len() as i32),
+                        data: cards,
+                        has_more: false,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => println!("Catalog lookup failed: {}", e),
+            }
+        }
+
         let url = format!("{}/cards/search", self.base_url);
         println!("Searching Scryfall: {} (Page {})", query, page);
-        let resp = self
+        let request = self
             .client
             .get(&url)
             .query(&[
                 ("q", query),
                 ("unique", "prints"),
                 ("page", &page.to_string()),
-            ])
-            .send()
-            .await?;
+            ]);
+        let resp = self.send(request).await?;
 
-        let url_debug = resp.url().to_string();
-        println!("Request URL: {}", url_debug);
+        println!("Request URL: {}", resp.url);
 
-        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        if resp.status == StatusCode::NOT_FOUND {
             println!("Scryfall returned 404 (No cards found)");
-            return Ok(crate::models::scryfall::ScryfallCardList {
+            return Ok(ScryfallCardList {
                 data: vec![],
                 has_more: false,
                 total_cards: Some(0),
             });
         }
 
-        let list: crate::models::scryfall::ScryfallCardList = resp.json().await?;
+        let list: ScryfallCardList = resp.json()?;
         println!(
             "Found {} cards, has_more: {}",
             list.data.len(),
@@ -119,15 +390,15 @@ impl ScryfallService {
     ///
     /// # Returns
     ///
-    /// * `Result<ScryfallCardList, Box<dyn Error>>` - A paginated list of cards or an error.
-    pub async fn fetch_cards_by_set(
+    /// * `Result<ScryfallCardList, ProviderError>` - A paginated list of cards or an error.
+    async fn fetch_cards_by_set(
         &self,
         set_code: &str,
         page: u32,
-    ) -> Result<crate::models::scryfall::ScryfallCardList, Box<dyn Error>> {
+    ) -> Result<ScryfallCardList, ProviderError> {
         let url = format!("{}/cards/search", self.base_url);
         let query = format!("e:{}", set_code);
-        let resp = self
+        let request = self
             .client
             .get(&url)
             .query(&[
@@ -135,19 +406,18 @@ impl ScryfallService {
                 ("unique", "prints"),
                 ("order", "set"),
                 ("page", &page.to_string()),
-            ])
-            .send()
-            .await?;
+            ]);
+        let resp = self.send(request).await?;
 
-        if resp.status() == reqwest::StatusCode::NOT_FOUND {
-            return Ok(crate::models::scryfall::ScryfallCardList {
+        if resp.status == StatusCode::NOT_FOUND {
+            return Ok(ScryfallCardList {
                 data: vec![],
                 has_more: false,
                 total_cards: Some(0),
             });
         }
 
-        let list: crate::models::scryfall::ScryfallCardList = resp.json().await?;
+        let list: ScryfallCardList = resp.json()?;
         Ok(list)
     }
 
@@ -160,12 +430,24 @@ impl ScryfallService {
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<String>, Box<dyn Error>>` - A list of language codes or an error.
-    pub async fn get_card_languages(
+    /// * `Result<Vec<String>, ProviderError>` - A list of language codes or an error.
+    async fn get_card_languages(
         &self,
         oracle_id: &str,
         set_code: &str,
-    ) -> Result<Vec<String>, Box<dyn Error>> {
+    ) -> Result<Vec<String>, ProviderError> {
+        // Only the all_cards bulk file has every language of a printing
+        if let Some(catalog) = &self.catalog {
+            let complete = catalog.info().ok().flatten().is_some_and(|info| info.kind == "all_cards");
+            if complete {
+                match catalog.languages(oracle_id, set_code) {
+                    Ok(languages) if !languages.is_empty() => return Ok(languages),
+                    Ok(_) => {}
+                    Err(e) => println!("Catalog lookup failed: {}", e),
+                }
+            }
+        }
+
         let url = format!("{}/cards/search", self.base_url);
         let query = format!("oracle_id:{} set:{}", oracle_id, set_code);
         println!(
@@ -174,23 +456,22 @@ impl ScryfallService {
         );
         println!("Query: {}", query);
 
-        let resp = self
+        let request = self
             .client
             .get(&url)
             .query(&[
                 ("q", query.as_str()),
                 ("unique", "prints"),
                 ("include_multilingual", "true"),
-            ])
-            .send()
-            .await?;
+            ]);
+        let resp = self.send(request).await?;
 
-        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        if resp.status == StatusCode::NOT_FOUND {
             println!("No languages found (404)");
             return Ok(vec![]);
         }
 
-        let list: crate::models::scryfall::ScryfallCardList = resp.json().await?;
+        let list: ScryfallCardList = resp.json()?;
         println!("Found {} printings", list.data.len());
 
         let mut languages: Vec<String> =
@@ -213,18 +494,18 @@ impl ScryfallService {
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<crate::models::scryfall::ScryfallCard>, Box<dyn Error>>`
-    pub async fn get_top_cards(
+    /// * `Result<Vec<ScryfallCard>, ProviderError>`
+    async fn get_top_cards(
         &self,
         query: &str,
         order: &str,
         dir: &str,
         limit: usize,
-    ) -> Result<Vec<crate::models::scryfall::ScryfallCard>, Box<dyn Error>> {
+    ) -> Result<Vec<ScryfallCard>, ProviderError> {
         let url = format!("{}/cards/search", self.base_url);
         println!("Fetching top cards: {} order:{} dir:{}", query, order, dir);
 
-        let resp = self
+        let request = self
             .client
             .get(&url)
             .query(&[
@@ -232,15 +513,14 @@ impl ScryfallService {
                 ("order", order),
                 ("dir", dir),
                 ("unique", "prints"), // Use prints to get specific versions if needed, or cards for unique names
-            ])
-            .send()
-            .await?;
+            ]);
+        let resp = self.send(request).await?;
 
-        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        if resp.status == StatusCode::NOT_FOUND {
             return Ok(vec![]);
         }
 
-        let list: crate::models::scryfall::ScryfallCardList = resp.json().await?;
+        let list: ScryfallCardList = resp.json()?;
 
         // Take only the requested limit
         let cards = list.data.into_iter().take(limit).collect();
@@ -251,37 +531,223 @@ impl ScryfallService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::scryfall::Prices;
+    use crate::services::fixtures::{bundled_fixtures, card_page, sort_cards, FixtureService};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves the Scryfall endpoints the service uses from fixtures on a
+    /// local port. Responses carry an `ETag`, and requests with a matching
+    /// `If-None-Match` get 304 Not Modified.
+    struct LocalServer {
+        base_url: String,
+        /// Requests still to be answered with 429 Too Many Requests
+        rejections: Arc<AtomicUsize>,
+        /// Requests received
+        requests: Arc<AtomicUsize>,
+        /// Requests answered with 304 Not Modified
+        not_modified: Arc<AtomicUsize>,
+    }
+
+    impl LocalServer {
+        async fn start(fixtures: FixtureService) -> LocalServer {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let server = LocalServer {
+                base_url: format!("http://{}", listener.local_addr().unwrap()),
+                rejections: Arc::new(AtomicUsize::new(0)),
+                requests: Arc::new(AtomicUsize::new(0)),
+                not_modified: Arc::new(AtomicUsize::new(0)),
+            };
+            let fixtures = Arc::new(fixtures);
+            let (rejections, requests, not_modified) =
+                (server.rejections.clone(), server.requests.clone(), server.not_modified.clone());
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let fixtures = fixtures.clone();
+                    let (rejections, requests, not_modified) =
+                        (rejections.clone(), requests.clone(), not_modified.clone());
+                    tokio::spawn(async move {
+                        let mut request = Vec::new();
+                        let mut buf = [0u8; 4096];
+                        let mut body_start = None;
+                        let mut body_length = 0;
+                        while body_start.is_none_or(|start| request.len() < start + body_length) {
+                            match stream.read(&mut buf).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => request.extend_from_slice(&buf[..n]),
+                            }
+                            if body_start.is_none() {
+                                if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                                    body_start = Some(end + 4);
+                                    body_length = header(&String::from_utf8_lossy(&request[..end]), "content-length")
+                                        .and_then(|v| v.parse().ok())
+                                        .unwrap_or(0);
+                                }
+                            }
+                        }
+                        requests.fetch_add(1, Ordering::SeqCst);
+                        let request = String::from_utf8_lossy(&request);
+                        let body_start = body_start.unwrap_or(request.len()).min(request.len());
+                        let (head, body) = request.split_at(body_start);
+                        let target = head.split_whitespace().nth(1).unwrap_or("/");
+                        let rejected = rejections
+                            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+                            .is_ok();
+
+                        let (status, body) = if rejected {
+                            ("429 Too Many Requests\r\nRetry-After: 0".to_string(), r#"{"object":"error","status":429}"#.to_string())
+                        } else {
+                            let (status, body) = respond(&fixtures, target, body).await;
+                            let etag = format!("\"{}\"", &crate::image_cache::ImageCache::digest(body.as_bytes())[..16]);
+                            if header(head, "if-none-match") == Some(etag.clone()) {
+                                not_modified.fetch_add(1, Ordering::SeqCst);
+                                ("304 Not Modified".to_string(), String::new())
+                            } else {
+                                (format!("{}\r\nETag: {}", status, etag), body)
+                            }
+                        };
+                        let response = format!(
+                            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            status,
+                            body.len(),
+                            body
+                        );
+                        let _ = stream.write_all(response.as_bytes()).await;
+                    });
+                }
+            });
+            server
+        }
+
+        fn service(&self) -> ScryfallService {
+            ScryfallService::with_base_url(&self.base_url)
+        }
+    }
+
+    /// Value of a header in a request's head
+    fn header(head: &str, name: &str) -> Option<String> {
+        head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+    }
+
+    /// A service pointed at a `LocalServer` over the fixtures
+    async fn local_service(fixtures: FixtureService) -> ScryfallService {
+        LocalServer::start(fixtures).await.service()
+    }
+
+    /// Like `local_service`, but the first `rate_limited` requests are
+    /// answered with 429 Too Many Requests
+    async fn flaky_service(fixtures: FixtureService, rate_limited: usize) -> ScryfallService {
+        let server = LocalServer::start(fixtures).await;
+        server.rejections.store(rate_limited, Ordering::SeqCst);
+        server.service()
+    }
+
+    /// Answers a request the way Scryfall would, 404 when nothing matches
+    async fn respond(fixtures: &FixtureService, target: &str, body: &str) -> (&'static str, String) {
+        let url = reqwest::Url::parse(&format!("http://localhost{}", target)).unwrap();
+        let param = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.into_owned())
+                .unwrap_or_default()
+        };
+        let not_found = ("404 Not Found", r#"{"object":"error","status":404}"#.to_string());
+
+        match url.path() {
+            "/cards/collection" => {
+                let request: serde_json::Value = serde_json::from_str(body).unwrap();
+                let ids: Vec<String> = request["identifiers"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|identifier| identifier["id"].as_str().map(str::to_string))
+                    .collect();
+                if ids.len() > COLLECTION_BATCH_SIZE {
+                    return ("422 Unprocessable Entity", r#"{"object":"error","status":422}"#.to_string());
+                }
+                let data = fixtures.fetch_cards(&ids).await.unwrap();
+                let not_found: Vec<serde_json::Value> = ids
+                    .iter()
+                    .filter(|id| !data.iter().any(|card| &card.id == *id))
+                    .map(|id| serde_json::json!({ "id": id }))
+                    .collect();
+                ("200 OK", serde_json::to_string(&ScryfallCollection { data, not_found }).unwrap())
+            }
+            "/sets" => {
+                let sets = fixtures.fetch_sets().await.unwrap();
+                ("200 OK", serde_json::json!({ "data": sets, "has_more": false }).to_string())
+            }
+            "/cards/search" => {
+                let mut cards = fixtures.matching_cards(&param("q"), param("include_multilingual") == "true");
+                if cards.is_empty() {
+                    return not_found;
+                }
+                sort_cards(&mut cards, &param("order"), &param("dir"));
+                let page = param("page").parse().unwrap_or(1);
+                ("200 OK", serde_json::to_string(&card_page(cards, page)).unwrap())
+            }
+            path => match fixtures.fetch_card(path.trim_start_matches("/cards/")).await {
+                Ok(card) => ("200 OK", serde_json::to_string(&card).unwrap()),
+                Err(_) => not_found,
+            },
+        }
+    }
+
+    fn creature(i: usize) -> ScryfallCard {
+        ScryfallCard {
+            id: format!("creature-{}", i),
+            oracle_id: Some(format!("oracle-{}", i)),
+            name: format!("Creature {}", i),
+            lang: Some("en".to_string()),
+            set: "tst".to_string(),
+            set_name: "Test Set".to_string(),
+            collector_number: (i + 1).to_string(),
+            released_at: "2024-01-01".to_string(),
+            artist: None,
+            image_uris: None,
+            prices: Prices {
+                usd: None,
+                usd_foil: None,
+                eur: None,
+                eur_foil: None,
+            },
+            rarity: "common".to_string(),
+            similarity: None,
+            match_confidence: None,
+        }
+    }
 
     #[tokio::test]
     async fn test_search_pagination() {
-        let service = ScryfallService::new();
-        // "t:creature" definitely has more than 175 cards
+        let service = local_service(FixtureService::new(vec![], (0..200).map(creature).collect())).await;
         let result = service
             .search_cards("t:creature", 1)
             .await
             .expect("Search failed");
 
-        println!("Found {} cards", result.data.len());
-        println!("Has more: {}", result.has_more);
-        println!("Total cards: {:?}", result.total_cards);
-
         assert_eq!(
             result.data.len(),
             175,
             "Should return 175 cards for the first page"
         );
         assert!(result.has_more, "Should have more results");
+        assert_eq!(result.total_cards, Some(200));
+
+        let last = service.search_cards("t:creature", 2).await.expect("Search failed");
+        assert_eq!(last.data.len(), 25);
+        assert!(!last.has_more, "Should NOT have more results");
     }
 
     #[tokio::test]
     async fn test_search_fbb() {
-        let service = ScryfallService::new();
-        // Query "fbb" (name search) likely has few results
+        let service = local_service(bundled_fixtures()).await;
         let result = service.search_cards("fbb", 1).await.expect("Search failed");
 
-        println!("FBB Search - Found {} cards", result.data.len());
-        println!("FBB Search - Has more: {}", result.has_more);
-
         assert_eq!(
             result.data.len(),
             0,
@@ -292,40 +758,31 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_card_languages() {
-        let service = ScryfallService::new();
+        let service = local_service(bundled_fixtures()).await;
 
-        // 1. Fetch Lightning Bolt from M10 to get a valid Oracle ID
+        // 1. Fetch Lightning Bolt from M10 to get its Oracle ID
         let cards = service
             .search_cards("Lightning Bolt set:m10", 1)
             .await
             .expect("Failed to search card");
-        assert!(!cards.data.is_empty(), "Should find Lightning Bolt");
+        assert_eq!(cards.data.len(), 1, "Should find English Lightning Bolt only");
         let card = &cards.data[0];
         let oracle_id = card.oracle_id.as_ref().expect("Card should have oracle_id");
 
-        println!("Found Lightning Bolt, Oracle ID: {}", oracle_id);
-
-        // 2. Test get_card_languages with the valid ID
+        // 2. M10 Lightning Bolt was printed in several languages
         let languages = service
             .get_card_languages(oracle_id, "m10")
             .await
             .expect("Failed to fetch languages");
+        assert_eq!(languages, vec!["de", "en", "fr", "ja"]);
 
-        println!("Languages found for Lightning Bolt (M10): {:?}", languages);
-        assert!(languages.contains(&"en".to_string()));
-        // M10 Lightning Bolt should be printed in multiple languages
-        // If this fails, it might be that Scryfall API behavior changed or my assumption about M10 languages is wrong,
-        // but it's a standard set so it should have them.
-        assert!(languages.len() > 1, "Should have multiple languages");
-
-        // 3. Test with Alpha Black Lotus (English only)
+        // 3. Alpha Black Lotus is English only
         let cards_alpha = service
             .search_cards("Black Lotus set:lea", 1)
             .await
             .expect("Failed to search Alpha card");
         assert!(!cards_alpha.data.is_empty(), "Should find Black Lotus");
-        let card_alpha = &cards_alpha.data[0];
-        let oracle_id_alpha = card_alpha
+        let oracle_id_alpha = cards_alpha.data[0]
             .oracle_id
             .as_ref()
             .expect("Card should have oracle_id");
@@ -334,12 +791,112 @@ mod tests {
             .get_card_languages(oracle_id_alpha, "lea")
             .await
             .expect("Failed to fetch languages for Alpha");
+        assert_eq!(languages_alpha, vec!["en"]);
 
-        println!(
-            "Languages found for Black Lotus (Alpha): {:?}",
-            languages_alpha
-        );
-        assert_eq!(languages_alpha.len(), 1);
-        assert_eq!(languages_alpha[0], "en");
+        // No printings is a 404, which means no languages
+        let none = service.get_card_languages(oracle_id, "lea").await.unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_base_url() {
+        let service = local_service(bundled_fixtures()).await;
+
+        let sets = service.fetch_sets().await.expect("Failed to fetch sets");
+        assert_eq!(sets.len(), 5);
+
+        let set = service.fetch_cards_by_set("dmu", 1).await.unwrap();
+        assert_eq!(set.data[0].name, "Sheoldred, the Apocalypse");
+        let card = service.fetch_card(&set.data[1].id).await.unwrap();
+        assert_eq!(card.name, "Llanowar Elves");
+
+        let top = service.get_top_cards("game:paper", "usd", "asc", 1).await.unwrap();
+        assert_eq!(top[0].name, "Llanowar Elves");
+    }
+    #[tokio::test]
+    async fn test_retries_rate_limited_requests() {
+        let service = flaky_service(bundled_fixtures(), 2).await;
+        let result = service.search_cards("Black Lotus", 1).await.expect("Search failed");
+        assert_eq!(result.data.len(), 1);
+
+        let service = flaky_service(bundled_fixtures(), MAX_ATTEMPTS as usize).await;
+        let error = service.fetch_sets().await.expect_err("Should give up");
+        assert!(error.to_string().contains("429"), "{}", error);
+    }
+    #[tokio::test]
+    async fn test_fetch_cards_in_batches() {
+        let service = local_service(FixtureService::new(vec![], (0..200).map(creature).collect())).await;
+        let mut ids: Vec<String> = (0..200).map(|i| format!("creature-{}", i)).collect();
+        ids.push("unknown".to_string());
+
+        let cards = service.fetch_cards(&ids).await.expect("Fetch failed");
+        assert_eq!(cards.len(), 200);
+        assert!(service.fetch_cards(&[]).await.unwrap().is_empty());
+    }
+    #[tokio::test]
+    async fn test_catalog_answers_without_requests() {
+        let dir = std::env::temp_dir().join(format!("mtgia-scryfall-catalog-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("default-cards.json");
+        std::fs::write(&file, serde_json::to_string(&[creature(0)]).unwrap()).unwrap();
+        let catalog = Catalog::new(dir.join("catalog.db"));
+        catalog.import_bulk_file(&file, "default_cards", "2024-01-01", |_| {}).unwrap();
+
+        let server = LocalServer::start(FixtureService::new(vec![], (0..3).map(creature).collect())).await;
+        let service = server.service().with_catalog(catalog);
+
+        assert_eq!(service.fetch_card("creature-0").await.unwrap().name, "Creature 0");
+        let printing = service.search_cards("set:tst cn:1", 1).await.unwrap();
+        assert_eq!(printing.data[0].id, "creature-0");
+        let named = service.search_cards("!\"Creature 0\"", 1).await.unwrap();
+        assert_eq!(named.data.len(), 1);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 0);
+
+        // Cards the catalog lacks still come from the API
+        assert_eq!(service.fetch_card("creature-1").await.unwrap().name, "Creature 1");
+        assert_eq!(server.requests.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    async fn sets(service: &ScryfallService) -> usize {
+        service.fetch_sets().await.expect("Failed to fetch sets").len()
+    }
+
+    #[tokio::test]
+    async fn test_response_cache() {
+        let server = LocalServer::start(bundled_fixtures()).await;
+        let path = std::env::temp_dir().join(format!("mtgia-scryfall-cache-{}.db", uuid::Uuid::new_v4()));
+        let cache = ResponseCache::new(path.clone());
+        let service = server.service().with_cache(cache.clone());
+
+        // A fresh copy is answered from the cache
+        assert_eq!(sets(&service).await, 5);
+        assert_eq!(sets(&service).await, 5);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 1);
+
+        // A stale one is revalidated with its ETag
+        cache.age_entries(SETS_TTL);
+        assert_eq!(sets(&service).await, 5);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 1);
+        assert_eq!(sets(&service).await, 5);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 2);
+
+        // Queries are cached apart
+        let bolt = service.search_cards("Lightning Bolt", 1).await.unwrap();
+        let lotus = service.search_cards("Black Lotus", 1).await.unwrap();
+        assert_ne!(bolt.data[0].id, lotus.data[0].id);
+
+        // When the API fails, a stale copy is only served in stale-if-error mode
+        cache.age_entries(SETS_TTL);
+        server.rejections.store(usize::MAX, Ordering::SeqCst);
+        assert!(service.fetch_sets().await.is_err());
+        let offline = server.service().with_cache(cache.clone().with_stale_if_error(true));
+        assert_eq!(sets(&offline).await, 5);
+
+        // ...and only until it is too old to trust
+        cache.age_entries(MAX_STALE);
+        assert!(offline.fetch_sets().await.is_err());
+
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,342 @@
+use crate::card_features::margin_confidence;
+use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use imageproc::region_labelling::{connected_components, Connectivity};
+use resvg::{tiny_skia, usvg};
+
+/// Side of the square grid symbol shapes are normalized to
+const MASK_SIZE: u32 = 32;
+
+/// Size icons are rendered at before normalizing, large enough for thin details
+const RENDER_SIZE: u32 = 128;
+
+/// Minimum overlap between a card's symbol and a set icon to accept the match
+const MIN_SYMBOL_IOU: f32 = 0.6;
+
+/// How far a pixel's color must be from the type-line background to count as
+/// part of the symbol (RGB distance, 0-1)
+const FOREGROUND_DISTANCE: f32 = 0.22;
+
+/// The shape of an expansion symbol, cropped to its bounding box and scaled to
+/// fit a 32x32 grid. Set icons are single-color silhouettes while printed
+/// symbols are colored by rarity, so only the shape is compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolMask([u64; 16]);
+
+impl SymbolMask {
+    /// Builds a mask from a binary image, cropping it to the set pixels and
+    /// fitting them into the grid with their aspect ratio preserved.
+    /// Returns None if no pixel is set.
+    fn from_binary(pixels: &[bool], width: u32, height: u32) -> Option<Self> {
+        let is_set = |x: u32, y: u32| pixels[(y * width + x) as usize];
+
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        for y in 0..height {
+            for x in 0..width {
+                if is_set(x, y) {
+                    min_x = min_x.min(x);
+                    min_y = min_y.min(y);
+                    max_x = max_x.max(x);
+                    max_y = max_y.max(y);
+                }
+            }
+        }
+        if min_x > max_x {
+            return None;
+        }
+
+        let box_width = (max_x - min_x + 1) as f32;
+        let box_height = (max_y - min_y + 1) as f32;
+        let scale = MASK_SIZE as f32 / box_width.max(box_height);
+        let offset_x = (MASK_SIZE as f32 - box_width * scale) / 2.0;
+        let offset_y = (MASK_SIZE as f32 - box_height * scale) / 2.0;
+
+        let mut bits = [0u64; 16];
+        for gy in 0..MASK_SIZE {
+            for gx in 0..MASK_SIZE {
+                // Sample the source pixel under the center of each grid cell
+                let sx = (gx as f32 + 0.5 - offset_x) / scale;
+                let sy = (gy as f32 + 0.5 - offset_y) / scale;
+                if sx < 0.0 || sy < 0.0 || sx >= box_width || sy >= box_height {
+                    continue;
+                }
+                if is_set(min_x + sx as u32, min_y + sy as u32) {
+                    let bit = (gy * MASK_SIZE + gx) as usize;
+                    bits[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        }
+        Some(SymbolMask(bits))
+    }
+
+    /// Intersection over union of two shapes (1 = identical)
+    pub fn iou(&self, other: &SymbolMask) -> f32 {
+        let (mut intersection, mut union) = (0, 0);
+        for (a, b) in self.0.iter().zip(other.0.iter()) {
+            intersection += (a & b).count_ones();
+            union += (a | b).count_ones();
+        }
+        if union == 0 {
+            0.0
+        } else {
+            intersection as f32 / union as f32
+        }
+    }
+
+    /// Serializes the mask for the database (128 bytes)
+    pub fn to_bytes(self) -> Vec<u8> {
+        self.0.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// Reads a mask written by [`SymbolMask::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 128 {
+            return None;
+        }
+        let mut bits = [0u64; 16];
+        for (word, chunk) in bits.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().ok()?);
+        }
+        Some(SymbolMask(bits))
+    }
+}
+
+/// Renders a set icon SVG into a symbol mask.
+///
+/// # Arguments
+///
+/// * `svg` - The SVG document, as served by Scryfall's `icon_svg_uri`.
+///
+/// # Returns
+///
+/// * `Result<SymbolMask, String>` - The icon's shape or an error message.
+pub fn render_set_icon(svg: &[u8]) -> Result<SymbolMask, String> {
+    let tree = usvg::Tree::from_data(svg, &usvg::Options::default())
+        .map_err(|e| format!("Failed to parse set icon: {}", e))?;
+
+    let size = tree.size();
+    let scale = RENDER_SIZE as f32 / size.width().max(size.height());
+    let width = ((size.width() * scale).ceil() as u32).max(1);
+    let height = ((size.height() * scale).ceil() as u32).max(1);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| "Failed to allocate set icon canvas".to_string())?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let pixels: Vec<bool> = pixmap.pixels().iter().map(|p| p.alpha() > 127).collect();
+    SymbolMask::from_binary(&pixels, width, height)
+        .ok_or_else(|| "Set icon is empty".to_string())
+}
+
+/// Crops the expansion symbol area, at the right end of the type line.
+/// Assumes a rectified card; the region is wide enough to cover the symbol
+/// position of every frame style.
+pub fn crop_symbol_region(image: &DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let x = (width as f32 * 0.80) as u32;
+    let y = (height as f32 * 0.54) as u32;
+    let crop_width = (width as f32 * 0.15) as u32;
+    let crop_height = (height as f32 * 0.08) as u32;
+    image.crop_imm(x, y, crop_width.max(1), crop_height.max(1))
+}
+
+/// Extracts the shape of the expansion symbol from a rectified card image.
+///
+/// The type-line background color is estimated from the edges of the symbol
+/// region; pixels far from it are foreground, and the largest foreground blob
+/// is taken as the symbol (type-line text that reaches the region is smaller).
+///
+/// # Returns
+///
+/// * `Option<SymbolMask>` - The symbol shape, or None if nothing stands out.
+pub fn extract_symbol_mask(image: &DynamicImage) -> Option<SymbolMask> {
+    let region = crop_symbol_region(image).to_rgb8();
+    let (width, height) = region.dimensions();
+    if width < 8 || height < 8 {
+        return None;
+    }
+
+    // Median color of the region's outline approximates the type-line bar
+    let mut edge: Vec<[u8; 3]> = Vec::new();
+    for x in 0..width {
+        edge.push(region.get_pixel(x, 0).0);
+        edge.push(region.get_pixel(x, height - 1).0);
+    }
+    for y in 0..height {
+        edge.push(region.get_pixel(0, y).0);
+        edge.push(region.get_pixel(width - 1, y).0);
+    }
+    let median = |channel: usize| {
+        let mut values: Vec<u8> = edge.iter().map(|p| p[channel]).collect();
+        values.sort_unstable();
+        values[values.len() / 2] as f32
+    };
+    let background = [median(0), median(1), median(2)];
+
+    let foreground = GrayImage::from_fn(width, height, |x, y| {
+        let p = region.get_pixel(x, y);
+        let distance = (0..3)
+            .map(|c| ((p[c] as f32 - background[c]) / 255.0).powi(2))
+            .sum::<f32>()
+            .sqrt()
+            / 3f32.sqrt();
+        Luma([if distance > FOREGROUND_DISTANCE { 255 } else { 0 }])
+    });
+
+    let labels = connected_components(&foreground, Connectivity::Eight, Luma([0u8]));
+    let mut sizes = std::collections::HashMap::new();
+    for label in labels.pixels().map(|p| p[0]).filter(|l| *l != 0) {
+        *sizes.entry(label).or_insert(0u32) += 1;
+    }
+    let (symbol_label, symbol_size) = sizes.into_iter().max_by_key(|(_, size)| *size)?;
+
+    // Noise specks aren't a symbol
+    if symbol_size < width * height / 100 {
+        return None;
+    }
+
+    let pixels: Vec<bool> = labels.pixels().map(|p| p[0] == symbol_label).collect();
+    SymbolMask::from_binary(&pixels, width, height)
+}
+
+/// In-memory set icons, matched against the symbols found on cards
+#[derive(Default)]
+pub struct SetSymbolIndex {
+    symbols: Vec<(String, SymbolMask)>,
+}
+
+impl SetSymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the index from (set code, mask bytes) rows, skipping invalid masks
+    pub fn from_rows(rows: Vec<(String, Vec<u8>)>) -> Self {
+        let mut index = SetSymbolIndex::new();
+        for (set_code, bytes) in rows {
+            if let Some(mask) = SymbolMask::from_bytes(&bytes) {
+                index.insert(set_code, mask);
+            }
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Adds or replaces the icon of a set
+    pub fn insert(&mut self, set_code: String, mask: SymbolMask) {
+        match self.symbols.iter_mut().find(|(code, _)| *code == set_code) {
+            Some(entry) => entry.1 = mask,
+            None => self.symbols.push((set_code, mask)),
+        }
+    }
+
+    /// Finds the set whose icon best matches a symbol.
+    ///
+    /// Token, promo and other supplemental sets reuse their parent set's icon,
+    /// so equal scores are resolved towards the shortest code ("dom" over "tdom").
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(String, f32)>` - The set code and its overlap score.
+    pub fn best_match(&self, symbol: &SymbolMask) -> Option<(String, f32)> {
+        self.symbols
+            .iter()
+            .map(|(code, mask)| (code, symbol.iou(mask)))
+            .filter(|(_, score)| *score >= MIN_SYMBOL_IOU)
+            .max_by(|(code_a, a), (code_b, b)| {
+                a.total_cmp(b)
+                    .then_with(|| code_b.len().cmp(&code_a.len()))
+                    .then_with(|| code_b.cmp(code_a))
+            })
+            .map(|(code, score)| (code.clone(), score))
+    }
+
+    /// Detects the set of a card from the shape of its expansion symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol cut out of the card by [`extract_symbol_mask`].
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(String, f32)>` - The set code and how sure the match is (0.5-1),
+    ///   from how far its overlap score clears the minimum.
+    pub fn detect_set(&self, symbol: &SymbolMask) -> Option<(String, f32)> {
+        let (set_code, score) = self.best_match(symbol)?;
+        println!("Set symbol matched {} (iou={:.2})", set_code, score);
+        Some((set_code, margin_confidence(score - MIN_SYMBOL_IOU, 1.0 - MIN_SYMBOL_IOU)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    const CIRCLE_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><circle cx="16" cy="16" r="14"/></svg>"#;
+    const SQUARE_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><rect x="2" y="2" width="28" height="28"/></svg>"#;
+    const TRIANGLE_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><path d="M16 2 L30 30 L2 30 Z"/></svg>"#;
+
+    /// A rectified card with a gold circle on a light type line
+    fn card_with_circle_symbol() -> DynamicImage {
+        let (width, height) = (630u32, 880u32);
+        let center = (width as f32 * 0.875, height as f32 * 0.58);
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let dx = x as f32 - center.0;
+            let dy = y as f32 - center.1;
+            if (dx * dx + dy * dy).sqrt() < 18.0 {
+                Rgb([200, 160, 40])
+            } else {
+                Rgb([225, 220, 210])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_mask_bytes_round_trip() {
+        let mask = render_set_icon(CIRCLE_SVG.as_bytes()).unwrap();
+        let bytes = mask.to_bytes();
+        assert_eq!(bytes.len(), 128);
+        assert_eq!(SymbolMask::from_bytes(&bytes), Some(mask));
+        assert_eq!(SymbolMask::from_bytes(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn test_render_set_icon_shapes() {
+        let circle = render_set_icon(CIRCLE_SVG.as_bytes()).unwrap();
+        let square = render_set_icon(SQUARE_SVG.as_bytes()).unwrap();
+        assert_eq!(circle.iou(&circle), 1.0);
+        // A square fills the whole grid, a circle about 78% of it
+        assert!(circle.iou(&square) > 0.7 && circle.iou(&square) < 0.85);
+        assert!(render_set_icon(b"not svg").is_err());
+    }
+
+    #[test]
+    fn test_detect_set_from_card() {
+        let mut index = SetSymbolIndex::new();
+        index.insert("sqr".to_string(), render_set_icon(SQUARE_SVG.as_bytes()).unwrap());
+        index.insert("tri".to_string(), render_set_icon(TRIANGLE_SVG.as_bytes()).unwrap());
+        index.insert("cir".to_string(), render_set_icon(CIRCLE_SVG.as_bytes()).unwrap());
+        // A token set sharing the circle icon
+        index.insert("tcir".to_string(), render_set_icon(CIRCLE_SVG.as_bytes()).unwrap());
+
+        let symbol = extract_symbol_mask(&card_with_circle_symbol()).unwrap();
+        let (set_code, confidence) = index.detect_set(&symbol).unwrap();
+        assert_eq!(set_code, "cir");
+        assert!(confidence > 0.5 && confidence <= 1.0);
+
+        // A blank type line has no symbol
+        let blank = DynamicImage::ImageRgb8(RgbImage::from_pixel(630, 880, Rgb([225, 220, 210])));
+        assert_eq!(extract_symbol_mask(&blank), None);
+    }
+}
@@ -22,22 +22,28 @@ interface CardDetailsModalProps {
     mode?: 'add' | 'view';
     /** The existing collection card data (required if mode is 'view') */
     collectionCard?: CollectionCard;
+    /** Language code to preselect when adding (e.g. read from the scanned card) */
+    initialLanguage?: string | null;
+    /** Condition to preselect when adding (e.g. estimated from the scanned card) */
+    initialCondition?: string | null;
+    /** Finish to preselect when adding (e.g. told from a burst of scanned frames) */
+    initialFinish?: string | null;
 }
 
 /**
  * Modal for viewing detailed card information and performing actions.
  * Supports adding new cards to collection/wishlist or editing existing collection cards.
  */
-export default function CardDetailsModal({ card, onClose, onCardAdded, mode = 'add', collectionCard }: CardDetailsModalProps) {
+export default function CardDetailsModal({ card, onClose, onCardAdded, mode = 'add', collectionCard, initialLanguage, initialCondition, initialFinish }: CardDetailsModalProps) {
     const { currency, formatPrice } = useSettings();
     const [activeTab, setActiveTab] = useState<'collection' | 'wishlist'>('collection');
     const [loading, setLoading] = useState(false);
 
     // Collection Form State
     const [quantity, setQuantity] = useState(1);
-    const [condition, setCondition] = useState('NM');
+    const [condition, setCondition] = useState(mode === 'add' && initialCondition ? initialCondition : 'NM');
     const [language, setLanguage] = useState('en'); // Default to code 'en'
-    const [finish, setFinish] = useState('nonfoil');
+    const [finish, setFinish] = useState(mode === 'add' && initialFinish ? initialFinish : 'nonfoil');
     const [price, setPrice] = useState(0);
 
     // Wishlist Form State
@@ -61,7 +67,9 @@ export default function CardDetailsModal({ card, onClose, onCardAdded, mode = 'a
 
     async function fetchLanguages() {
         if (!card.oracle_id) {
-            setAvailableLanguages(['en']);
+            const fallback = mode === 'add' && initialLanguage ? initialLanguage : 'en';
+            setAvailableLanguages([fallback]);
+            if (mode === 'add') setLanguage(fallback);
             return;
         }
 
@@ -74,9 +82,11 @@ export default function CardDetailsModal({ card, onClose, onCardAdded, mode = 'a
 
             if (langs.length > 0) {
                 setAvailableLanguages(langs);
-                // If adding new card, default to English or first available
+                // If adding new card, default to the scanned language, English or first available
                 if (mode === 'add') {
-                    if (langs.includes('en')) {
+                    if (initialLanguage && langs.includes(initialLanguage)) {
+                        setLanguage(initialLanguage);
+                    } else if (langs.includes('en')) {
                         setLanguage('en');
                     } else {
                         setLanguage(langs[0]);
@@ -1,8 +1,9 @@
 import { useState, useRef, useCallback, useEffect } from 'react';
 import Webcam from 'react-webcam';
 import { invoke } from '@tauri-apps/api/core';
-import { ScryfallCard, RecognitionResult } from '../types';
+import { ScryfallCard, RecognitionResult, ConditionEstimate, FinishEstimate } from '../types';
 import CardDetailsModal from './CardDetailsModal';
+import { getFinishLabel } from '../utils/cardFinishes';
 
 interface ScannerModalProps {
     onClose: () => void;
@@ -11,6 +12,11 @@ interface ScannerModalProps {
 
 type ScanMode = 'camera' | 'file';
 
+// Frames captured per camera scan, for telling foil from nonfoil by how the
+// card catches the light as it moves
+const BURST_FRAMES = 4;
+const BURST_INTERVAL_MS = 150;
+
 export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps) {
     const webcamRef = useRef<Webcam>(null);
     const fileInputRef = useRef<HTMLInputElement>(null);
@@ -18,6 +24,10 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
     const [ocrText, setOcrText] = useState('');
     const [searchResults, setSearchResults] = useState<ScryfallCard[]>([]);
     const [selectedCard, setSelectedCard] = useState<ScryfallCard | null>(null);
+    const [detectedLanguage, setDetectedLanguage] = useState<string | null>(null);
+    const [imageHashes, setImageHashes] = useState<string | null>(null);
+    const [conditionEstimate, setConditionEstimate] = useState<ConditionEstimate | null>(null);
+    const [finishEstimate, setFinishEstimate] = useState<FinishEstimate | null>(null);
     const [status, setStatus] = useState('Ready to scan');
     const [cameraAvailable, setCameraAvailable] = useState<boolean | null>(null);
     const [scanMode, setScanMode] = useState<ScanMode>('camera');
@@ -59,7 +69,7 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
         checkCamera();
     }, []);
 
-    const processImage = async (imageSrc: string) => {
+    const processImage = async (imageSrc: string, burst?: string[]) => {
         setScanning(true);
         setStatus('Analyzing card features...');
         setSearchResults([]);
@@ -74,15 +84,25 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
             // Call new recognition command
             const result = await invoke<RecognitionResult>(
                 'recognize_card_with_features',
-                { imageData: base64Data }
+                burst
+                    ? { imageData: base64Data, burst: burst.map(frame => frame.split(',')[1]) }
+                    : { imageData: base64Data }
             );
 
             // Update UI with results
             setOcrText(result.feature_description);
             setSearchResults(result.candidates.slice(0, 5));
+            setDetectedLanguage(result.detected_language ?? null);
+            setImageHashes(result.image_hashes);
+            setConditionEstimate(result.condition ?? null);
+            setFinishEstimate(result.finish ?? null);
 
             console.log('Detected features:', result.features);
             console.log('Search query:', result.search_query);
+            console.log('Stage timings (ms):', result.timings);
+            if (result.rotation) {
+                console.log(`Photo turned ${result.rotation}° to read the card`);
+            }
 
             if (result.candidates && result.candidates.length > 0) {
                 setStatus(`Found ${result.candidates.length} matches`);
@@ -98,10 +118,32 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
         }
     };
 
+    // Teach the recognizer which candidate was right, and which ranked above it wrongly
+    const recordFeedback = async (chosen: ScryfallCard) => {
+        if (!imageHashes) return;
+        const index = searchResults.findIndex(c => c.id === chosen.id);
+        const rejected = index > 0 ? searchResults.slice(0, index) : [];
+        try {
+            await invoke('record_recognition_feedback', { imageHashes, chosen, rejected });
+        } catch (error) {
+            console.error('Failed to record recognition feedback:', error);
+        }
+    };
+
     const capture = useCallback(async () => {
         const imageSrc = webcamRef.current?.getScreenshot();
         if (!imageSrc) return;
-        await processImage(imageSrc);
+
+        setStatus('Tilt the card slightly...');
+        const burst = [imageSrc];
+        while (burst.length < BURST_FRAMES) {
+            await new Promise(resolve => setTimeout(resolve, BURST_INTERVAL_MS));
+            const frame = webcamRef.current?.getScreenshot();
+            if (!frame) break;
+            burst.push(frame);
+        }
+        // The photo itself is the burst's first frame, only the rest is sent again
+        await processImage(imageSrc, burst.slice(1));
     }, [webcamRef]);
 
     const handleFileUpload = useCallback(async (event: React.ChangeEvent<HTMLInputElement>) => {
@@ -242,6 +284,30 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
                             </div>
                         )}
 
+                        {conditionEstimate && (
+                            <div className="mb-4">
+                                <h4 className="text-sm font-medium text-gray-700 mb-2">
+                                    Suggested Condition: {conditionEstimate.grade}
+                                </h4>
+                                <ul className="text-xs text-gray-500 bg-white p-2 rounded border space-y-1">
+                                    {conditionEstimate.evidence.map(evidence => (
+                                        <li key={evidence.defect} className="flex justify-between">
+                                            <span>{evidence.detail}</span>
+                                            <span className="font-medium text-gray-700">{evidence.grade}</span>
+                                        </li>
+                                    ))}
+                                </ul>
+                            </div>
+                        )}
+
+                        {finishEstimate && (
+                            <div className="mb-4">
+                                <h4 className="text-sm font-medium text-gray-700 mb-2">
+                                    Suggested Finish: {getFinishLabel(finishEstimate.finish)}
+                                </h4>
+                            </div>
+                        )}
+
                         <div>
                             <h4 className="text-sm font-medium text-gray-700 mb-2">Results</h4>
                             <div className="space-y-2">
@@ -274,6 +340,8 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
                                             {card.similarity !== undefined && (
                                                 <p className="text-xs text-blue-600 mt-1">
                                                     Visual diff: {card.similarity}
+                                                    {card.match_confidence !== undefined &&
+                                                        ` • ${Math.round(card.match_confidence * 100)}% match`}
                                                 </p>
                                             )}
                                         </div>
@@ -294,8 +362,12 @@ export default function ScannerModal({ onClose, onCardAdded }: ScannerModalProps
                 <CardDetailsModal
                     card={selectedCard}
                     mode="add"
+                    initialLanguage={detectedLanguage}
+                    initialCondition={conditionEstimate?.grade}
+                    initialFinish={finishEstimate?.finish}
                     onClose={() => setSelectedCard(null)}
                     onCardAdded={() => {
+                        recordFeedback(selectedCard);
                         setSelectedCard(null);
                         onCardAdded();
                     }}
@@ -153,7 +153,8 @@ describe('ScannerModal', () => {
 
         await waitFor(() => {
             expect(mockInvoke).toHaveBeenCalledWith('recognize_card_with_features', {
-                imageData: expect.any(String)
+                imageData: expect.any(String),
+                burst: expect.any(Array)
             });
         });
     });
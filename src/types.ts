@@ -36,6 +36,7 @@ export interface ScryfallCard {
     /** Rarity (common, uncommon, rare, mythic) */
     rarity: string;
     similarity?: number; // Hamming distance (lower is better)
+    match_confidence?: number; // Fused hash match confidence (0-1, higher is better)
     /** Type line (e.g., "Creature — Elf Warrior") */
     type_line?: string;
     /** Oracle text (rules text) */
@@ -187,15 +188,47 @@ export interface Tag {
     color: string;
 }
 
+export type ManaColor = 'White' | 'Blue' | 'Black' | 'Red' | 'Green';
+
 /**
  * Visual features detected from a card image.
  */
 export interface CardFeatures {
     border_type: 'Black' | 'White' | 'Silver' | 'Unknown';
-    frame_color: 'Black' | 'Blue' | 'Red' | 'Green' | 'White' | 'Gold' | 'Land' | 'Unknown';
-    frame_style: 'OldFrame' | 'ModernFrame' | 'M15Frame' | 'Unknown';
+    frame_color:
+        | 'Black'
+        | 'Blue'
+        | 'Red'
+        | 'Green'
+        | 'White'
+        | 'Gold'
+        | { TwoColor: [ManaColor, ManaColor] }
+        | { Hybrid: [ManaColor, ManaColor] }
+        | 'Artifact'
+        | 'Colorless'
+        | 'Vehicle'
+        | 'Land'
+        | 'Unknown';
+    frame_style:
+        | 'OldFrame'
+        | 'OldFrame1993'
+        | 'OldFrame1997'
+        | 'ModernFrame'
+        | 'M15Frame'
+        | 'FutureFrame'
+        | 'ShowcaseFrame'
+        | 'BorderlessFrame'
+        | 'Unknown';
     has_corner_dots: boolean;
     is_foil: boolean;
+    set_code?: string | null; // Set matched from the expansion symbol
+    /** How sure each detector is of its reading (0-1), 0 when nothing was detected */
+    confidence: {
+        border: number;
+        frame_color: number;
+        frame_style: number;
+        set_symbol: number;
+    };
 }
 
 /**
@@ -207,4 +240,124 @@ export interface RecognitionResult {
     feature_description: string;
     search_query: string;
     candidates: ScryfallCard[];
+    detected_language?: string | null;
+    image_hashes: string;
+    /** Clockwise degrees the photo was turned to make the card upright */
+    rotation: number;
+    /** Suggested condition, null if the card's outline wasn't found */
+    condition?: ConditionEstimate | null;
+    /** Suggested finish, only when a burst of frames was sent */
+    finish?: FinishEstimate | null;
+    /** Time spent in each recognition stage */
+    timings: StageTimings;
+}
+
+/**
+ * Time spent in each stage of recognizing a photo, in milliseconds.
+ */
+export interface StageTimings {
+    decode_ms: number;
+    prepare_ms: number;
+    features_ms: number;
+    ocr_ms: number;
+    matching_ms: number;
+}
+
+export type CardCondition = 'NM' | 'LP' | 'MP' | 'HP' | 'DMG';
+
+/**
+ * What one condition detector measured on a scanned card.
+ */
+export interface DefectEvidence {
+    defect: 'EdgeWhitening' | 'CornerWear' | 'Crease' | 'Scratches';
+    /** Share of the checked pixels that are damaged, or the number of creases */
+    score: number;
+    /** The best grade this defect allows */
+    grade: CardCondition;
+    detail: string;
+}
+
+/**
+ * Suggested condition of a scanned card and the evidence behind it.
+ */
+export interface ConditionEstimate {
+    grade: CardCondition;
+    evidence: DefectEvidence[];
+}
+
+export type LivePhase = 'searching' | 'steadying' | 'recognizing' | 'locked';
+
+/**
+ * Measurements of the last camera frame of a live scan.
+ */
+export interface FrameStatus {
+    phase: LivePhase;
+    /** Change from the previous frame (0-255), null for the first frame */
+    motion: number | null;
+    /** Variance of the Laplacian, low when the frame is blurry */
+    sharpness: number;
+    still_frames: number;
+}
+
+/**
+ * Events of a live camera scan. A card is recognized once when it is held
+ * still, and its result holds until it leaves the view.
+ */
+export type LiveScanEvent =
+    | { event: 'status'; data: FrameStatus }
+    | { event: 'recognizing' }
+    | { event: 'locked'; data: RecognitionResult }
+    | { event: 'failed'; data: string }
+    | { event: 'cleared' };
+
+export type Finish = 'nonfoil' | 'foil' | 'etched';
+
+/**
+ * Suggested finish of a scanned card, told from how it caught the light across a burst of frames.
+ */
+export interface FinishEstimate {
+    finish: Finish;
+    /** Share of art pixels that shifted color across the frames */
+    art_rainbow: number;
+    /** Share of art pixels that caught the light across the frames */
+    art_shimmer: number;
+    /** Share of frame pixels that caught the light across the frames */
+    frame_shimmer: number;
+    frames: number;
+}
+
+export interface SlotRecognition {
+    row: number;
+    column: number;
+    /** Card outline in photo pixels: top-left, top-right, bottom-right, bottom-left */
+    corners: [number, number][];
+    /** False when no outline was found and the pocket was cut from an even grid */
+    outlined: boolean;
+    result: RecognitionResult | null;
+    error: string | null;
+}
+
+export interface ScanSession {
+    id: string;
+    name: string;
+    status: 'open' | 'committed';
+    created_at: string;
+    updated_at: string;
+    item_count: number;
+    card_count: number;
+}
+
+export interface ScanItem {
+    id: string;
+    session_id: string;
+    image_path: string | null;
+    candidates: ScryfallCard[];
+    selected: ScryfallCard | null;
+    condition: string;
+    language: string;
+    finish: string;
+    purchase_price: number;
+    quantity: number;
+    phash: string | null;
+    created_at: string;
 }
@@ -0,0 +1,28 @@
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { LiveScanEvent } from '../types';
+
+export interface LiveScan {
+    /** Sends one camera frame; resolves once the backend has handled it */
+    pushFrame: (frame: ImageData) => Promise<void>;
+    stop: () => Promise<void>;
+}
+
+// Starts a live camera scan. Frames go to the backend as raw RGBA pixels and
+// results come back over a channel.
+export const startLiveScan = async (onEvent: (event: LiveScanEvent) => void): Promise<LiveScan> => {
+    const channel = new Channel<LiveScanEvent>();
+    channel.onmessage = onEvent;
+    const scanId = await invoke<string>('start_live_scan', { onEvent: channel });
+
+    return {
+        pushFrame: (frame: ImageData) =>
+            invoke<void>('push_live_frame', new Uint8Array(frame.data.buffer), {
+                headers: {
+                    'x-live-scan': scanId,
+                    'x-frame-width': String(frame.width),
+                    'x-frame-height': String(frame.height),
+                },
+            }),
+        stop: () => invoke<void>('stop_live_scan', { scanId }),
+    };
+};